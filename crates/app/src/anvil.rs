@@ -0,0 +1,383 @@
+//! Import of worlds saved by vanilla Minecraft 1.18 and newer in the Anvil
+//! format, which stores chunks as NBT inside of `region/r.x.z.mca` files.
+
+use crate::{
+    chunk::{BiomeId, BlockState, Chunk, ChunkColumn, LightLevel, WorldHeight, EMPTY_BLOCK, SIZE},
+    loaders::{BiomeLoader, BlockStatesLoader},
+    region::{region_position, RegionFile},
+};
+use data::nbt;
+use macroquad::math::{ivec3, IVec2, IVec3};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+    fs::File,
+    io,
+    path::PathBuf,
+};
+
+/// Block every vanilla block without a registered counterpart turns into.
+pub const PLACEHOLDER_BLOCK: &str = "unknown";
+/// Plains, used for biomes maqo doesn't know.
+pub const FALLBACK_BIOME: BiomeId = BiomeId { value: 1 };
+/// Height the biomes of a column are taken from, vanilla stores them in 3D.
+const BIOME_HEIGHT: i32 = 64;
+const AIR_BLOCKS: [&str; 3] = ["minecraft:air", "minecraft:cave_air", "minecraft:void_air"];
+/// Data version of 21w43a, the first snapshot of 1.18 which stores sections
+/// with their block states at the top of a chunk instead of in `Level`.
+const MIN_DATA_VERSION: i32 = 2844;
+
+#[derive(Deserialize)]
+struct LevelDat {
+    #[serde(rename = "Data")]
+    data: VanillaLevel,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct VanillaLevel {
+    spawn_x: i32,
+    spawn_y: i32,
+    spawn_z: i32,
+}
+
+#[derive(Deserialize)]
+struct AnvilColumn {
+    /// Missing in chunks from before 1.9.
+    #[serde(rename = "DataVersion", default)]
+    data_version: i32,
+    #[serde(default)]
+    sections: Vec<AnvilSection>,
+}
+
+#[derive(Deserialize)]
+struct AnvilSection {
+    #[serde(rename = "Y")]
+    y: i8,
+    block_states: Option<PalettedContainer<PaletteEntry>>,
+    biomes: Option<PalettedContainer<String>>,
+    #[serde(rename = "BlockLight")]
+    block_light: Option<Vec<i8>>,
+    #[serde(rename = "SkyLight")]
+    sky_light: Option<Vec<i8>>,
+}
+
+/// Values of a section as indices into a palette, packed into longs.
+#[derive(Deserialize)]
+struct PalettedContainer<T> {
+    palette: Vec<T>,
+    /// Missing if the palette has a single entry.
+    #[serde(default)]
+    data: Vec<i64>,
+}
+
+impl<T> PalettedContainer<T> {
+    /// Unpacks `count` palette indices, each taking at least `min_bits` bits.
+    ///
+    /// Indices don't span two longs, the remaining bits of a long are unused.
+    fn indices(&self, count: usize, min_bits: u32) -> Vec<usize> {
+        if self.palette.len() <= 1 || self.data.is_empty() {
+            return vec![0; count];
+        }
+
+        let bits = (usize::BITS - (self.palette.len() - 1).leading_zeros()).max(min_bits);
+        let per_long = (64 / bits) as usize;
+        let mask = (1u64 << bits) - 1;
+
+        (0..count)
+            .map(|i| {
+                let long = self.data.get(i / per_long).copied().unwrap_or(0) as u64;
+
+                ((long >> ((i % per_long) as u32 * bits)) & mask) as usize
+            })
+            .map(|index| index.min(self.palette.len() - 1))
+            .collect()
+    }
+}
+
+/// Block state in the form vanilla stores in palettes, used by chunks and
+/// structure files.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaletteEntry {
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(
+        rename = "Properties",
+        default,
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub properties: BTreeMap<String, String>,
+}
+
+impl PaletteEntry {
+    pub fn from_state(block_states: &BlockStatesLoader, state: BlockState) -> Self {
+        let id = usize::from(state.value);
+
+        Self {
+            name: block_states
+                .get_name_by_id(id)
+                .unwrap_or(AIR_BLOCKS[0])
+                .to_owned(),
+            properties: block_states
+                .get_by_id(id)
+                .map(|(block, _)| {
+                    block
+                        .properties
+                        .iter()
+                        .map(|(key, property)| (key.clone(), property.value.to_string()))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn is_air(&self) -> bool {
+        AIR_BLOCKS.contains(&self.name.as_str())
+    }
+
+    /// Finds the matching registered state, `None` if the block isn't registered.
+    pub fn to_state(&self, block_states: &BlockStatesLoader) -> Option<BlockState> {
+        if self.is_air() {
+            return Some(EMPTY_BLOCK);
+        }
+
+        let properties = self
+            .properties
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()));
+
+        block_states.find_state(&self.name, properties)
+    }
+}
+
+impl fmt::Display for PaletteEntry {
+    /// Writes the state like `minecraft:lever[face=wall,facing=north,powered=false]`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)?;
+
+        if !self.properties.is_empty() {
+            let properties: Vec<_> = self
+                .properties
+                .iter()
+                .map(|(key, value)| format!("{key}={value}"))
+                .collect();
+
+            write!(f, "[{}]", properties.join(","))?;
+        }
+
+        Ok(())
+    }
+}
+
+fn invalid_data(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+fn decode(data: Vec<u8>) -> io::Result<AnvilColumn> {
+    let (_, root) = nbt::read_root(&data[..]).map_err(invalid_data)?;
+
+    nbt::from_tag(nbt::Tag::Compound(root)).map_err(invalid_data)
+}
+
+/// Reads the nibbles of a light array, which is missing where vanilla
+/// didn't compute light.
+fn light_nibble(light: Option<&[i8]>, index: usize, default: u8) -> u8 {
+    match light.and_then(|light| light.get(index / 2)) {
+        Some(&byte) => (byte as u8 >> (index % 2 * 4)) & 0xf,
+        None => default,
+    }
+}
+
+/// A vanilla world directory, read only.
+pub struct AnvilWorld {
+    directory: PathBuf,
+    regions: HashMap<IVec2, Option<RegionFile>>,
+    /// Names of unknown blocks which were already reported.
+    unknown_blocks: HashSet<String>,
+    /// Unsupported data versions which were already reported.
+    unsupported_versions: HashSet<i32>,
+}
+
+impl AnvilWorld {
+    pub fn open(directory: impl Into<PathBuf>) -> io::Result<Self> {
+        let directory = directory.into();
+
+        if !directory.join("region").is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} has no region directory", directory.display()),
+            ));
+        }
+
+        Ok(Self {
+            directory,
+            regions: HashMap::new(),
+            unknown_blocks: HashSet::new(),
+            unsupported_versions: HashSet::new(),
+        })
+    }
+
+    /// World spawn stored in `level.dat`.
+    pub fn spawn(&self) -> io::Result<IVec3> {
+        let file = File::open(self.directory.join("level.dat"))?;
+        let level: LevelDat = nbt::from_reader(file).map_err(invalid_data)?;
+
+        Ok(ivec3(
+            level.data.spawn_x,
+            level.data.spawn_y,
+            level.data.spawn_z,
+        ))
+    }
+
+    fn read(&mut self, position: IVec2) -> io::Result<Option<Vec<u8>>> {
+        let (region, index) = region_position(position);
+
+        if !self.regions.contains_key(&region) {
+            let path = self
+                .directory
+                .join("region")
+                .join(format!("r.{}.{}.mca", region.x, region.y));
+
+            // Regions nobody went to don't exist.
+            let file = if path.is_file() {
+                Some(RegionFile::open_read(path)?)
+            } else {
+                None
+            };
+
+            self.regions.insert(region, file);
+        }
+
+        match self.regions.get_mut(&region).unwrap() {
+            Some(region) => region.read(index),
+            None => Ok(None),
+        }
+    }
+
+    /// Converts a column, `None` if vanilla never generated it or it can't be
+    /// imported, which is reported.
+    ///
+    /// Only sections inside of `height` are imported.
+    pub fn load_column(
+        &mut self,
+        position: IVec2,
+        height: WorldHeight,
+        block_states: &BlockStatesLoader,
+        biomes: &BiomeLoader,
+    ) -> io::Result<Option<ChunkColumn>> {
+        // Damaged columns and ones stored in external files are skipped
+        // instead of stopping the whole import.
+        let column = match self
+            .read(position)
+            .and_then(|data| data.map(decode).transpose())
+        {
+            Ok(Some(column)) => column,
+            Ok(None) => return Ok(None),
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::InvalidData | io::ErrorKind::Unsupported
+                ) =>
+            {
+                eprintln!("skipping column {position}: {err}");
+
+                return Ok(None);
+            }
+            Err(err) => return Err(err),
+        };
+
+        if column.data_version < MIN_DATA_VERSION {
+            if self.unsupported_versions.insert(column.data_version) {
+                eprintln!(
+                    "columns of data version {} are older than 1.18 and can't be imported, skipping them",
+                    column.data_version
+                );
+            }
+
+            return Ok(None);
+        }
+
+        let placeholder = block_states
+            .get_default_state(PLACEHOLDER_BLOCK)
+            .unwrap_or(EMPTY_BLOCK);
+
+        let mut imported = ChunkColumn::new(height, [[FALLBACK_BIOME; SIZE]; SIZE]);
+
+        for section in column.sections.iter() {
+            if let Some(container) = section
+                .biomes
+                .as_ref()
+                .filter(|_| i32::from(section.y) == BIOME_HEIGHT / SIZE as i32)
+            {
+                let palette: Vec<_> = container
+                    .palette
+                    .iter()
+                    .map(|name| biomes.get_id_by_name(name).unwrap_or(FALLBACK_BIOME))
+                    .collect();
+                let indices = container.indices(64, 0);
+                let layer = (BIOME_HEIGHT as usize % SIZE) / 4;
+
+                for (z, row) in imported.biomes.iter_mut().enumerate() {
+                    for (x, biome) in row.iter_mut().enumerate() {
+                        *biome = palette[indices[(layer * 4 + z / 4) * 4 + x / 4]];
+                    }
+                }
+            }
+
+            let Some(index) = imported.section_index(section.y.into()) else {
+                continue;
+            };
+            let Some(container) = &section.block_states else {
+                continue;
+            };
+
+            if container.palette.iter().all(PaletteEntry::is_air) {
+                continue;
+            }
+
+            let palette: Vec<_> = container
+                .palette
+                .iter()
+                .map(|entry| self.map_state(entry, block_states, placeholder))
+                .collect();
+            let indices = container.indices(SIZE * SIZE * SIZE, 4);
+
+            let block_light = section.block_light.as_deref();
+            let sky_light = section.sky_light.as_deref();
+
+            let chunk = imported.chunks[index].insert(Box::new(Chunk {
+                blocks: [[[EMPTY_BLOCK; SIZE]; SIZE]; SIZE],
+                light_levels: [[[LightLevel { value: 0xf0 }; SIZE]; SIZE]; SIZE],
+            }));
+
+            for (i, &index) in indices.iter().enumerate() {
+                let (x, z, y) = (i % SIZE, i / SIZE % SIZE, i / (SIZE * SIZE));
+
+                chunk.blocks[y][z][x] = palette[index];
+                chunk.light_levels[y][z][x] = LightLevel {
+                    value: light_nibble(block_light, i, 0) | light_nibble(sky_light, i, 0xf) << 4,
+                };
+            }
+        }
+
+        Ok(Some(imported))
+    }
+
+    /// Looks up the registered state matching a vanilla one, reporting
+    /// unknown blocks once.
+    fn map_state(
+        &mut self,
+        entry: &PaletteEntry,
+        block_states: &BlockStatesLoader,
+        placeholder: BlockState,
+    ) -> BlockState {
+        entry.to_state(block_states).unwrap_or_else(|| {
+            if self.unknown_blocks.insert(entry.name.clone()) {
+                eprintln!("unknown block {entry}, using a placeholder");
+            }
+
+            placeholder
+        })
+    }
+}
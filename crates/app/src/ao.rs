@@ -0,0 +1,308 @@
+//! Smooth lighting and ambient occlusion of faces, like vanilla: every
+//! corner of a face is lit by the four blocks in front of it that share the
+//! corner, and the ones with a full shape darken it.
+
+use data::model::{Axis, Direction};
+use macroquad::math::{IVec3, Vec3};
+
+/// Brightness a block with a full shape leaves the corners it touches.
+pub const OCCLUDED_BRIGHTNESS: f32 = 0.2;
+
+/// A block around a face, as far as its lighting is concerned.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct AoSample {
+    /// Whether the block has a full shape, those cast ambient occlusion.
+    pub occludes: bool,
+    pub block_light: u8,
+    pub sky_light: u8,
+}
+
+impl AoSample {
+    fn brightness(self) -> f32 {
+        if self.occludes {
+            OCCLUDED_BRIGHTNESS
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Lighting of a point on a face, the light levels go from 0 to 15.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct VertexLight {
+    /// Share of the light the blocks around let through.
+    pub brightness: f32,
+    pub block_light: f32,
+    pub sky_light: f32,
+}
+
+impl VertexLight {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+
+        Self {
+            brightness: lerp(self.brightness, other.brightness),
+            block_light: lerp(self.block_light, other.block_light),
+            sky_light: lerp(self.sky_light, other.sky_light),
+        }
+    }
+}
+
+/// Averages the blocks around a corner, `center` is the one right in front
+/// of the face. Dark blocks take the light of the center like in vanilla,
+/// otherwise the walls next to a lit face would make its corners black.
+fn blend(center: AoSample, others: [AoSample; 3]) -> VertexLight {
+    let mut light = VertexLight {
+        brightness: center.brightness(),
+        block_light: center.block_light as f32,
+        sky_light: center.sky_light as f32,
+    };
+
+    for sample in others {
+        let sample = if sample.block_light == 0 && sample.sky_light == 0 {
+            AoSample {
+                occludes: sample.occludes,
+                ..center
+            }
+        } else {
+            sample
+        };
+
+        light.brightness += sample.brightness();
+        light.block_light += sample.block_light as f32;
+        light.sky_light += sample.sky_light as f32;
+    }
+
+    VertexLight {
+        brightness: light.brightness / 4.0,
+        block_light: light.block_light / 4.0,
+        sky_light: light.sky_light / 4.0,
+    }
+}
+
+/// Axes along the sides of a face looking in `direction`.
+fn face_axes(direction: Direction) -> [IVec3; 2] {
+    match direction.get_axis() {
+        Axis::X => [IVec3::Y, IVec3::Z],
+        Axis::Y => [IVec3::X, IVec3::Z],
+        Axis::Z => [IVec3::X, IVec3::Y],
+    }
+}
+
+/// Whether the face looking in `direction` lies on the side of its block,
+/// the block positions are centered.
+pub fn is_flush(direction: Direction, positions: [Vec3; 4]) -> bool {
+    let normal = direction.as_vec3().as_vec3();
+
+    positions
+        .iter()
+        .all(|position| (position.dot(normal) - 0.5).abs() < 1e-4)
+}
+
+/// Whether a quad with these brightnesses at its corners should be split
+/// along the diagonal from its second to its fourth corner. Splitting it
+/// along the brighter diagonal keeps the shadow of a single dark corner
+/// from stretching over the whole quad.
+pub fn flip_diagonal(brightness: [f32; 4]) -> bool {
+    brightness[0] + brightness[2] < brightness[1] + brightness[3]
+}
+
+/// Brightness of faces looking in `direction`, vanilla's fixed shading
+/// which sets the sides of blocks apart.
+pub fn shade(direction: Direction) -> f32 {
+    match direction {
+        Direction::Top => 1.0,
+        Direction::Front | Direction::Back => 0.8,
+        Direction::Right | Direction::Left => 0.6,
+        Direction::Bottom => 0.5,
+    }
+}
+
+/// Lighting of the corners of a face.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FaceLight {
+    direction: Direction,
+    /// Indexed by whether the corner is on the positive side of the first
+    /// and the second axis of the face.
+    corners: [[VertexLight; 2]; 2],
+}
+
+impl FaceLight {
+    /// Lights the face looking in `direction` from the blocks around it,
+    /// `sample` gets them relative to the block of the face. Faces on the
+    /// side of their block are lit by the blocks in front of it, others by
+    /// the ones around their own block.
+    pub fn smooth(direction: Direction, flush: bool, sample: impl Fn(IVec3) -> AoSample) -> Self {
+        let plane = if flush {
+            direction.as_vec3()
+        } else {
+            IVec3::ZERO
+        };
+        let [first_axis, second_axis] = face_axes(direction);
+        let center = sample(plane);
+
+        let corners = [-1, 1].map(|first| {
+            [-1, 1].map(|second| {
+                let (first, second) = (first_axis * first, second_axis * second);
+                let side1 = sample(plane + first);
+                let side2 = sample(plane + second);
+                // Light can't get around two sides with a full shape.
+                let corner = if side1.occludes && side2.occludes {
+                    side1
+                } else {
+                    sample(plane + first + second)
+                };
+
+                blend(center, [side1, side2, corner])
+            })
+        });
+
+        Self { direction, corners }
+    }
+
+    /// Lights the whole face like `sample`, for models without ambient
+    /// occlusion.
+    pub fn flat(sample: AoSample) -> Self {
+        let light = VertexLight {
+            brightness: 1.0,
+            block_light: sample.block_light as f32,
+            sky_light: sample.sky_light as f32,
+        };
+
+        Self {
+            // Any will do, the corners are the same.
+            direction: Direction::Top,
+            corners: [[light; 2]; 2],
+        }
+    }
+
+    /// Lighting at `position` relative to the center of the block, faces
+    /// smaller than the block blend the corners by how close they are.
+    pub fn at(&self, position: Vec3) -> VertexLight {
+        let [first, second] = face_axes(self.direction)
+            .map(|axis| (position.dot(axis.as_vec3()) + 0.5).clamp(0.0, 1.0));
+        let [[a, b], [c, d]] = self.corners;
+
+        a.lerp(b, second).lerp(c.lerp(d, second), first)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use macroquad::math::ivec3;
+
+    const LIT: AoSample = AoSample {
+        occludes: false,
+        block_light: 0,
+        sky_light: 15,
+    };
+
+    const WALL: AoSample = AoSample {
+        occludes: true,
+        ..LIT
+    };
+
+    /// Lights the top face of a full block, the corner at -x and -z comes
+    /// first.
+    fn top_face(sample: impl Fn(IVec3) -> AoSample) -> FaceLight {
+        FaceLight::smooth(Direction::Top, true, sample)
+    }
+
+    fn light(brightness: f32, block_light: f32, sky_light: f32) -> VertexLight {
+        VertexLight {
+            brightness,
+            block_light,
+            sky_light,
+        }
+    }
+
+    #[test]
+    fn corner_between_two_walls_takes_the_first_one() {
+        let face = top_face(|position| match position {
+            IVec3 { x: -1, y: 1, z: 0 } => AoSample {
+                block_light: 4,
+                ..WALL
+            },
+            IVec3 { x: 0, y: 1, z: -1 } => AoSample {
+                block_light: 8,
+                ..WALL
+            },
+            IVec3 { x: -1, y: 1, z: -1 } => AoSample {
+                block_light: 15,
+                ..LIT
+            },
+            _ => AoSample {
+                block_light: 12,
+                ..LIT
+            },
+        });
+
+        assert_eq!(
+            face.corners[0][0],
+            light((1.0 + 0.2 + 0.2 + 0.2) / 4.0, 7.0, 15.0)
+        );
+    }
+
+    #[test]
+    fn single_occluding_corner() {
+        let face = top_face(|position| {
+            if position == ivec3(-1, 1, -1) {
+                WALL
+            } else {
+                LIT
+            }
+        });
+
+        assert_eq!(face.corners[0][0], light((3.0 + 0.2) / 4.0, 0.0, 15.0));
+        assert_eq!(face.corners[1][1], light(1.0, 0.0, 15.0));
+    }
+
+    #[test]
+    fn dark_samples_take_the_light_of_the_center() {
+        let face = top_face(|position| {
+            if position.x == -1 {
+                AoSample {
+                    occludes: false,
+                    block_light: 0,
+                    sky_light: 0,
+                }
+            } else {
+                AoSample {
+                    block_light: 6,
+                    ..LIT
+                }
+            }
+        });
+
+        assert_eq!(face.corners[0][0], light(1.0, 6.0, 15.0));
+        assert_eq!(face.corners[1][0], light(1.0, 6.0, 15.0));
+    }
+
+    #[test]
+    fn quads_are_split_along_the_brighter_diagonal() {
+        assert!(flip_diagonal([0.8, 1.0, 1.0, 1.0]));
+        assert!(!flip_diagonal([1.0, 0.8, 1.0, 1.0]));
+        assert!(!flip_diagonal([1.0; 4]));
+    }
+
+    #[test]
+    fn partial_faces_blend_the_corners() {
+        let corner = |block_light| light(1.0, block_light, 15.0);
+        let face = FaceLight {
+            direction: Direction::Top,
+            corners: [[corner(0.0), corner(4.0)], [corner(8.0), corner(12.0)]],
+        };
+        // The top of a slab-like box, half as wide and deep as the block.
+        let positions = [
+            Vec3::new(-0.25, 0.0, -0.25),
+            Vec3::new(-0.25, 0.0, 0.25),
+            Vec3::new(0.25, 0.0, 0.25),
+            Vec3::new(0.25, 0.0, -0.25),
+        ];
+
+        assert!(!is_flush(Direction::Top, positions));
+        assert_eq!(face.at(positions[3]), corner(7.0));
+        assert_eq!(face.at(Vec3::ZERO), corner(6.0));
+    }
+}
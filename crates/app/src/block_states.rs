@@ -1,21 +1,62 @@
-use crate::Minecraft;
-use data::block_states::{Property, PropertyValue};
-use macroquad::color::Color;
-use maqo_macros::Block;
-use std::collections::HashMap;
+use crate::{
+    anvil::PLACEHOLDER_BLOCK,
+    chunk::BlockState,
+    fluid::{FluidKind, FluidState, LavaBlock, WaterBlock},
+    loaders::BlockStatesLoader,
+    plant::{FlowerBlock, LeavesBlock, LogBlock, ShortGrassBlock},
+    redstone::{LeverBlock, RedstoneWireBlock, WireConnection, HORIZONTAL, MAX_POWER},
+    world::World,
+    Minecraft,
+};
+use data::{
+    block_states::{Property, PropertyValue},
+    model::Direction,
+};
+use macroquad::{
+    color::Color,
+    math::{ivec3, IVec3},
+    rand,
+};
+use maqo_macros::BlockProperties;
+use std::{collections::HashMap, fmt};
 
 pub trait GetProperty<T> {
     fn get_property(&self, name: &str) -> Option<&T>;
 }
 
-#[derive(Debug)]
+/// Color multiplied into faces with a `tintindex`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Tint {
+    Color(Color),
+    /// Grass color of the biome the face is in.
+    Grass,
+    /// Foliage color of the biome the face is in.
+    Foliage,
+    /// Water color of the biome the face is in.
+    Water,
+}
+
 pub struct BaseBlock {
     pub properties: HashMap<String, Property>,
     pub is_full_block: bool,
     pub is_translucent: bool,
     pub is_full_cube: bool,
     pub is_opaque_cube: bool,
-    pub tints: Vec<Color>,
+    pub tints: Vec<Tint>,
+    pub block: Box<dyn Block>,
+}
+
+impl fmt::Debug for BaseBlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BaseBlock")
+            .field("properties", &self.properties)
+            .field("is_full_block", &self.is_full_block)
+            .field("is_translucent", &self.is_translucent)
+            .field("is_full_cube", &self.is_full_cube)
+            .field("is_opaque_cube", &self.is_opaque_cube)
+            .field("tints", &self.tints)
+            .finish_non_exhaustive()
+    }
 }
 
 impl GetProperty<bool> for BaseBlock {
@@ -51,7 +92,7 @@ impl GetProperty<String> for BaseBlock {
     }
 }
 
-pub trait Block {
+pub trait BlockProperties {
     fn properties(&self) -> HashMap<String, Property>;
     fn is_full_block(&self) -> bool;
 
@@ -65,12 +106,117 @@ pub trait Block {
 
     fn is_translucent(&self) -> bool;
 
-    fn tints(&self) -> Vec<Color> {
+    fn tints(&self) -> Vec<Tint> {
         Vec::new()
     }
 }
 
-impl<T: Block> From<T> for BaseBlock {
+/// Behavior of a block in the world, on top of its static [`BlockProperties`].
+pub trait Block: BlockProperties {
+    /// Whether [`Block::on_random_tick`] should be called for this block.
+    fn has_random_ticks(&self) -> bool {
+        false
+    }
+
+    /// Called when a tick scheduled with [`World::schedule_tick`] is due.
+    fn on_scheduled_tick(&self, _world: &mut World, _position: IVec3) {}
+
+    /// Called when the block gets picked by the random tick of its section.
+    fn on_random_tick(&self, _world: &mut World, _position: IVec3) {}
+
+    /// Called after any of the six neighbors at `neighbor` was replaced.
+    fn on_neighbor_changed(&self, _world: &mut World, _position: IVec3, _neighbor: IVec3) {}
+
+    /// Called after the block was put into the world.
+    fn on_place(&self, _world: &mut World, _position: IVec3) {}
+
+    /// Called when the player interacts with the block, returns whether the
+    /// interaction did something.
+    fn on_use(&self, _world: &mut World, _position: IVec3, _state: BlockState) -> bool {
+        false
+    }
+
+    /// Whether the block emits redstone power on its own.
+    fn is_signal_source(&self) -> bool {
+        false
+    }
+
+    /// Weak power sent into the neighbor in `direction`, it powers redstone
+    /// components but isn't conducted any further.
+    fn power_output(&self, _world: &World, _position: IVec3, _direction: Direction) -> u8 {
+        0
+    }
+
+    /// Strong power sent into the neighbor in `direction`, which a solid
+    /// neighbor conducts to the blocks around it.
+    fn strong_power_output(&self, _world: &World, _position: IVec3, _direction: Direction) -> u8 {
+        0
+    }
+
+    /// Returns the state this block, currently `state`, should turn into after
+    /// the neighbor in `direction` became `neighbor`, or `None` to keep it.
+    fn update_shape(
+        &self,
+        _world: &mut World,
+        _position: IVec3,
+        _state: BlockState,
+        _direction: Direction,
+        _neighbor: BlockState,
+    ) -> Option<BlockState> {
+        None
+    }
+
+    /// Whether the face of `state` towards `direction` is hidden by
+    /// `neighbor`, even though the neighbor isn't opaque.
+    ///
+    /// Fluids aren't drawn from block models, [`get_fluid_faces`](crate::fluid::get_fluid_faces)
+    /// already hides their faces towards the same fluid.
+    fn skip_rendering(
+        &self,
+        _block_states: &BlockStatesLoader,
+        _state: BlockState,
+        _neighbor: BlockState,
+        _direction: Direction,
+    ) -> bool {
+        false
+    }
+
+    /// Layer the faces of the block are drawn in.
+    fn render_layer(&self) -> BlockRenderLayer {
+        BlockRenderLayer::Solid
+    }
+
+    /// Fluid contained in the block, such blocks are drawn by
+    /// [`get_fluid_faces`](crate::fluid::get_fluid_faces) instead of their model.
+    fn fluid(&self) -> Option<FluidState> {
+        None
+    }
+}
+
+fn is_snow(world: &World, state: BlockState) -> bool {
+    matches!(
+        world.block_states.get_name_by_id(state.value.into()),
+        Some("minecraft:snow" | "minecraft:snow_block")
+    )
+}
+
+/// Updates `snowy` of grass-like blocks when the block above them changes.
+/// Nothing changes if the other state isn't registered.
+fn update_snowy(
+    world: &World,
+    state: BlockState,
+    direction: Direction,
+    neighbor: BlockState,
+    snowy: bool,
+) -> Option<BlockState> {
+    if direction != Direction::Top || is_snow(world, neighbor) == snowy {
+        return None;
+    }
+
+    world.block_states.with_property(state, "snowy", !snowy)
+}
+
+impl<T: Block + 'static> From<T> for BaseBlock {
     fn from(value: T) -> Self {
         Self {
             properties: value.properties(),
@@ -79,37 +225,415 @@ impl<T: Block> From<T> for BaseBlock {
             is_full_cube: value.is_full_cube(),
             is_opaque_cube: value.is_opaque_cube(),
             tints: value.tints(),
+            block: Box::new(value),
         }
     }
 }
 
-#[derive(Default, Block)]
+#[derive(Default, BlockProperties)]
 #[block(full_block = true)]
-#[tint(0x6D9930)]
+#[tint(Tint::Grass)]
 pub struct GrassBlock {
     pub snowy: bool,
 }
 
-#[derive(Default, Block)]
+impl GrassBlock {
+    /// Grass can't stay under a block which covers it.
+    fn can_survive(world: &World, position: IVec3) -> bool {
+        !world
+            .get_base_block(position + ivec3(0, 1, 0))
+            .is_some_and(|block| block.is_opaque_cube)
+    }
+}
+
+impl Block for GrassBlock {
+    fn has_random_ticks(&self) -> bool {
+        true
+    }
+
+    fn on_random_tick(&self, world: &mut World, position: IVec3) {
+        let (Some(dirt), Some(grass)) = (
+            world.block_states.get_default_state("dirt"),
+            world.block_states.get_default_state("grass_block"),
+        ) else {
+            return;
+        };
+
+        if !Self::can_survive(world, position) {
+            world.set_block(position, dirt);
+
+            return;
+        }
+
+        // Like vanilla, grass only spreads where it's bright enough.
+        if world.get_brightness(position + ivec3(0, 1, 0)) < 9 {
+            return;
+        }
+
+        for _ in 0..4 {
+            // Negative ranges would round towards 0, so they are shifted.
+            let target = position
+                + ivec3(
+                    rand::gen_range(0, 3) - 1,
+                    rand::gen_range(0, 5) - 3,
+                    rand::gen_range(0, 3) - 1,
+                );
+
+            if world.get_name(target) == Some("minecraft:dirt") && Self::can_survive(world, target)
+            {
+                let snowy = world
+                    .get_block(target + ivec3(0, 1, 0))
+                    .is_some_and(|above| is_snow(world, above));
+
+                world.set_block(
+                    target,
+                    world
+                        .block_states
+                        .with_property(grass, "snowy", snowy)
+                        .unwrap_or(grass),
+                );
+            }
+        }
+    }
+
+    fn update_shape(
+        &self,
+        world: &mut World,
+        _position: IVec3,
+        state: BlockState,
+        direction: Direction,
+        neighbor: BlockState,
+    ) -> Option<BlockState> {
+        update_snowy(world, state, direction, neighbor, self.snowy)
+    }
+}
+
+#[derive(Default, BlockProperties)]
 #[block(full_block = true)]
 pub struct DirtBlock {
     pub snowy: bool,
 }
 
-#[derive(Block)]
+impl Block for DirtBlock {
+    fn update_shape(
+        &self,
+        world: &mut World,
+        _position: IVec3,
+        state: BlockState,
+        direction: Direction,
+        neighbor: BlockState,
+    ) -> Option<BlockState> {
+        update_snowy(world, state, direction, neighbor, self.snowy)
+    }
+}
+
+#[derive(BlockProperties)]
 #[block(full_cube = false, opaque_cube = false)]
-struct AirBlock;
+pub struct AirBlock;
+
+impl Block for AirBlock {}
+
+/// Stands in for blocks of imported worlds which aren't registered.
+#[derive(BlockProperties)]
+#[block(full_block = true)]
+struct UnknownBlock;
+
+impl Block for UnknownBlock {}
+
+#[derive(BlockProperties)]
+#[block(full_block = true, opaque_cube = false)]
+struct GlassBlock;
+
+impl Block for GlassBlock {
+    /// Glass next to the same glass looks like one pane, like vanilla's
+    /// half transparent blocks.
+    fn skip_rendering(
+        &self,
+        block_states: &BlockStatesLoader,
+        state: BlockState,
+        neighbor: BlockState,
+        _direction: Direction,
+    ) -> bool {
+        block_states.is_same_block(state, neighbor)
+    }
+
+    fn render_layer(&self) -> BlockRenderLayer {
+        BlockRenderLayer::Cutout
+    }
+}
+
+#[derive(BlockProperties)]
+#[block(full_block = true, opaque_cube = false)]
+struct StainedGlassBlock;
 
+impl Block for StainedGlassBlock {
+    fn skip_rendering(
+        &self,
+        block_states: &BlockStatesLoader,
+        state: BlockState,
+        neighbor: BlockState,
+        _direction: Direction,
+    ) -> bool {
+        block_states.is_same_block(state, neighbor)
+    }
+
+    fn render_layer(&self) -> BlockRenderLayer {
+        BlockRenderLayer::Translucent
+    }
+}
+
+/// How the faces of a block are drawn.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum BlockRenderLayer {
     Solid,
+    /// Cutout textures far away get blurry, but keep their holes.
     CutoutMipped,
+    /// Cutout textures with no mipmaps, for thin textures like flowers which
+    /// would disappear in the distance.
+    Cutout,
     Translucent,
 }
 
+impl BlockRenderLayer {
+    pub fn is_mipmapped(self) -> bool {
+        self != Self::Cutout
+    }
+}
+
 impl Minecraft {
     pub fn init(&mut self) {
         self.register_block(0, "air", AirBlock);
         self.register_block(1, "dirt", DirtBlock::default());
         self.register_block(2, "grass_block", GrassBlock::default());
+
+        let mut id = 3;
+
+        for face in ["floor", "wall", "ceiling"] {
+            for (_, facing) in HORIZONTAL {
+                for powered in [false, true] {
+                    self.register_block(
+                        id,
+                        "lever",
+                        LeverBlock {
+                            face: face.into(),
+                            facing: facing.into(),
+                            powered,
+                        },
+                    );
+
+                    id += 1;
+                }
+            }
+        }
+
+        let connections = [
+            WireConnection::None,
+            WireConnection::Side,
+            WireConnection::Up,
+        ];
+
+        for power in 0..=i64::from(MAX_POWER) {
+            for north in connections {
+                for east in connections {
+                    for south in connections {
+                        for west in connections {
+                            self.register_block(
+                                id,
+                                "redstone_wire",
+                                RedstoneWireBlock {
+                                    power,
+                                    north,
+                                    east,
+                                    south,
+                                    west,
+                                },
+                            );
+
+                            id += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        for kind in [FluidKind::Water, FluidKind::Lava] {
+            for texture in [kind.still_texture(), kind.flow_texture()] {
+                if !self.textures.contains(texture) {
+                    self.textures.load(texture);
+                }
+            }
+        }
+
+        for level in 0..16 {
+            self.register_block(id, "water", WaterBlock { level });
+            self.register_block(id + 1, "lava", LavaBlock { level });
+
+            id += 2;
+        }
+
+        for axis in ["x", "y", "z"] {
+            self.register_block(
+                id,
+                "oak_log",
+                LogBlock {
+                    axis: axis.to_owned(),
+                },
+            );
+
+            id += 1;
+        }
+
+        self.register_block(id, "oak_leaves", LeavesBlock);
+        self.register_block(id + 1, "short_grass", ShortGrassBlock);
+        self.register_block(id + 2, "dandelion", FlowerBlock);
+        self.register_block(id + 3, "poppy", FlowerBlock);
+        self.register_block(id + 4, PLACEHOLDER_BLOCK, UnknownBlock);
+        self.register_block(id + 5, "glass", GlassBlock);
+        self.register_block(id + 6, "black_stained_glass", StainedGlassBlock);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        chunk::{BiomeId, ChunkColumn, ChunkManager, WorldHeight, EMPTY_BLOCK, SIZE},
+        loaders::Model,
+    };
+    use macroquad::math::ivec2;
+
+    fn block_states() -> BlockStatesLoader {
+        let mut block_states = BlockStatesLoader::default();
+
+        block_states.register_block_with_model(0, "air", AirBlock, Model::empty());
+        block_states.register_block_with_model(1, "dirt", DirtBlock::default(), Model::empty());
+        for snowy in [false, true] {
+            block_states.register_block_with_model(
+                2 + usize::from(snowy),
+                "grass_block",
+                GrassBlock { snowy },
+                Model::empty(),
+            );
+        }
+
+        block_states.register_block_with_model(4, "snow_block", UnknownBlock, Model::empty());
+
+        block_states
+    }
+
+    /// A single column with a floor of dirt at a height of 0 and grass in
+    /// the middle of it.
+    fn chunks(block_states: &BlockStatesLoader) -> ChunkManager {
+        let mut chunks = ChunkManager::new();
+        let dirt = block_states.get_default_state("dirt").unwrap();
+        let grass = block_states.get_default_state("grass_block").unwrap();
+
+        chunks.add_chunk_column(
+            ivec2(0, 0),
+            ChunkColumn::new(WorldHeight::default(), [[BiomeId { value: 0 }; SIZE]; SIZE]),
+        );
+
+        for z in 0..SIZE as i32 {
+            for x in 0..SIZE as i32 {
+                chunks.set_block(ivec3(x, 0, z), dirt, block_states);
+            }
+        }
+
+        chunks.set_block(ivec3(8, 0, 8), grass, block_states);
+
+        chunks
+    }
+
+    fn random_ticks(world: &mut World, position: IVec3, count: usize) {
+        for _ in 0..count {
+            let block = world.get_base_block(position).unwrap();
+
+            block.block.on_random_tick(world, position);
+        }
+    }
+
+    #[test]
+    fn grass_spreads_in_every_direction() {
+        let block_states = block_states();
+        let mut chunks = chunks(&block_states);
+        chunks.clock.set_day_time(6000);
+        let mut world = World::new(&block_states, &mut chunks);
+
+        rand::srand(1);
+        random_ticks(&mut world, ivec3(8, 0, 8), 200);
+
+        for offset in [
+            ivec3(-1, 0, 0),
+            ivec3(1, 0, 0),
+            ivec3(0, 0, -1),
+            ivec3(0, 0, 1),
+        ] {
+            assert_eq!(
+                world.get_name(ivec3(8, 0, 8) + offset),
+                Some("minecraft:grass_block")
+            );
+        }
+    }
+
+    #[test]
+    fn grass_does_not_spread_in_the_dark() {
+        let block_states = block_states();
+        let mut chunks = chunks(&block_states);
+        chunks.clock.set_day_time(18000);
+        let mut world = World::new(&block_states, &mut chunks);
+
+        assert_eq!(world.get_brightness(ivec3(8, 1, 8)), 4);
+
+        rand::srand(1);
+        random_ticks(&mut world, ivec3(8, 0, 8), 200);
+
+        for offset in [
+            ivec3(-1, 0, 0),
+            ivec3(1, 0, 0),
+            ivec3(0, 0, -1),
+            ivec3(0, 0, 1),
+        ] {
+            assert_eq!(
+                world.get_name(ivec3(8, 0, 8) + offset),
+                Some("minecraft:dirt")
+            );
+        }
+    }
+
+    fn is_snowy(world: &World, position: IVec3) -> bool {
+        let block = world.get_base_block(position).unwrap();
+
+        *GetProperty::<bool>::get_property(block, "snowy").unwrap()
+    }
+
+    #[test]
+    fn snow_above_makes_grass_snowy() {
+        let block_states = block_states();
+        let mut chunks = chunks(&block_states);
+        let mut world = World::new(&block_states, &mut chunks);
+        let snow = block_states.get_default_state("snow_block").unwrap();
+        let (grass, above) = (ivec3(8, 0, 8), ivec3(8, 1, 8));
+
+        world.set_block(above, snow);
+
+        assert!(is_snowy(&world, grass));
+
+        world.set_block(above, EMPTY_BLOCK);
+
+        assert!(!is_snowy(&world, grass));
+    }
+
+    #[test]
+    fn unregistered_snowy_states_are_skipped() {
+        let block_states = block_states();
+        let mut chunks = chunks(&block_states);
+        let mut world = World::new(&block_states, &mut chunks);
+        let snow = block_states.get_default_state("snow_block").unwrap();
+
+        // Only dirt which isn't snowy is registered.
+        world.set_block(ivec3(2, 1, 2), snow);
+
+        assert_eq!(world.get_name(ivec3(2, 0, 2)), Some("minecraft:dirt"));
+        assert!(!is_snowy(&world, ivec3(2, 0, 2)));
     }
 }
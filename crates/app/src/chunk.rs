@@ -1,8 +1,18 @@
-use macroquad::{
-    math::{ivec2, ivec3, IVec2, IVec3, Vec3},
-    models::Mesh,
+use crate::{
+    block_states::BaseBlock,
+    heightmap::{Heightmap, HeightmapKind, Heightmaps},
+    loaders::BlockStatesLoader,
+    mesh::SectionMesh,
+    tick::TickScheduler,
+    time::WorldClock,
+    visibility::SectionVisibility,
+};
+use macroquad::math::{ivec2, ivec3, IVec2, IVec3};
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
 };
-use std::{cell::RefCell, collections::HashMap};
 // use crate::Model;
 
 #[derive(Copy, Clone)]
@@ -45,15 +55,315 @@ pub const EMPTY_CHUNK: &Chunk = &Chunk {
     light_levels: [[[LightLevel { value: 0xf0 }; SIZE]; SIZE]; SIZE],
 };
 
+/// Vertical extent of the world in blocks, both values are multiples of
+/// the section size.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorldHeight {
+    pub min_y: i32,
+    pub height: i32,
+}
+
+impl Default for WorldHeight {
+    /// Height of the vanilla overworld, from -64 up to 320.
+    fn default() -> Self {
+        Self {
+            min_y: -64,
+            height: 384,
+        }
+    }
+}
+
+impl WorldHeight {
+    /// First height above the world.
+    pub fn max_y(self) -> i32 {
+        self.min_y + self.height
+    }
+
+    pub fn min_section(self) -> i32 {
+        self.min_y.div_euclid(SIZE as i32)
+    }
+
+    pub fn section_count(self) -> usize {
+        (self.height / SIZE as i32).max(0) as usize
+    }
+
+    pub fn contains(self, y: i32) -> bool {
+        (self.min_y..self.max_y()).contains(&y)
+    }
+}
+
 pub struct ChunkColumn {
-    pub chunks: Vec<Chunk>,
+    /// Sections from the bottom of the world up, `None` as long as they
+    /// only contain air.
+    pub chunks: Vec<Option<Box<Chunk>>>,
+    /// Section height of the first entry of `chunks`.
+    pub min_section: i32,
     pub biomes: [[BiomeId; SIZE]; SIZE],
-    pub buffers: [RefCell<Vec<(Vec3, Mesh)>>; SIZE],
+    pub buffers: Vec<RefCell<SectionMesh>>,
+    /// Faces of each section which see each other, computed along with the meshes.
+    pub visibility: Vec<SectionVisibility>,
+    /// Kept up to date by [`ChunkManager::set_block`], changes made to the
+    /// column directly need [`ChunkColumn::compute_heightmaps`].
+    pub heightmaps: Heightmaps,
+}
+
+/// Lights the blocks of a section at the height `section` which are above
+/// `heightmap` with the sky, and darkens the ones below it.
+fn seed_section_sky_light(chunk: &mut Chunk, section: i32, heightmap: &Heightmap) {
+    for (y, layer) in chunk.light_levels.iter_mut().enumerate() {
+        let y = section * SIZE as i32 + y as i32;
+
+        for (z, row) in layer.iter_mut().enumerate() {
+            for (x, light) in row.iter_mut().enumerate() {
+                let sky = if y >= heightmap.get(x, z) { 15 } else { 0 };
+
+                light.value = light.block_light() | sky << 4;
+            }
+        }
+    }
+}
+
+impl ChunkColumn {
+    /// Column of air.
+    pub fn new(height: WorldHeight, biomes: [[BiomeId; SIZE]; SIZE]) -> Self {
+        let count = height.section_count();
+
+        Self {
+            chunks: (0..count).map(|_| None).collect(),
+            min_section: height.min_section(),
+            biomes,
+            buffers: (0..count).map(|_| RefCell::default()).collect(),
+            visibility: vec![SectionVisibility::ALL; count],
+            heightmaps: Heightmaps::new(height.min_y),
+        }
+    }
+
+    pub fn height(&self) -> WorldHeight {
+        WorldHeight {
+            min_y: self.min_section * SIZE as i32,
+            height: (self.chunks.len() * SIZE) as i32,
+        }
+    }
+
+    /// Index into `chunks` of the section at height `section`.
+    pub fn section_index(&self, section: i32) -> Option<usize> {
+        usize::try_from(section - self.min_section)
+            .ok()
+            .filter(|&index| index < self.chunks.len())
+    }
+
+    /// The section at height `section`, `None` if it's empty or outside of the world.
+    pub fn get_section(&self, section: i32) -> Option<&Chunk> {
+        self.chunks[self.section_index(section)?].as_deref()
+    }
+
+    /// The section at height `section`, allocated if it's empty.
+    pub fn get_section_mut(&mut self, section: i32) -> Option<&mut Chunk> {
+        let index = self.section_index(section)?;
+        let heightmap = self.heightmaps.get(HeightmapKind::MotionBlocking);
+
+        Some(self.chunks[index].get_or_insert_with(|| {
+            let mut chunk = Box::new(*EMPTY_CHUNK);
+
+            seed_section_sky_light(&mut chunk, section, heightmap);
+
+            chunk
+        }))
+    }
+
+    pub fn get_buffer(&self, section: i32) -> Option<&RefCell<SectionMesh>> {
+        self.buffers.get(self.section_index(section)?)
+    }
+
+    /// Heights and contents of the sections which aren't empty.
+    pub fn sections(&self) -> impl Iterator<Item = (i32, &Chunk)> {
+        self.chunks
+            .iter()
+            .enumerate()
+            .filter_map(|(index, chunk)| Some((self.min_section + index as i32, chunk.as_deref()?)))
+    }
+
+    /// Block at `x` and `z` inside of the column and the world height `y`,
+    /// `None` outside of the world.
+    pub fn get_block(&self, x: usize, y: i32, z: usize) -> Option<BlockState> {
+        let (section, [_, y, _]) = section_position(ivec3(0, y, 0));
+        let index = self.section_index(section.y)?;
+
+        Some(
+            self.chunks[index]
+                .as_ref()
+                .map_or(EMPTY_BLOCK, |chunk| chunk.blocks[y][z][x]),
+        )
+    }
+
+    /// Light at `x` and `z` inside of the column and the world height `y`,
+    /// `None` outside of the world.
+    pub fn get_light_level(&self, x: usize, y: i32, z: usize) -> Option<LightLevel> {
+        let (section, [_, y, _]) = section_position(ivec3(0, y, 0));
+        let index = self.section_index(section.y)?;
+
+        Some(
+            self.chunks[index]
+                .as_deref()
+                .unwrap_or(EMPTY_CHUNK)
+                .light_levels[y][z][x],
+        )
+    }
+
+    /// Sets a block without any updates, returns `false` outside of the world.
+    pub fn set_block(&mut self, x: usize, y: i32, z: usize, state: BlockState) -> bool {
+        let (section, [_, y, _]) = section_position(ivec3(0, y, 0));
+
+        // Air doesn't need a section to be allocated.
+        if state.value == EMPTY_BLOCK.value {
+            let Some(index) = self.section_index(section.y) else {
+                return false;
+            };
+
+            if let Some(chunk) = &mut self.chunks[index] {
+                chunk.blocks[y][z][x] = state;
+            }
+
+            return true;
+        }
+
+        match self.get_section_mut(section.y) {
+            Some(chunk) => {
+                chunk.blocks[y][z][x] = state;
+
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn get_base_block<'a>(
+        &self,
+        block_states: &'a BlockStatesLoader,
+        x: usize,
+        y: i32,
+        z: usize,
+    ) -> Option<&'a BaseBlock> {
+        let state = self.get_block(x, y, z)?;
+
+        block_states
+            .get_by_id(state.value.into())
+            .map(|(block, _)| block)
+    }
+
+    pub fn heightmap(&self, kind: HeightmapKind) -> &Heightmap {
+        self.heightmaps.get(kind)
+    }
+
+    /// Recomputes the heightmaps from all blocks of the column.
+    pub fn compute_heightmaps(&mut self, block_states: &BlockStatesLoader) {
+        let height = self.height();
+        let top = self
+            .sections()
+            .last()
+            .map_or(height.min_y, |(y, _)| (y + 1) * SIZE as i32);
+        let mut heightmaps = Heightmaps::new(height.min_y);
+
+        for z in 0..SIZE {
+            for x in 0..SIZE {
+                for kind in HeightmapKind::ALL {
+                    let height = (height.min_y..top)
+                        .rev()
+                        .find(|&y| kind.is_opaque(self.get_base_block(block_states, x, y, z)))
+                        .map_or(height.min_y, |y| y + 1);
+
+                    heightmaps.get_mut(kind).set(x, z, height);
+                }
+            }
+        }
+
+        self.heightmaps = heightmaps;
+    }
+
+    /// Updates the heightmaps after the block at `x`, `y` and `z` changed,
+    /// returns the previous height of [`HeightmapKind::MotionBlocking`] if
+    /// it changed.
+    pub fn update_heightmaps(
+        &mut self,
+        block_states: &BlockStatesLoader,
+        x: usize,
+        y: i32,
+        z: usize,
+    ) -> Option<i32> {
+        let mut previous = None;
+
+        for kind in HeightmapKind::ALL {
+            let mut heightmap = *self.heightmaps.get(kind);
+            let changed = heightmap.update(x, y, z, |y| {
+                kind.is_opaque(self.get_base_block(block_states, x, y, z))
+            });
+
+            *self.heightmaps.get_mut(kind) = heightmap;
+
+            if kind == HeightmapKind::MotionBlocking {
+                previous = changed;
+            }
+        }
+
+        previous
+    }
+
+    /// Gives every block above [`HeightmapKind::MotionBlocking`] full sky
+    /// light and every block below it none, as long as there is no light
+    /// propagation this is all the sky light there is.
+    pub fn seed_sky_light(&mut self) {
+        let heightmap = *self.heightmaps.get(HeightmapKind::MotionBlocking);
+        let min_section = self.min_section;
+
+        for (index, chunk) in self.chunks.iter_mut().enumerate() {
+            if let Some(chunk) = chunk {
+                seed_section_sky_light(chunk, min_section + index as i32, &heightmap);
+            }
+        }
+    }
+
+    /// Seeds the sky light of `x` and `z` again between the previous height
+    /// of [`HeightmapKind::MotionBlocking`] and the current one.
+    pub fn update_sky_light(&mut self, x: usize, z: usize, previous: i32) {
+        let height = self.heightmaps.get(HeightmapKind::MotionBlocking).get(x, z);
+
+        for y in previous.min(height)..previous.max(height) {
+            let (section, [_, local_y, _]) = section_position(ivec3(0, y, 0));
+            let Some(index) = self.section_index(section.y) else {
+                continue;
+            };
+
+            if let Some(chunk) = &mut self.chunks[index] {
+                let light = &mut chunk.light_levels[local_y][z][x];
+                let sky = if y >= height { 15 } else { 0 };
+
+                light.value = light.block_light() | sky << 4;
+            }
+        }
+    }
+}
+
+/// Splits a block position into its section position and the position inside of that section.
+pub fn section_position(position: IVec3) -> (IVec3, [usize; 3]) {
+    let size = SIZE as i32;
+
+    (
+        ivec3(
+            position.x.div_euclid(size),
+            position.y.div_euclid(size),
+            position.z.div_euclid(size),
+        ),
+        [position.x, position.y, position.z].map(|x| x.rem_euclid(size) as usize),
+    )
 }
 
 #[derive(Default)]
 pub struct ChunkManager {
     chunk_columns: HashMap<IVec2, ChunkColumn>,
+    dirty_chunks: HashSet<IVec3>,
+    unsaved_columns: HashSet<IVec2>,
+    pub ticks: TickScheduler,
+    pub clock: WorldClock,
 }
 
 impl ChunkManager {
@@ -61,61 +371,245 @@ impl ChunkManager {
         Self::default()
     }
 
+    #[allow(clippy::type_complexity)]
+    pub fn get_chunk_and_neighbors(
+        &self,
+        position: IVec3,
+    ) -> Option<(
+        &RefCell<SectionMesh>,
+        [[[&Chunk; 3]; 3]; 3],
+        [[Option<&[[BiomeId; SIZE]; SIZE]>; 3]; 3],
+    )> {
+        let columns = [-1, 0, 1].map(|dz| {
+            [-1, 0, 1].map(|dx| {
+                self.chunk_columns
+                    .get(&ivec2(position.x + dx, position.z + dz))
+            })
+        });
+
+        let central = columns[1][1]?;
+
+        // Empty sections have nothing to mesh.
+        central.get_section(position.y)?;
+
+        let chunks = [-1, 0, 1].map(|dy| {
+            columns.map(|cz| {
+                cz.map(|cx| {
+                    cx.and_then(|c| c.get_section(position.y + dy))
+                        .unwrap_or(EMPTY_CHUNK)
+                })
+            })
+        });
+
+        Some((
+            central.get_buffer(position.y)?,
+            chunks,
+            columns.map(|cz| cz.map(|cx| cx.map(|c| &c.biomes))),
+        ))
+    }
+
     pub fn each_chunk_and_neighbors<'a, F>(&'a self, mut f: F)
     where
         F: FnMut(
             /*coords:*/ IVec3,
-            /*buffer:*/ &'a RefCell<Vec<(Vec3, Mesh)>>,
+            /*buffer:*/ &'a RefCell<SectionMesh>,
             /*chunks:*/ [[[&'a Chunk; 3]; 3]; 3],
             /*biomes:*/ [[Option<&'a [[BiomeId; SIZE]; SIZE]>; 3]; 3],
         ),
     {
-        for &position in self.chunk_columns.keys() {
-            let columns = [-1, 0, 1].map(|dz| {
-                [-1, 0, 1].map(|dx| {
-                    self.chunk_columns
-                        .get(&ivec2(position.x + dx, position.y + dz))
-                })
-            });
+        for position in self.sections() {
+            if let Some((buffer, chunks, biomes)) = self.get_chunk_and_neighbors(position) {
+                f(position, buffer, chunks, biomes)
+            }
+        }
+    }
+
+    /// Positions of every loaded section which isn't empty.
+    pub fn sections(&self) -> Vec<IVec3> {
+        self.chunk_columns
+            .iter()
+            .flat_map(|(&position, c)| {
+                c.sections()
+                    .map(move |(y, _)| ivec3(position.x, y, position.y))
+            })
+            .collect()
+    }
+
+    pub fn get_block(&self, position: IVec3) -> Option<BlockState> {
+        let (section, [x, _, z]) = section_position(position);
+
+        self.chunk_columns
+            .get(&ivec2(section.x, section.z))?
+            .get_block(x, position.y, z)
+    }
+
+    pub fn get_light_level(&self, position: IVec3) -> Option<LightLevel> {
+        let (section, [x, _, z]) = section_position(position);
+
+        self.chunk_columns
+            .get(&ivec2(section.x, section.z))?
+            .get_light_level(x, position.y, z)
+    }
+
+    pub fn get_biome(&self, position: IVec3) -> Option<BiomeId> {
+        let (section, [x, _, z]) = section_position(position);
+
+        Some(self.chunk_columns.get(&ivec2(section.x, section.z))?.biomes[z][x])
+    }
+
+    /// Replaces a block, marking its section and the touched neighbors as dirty.
+    /// The heightmaps of the column and the sky light below them follow the change.
+    ///
+    /// Returns `false` if the position isn't loaded or the block is already there.
+    pub fn set_block(
+        &mut self,
+        position: IVec3,
+        state: BlockState,
+        block_states: &BlockStatesLoader,
+    ) -> bool {
+        let (section, [x, _, z]) = section_position(position);
+
+        let Some(column) = self.chunk_columns.get_mut(&ivec2(section.x, section.z)) else {
+            return false;
+        };
 
-            let central = columns[1][1].unwrap();
-
-            for y in 0..central.chunks.len() {
-                let chunks = [-1, 0, 1].map(|dy| {
-                    let y = y as i32 + dy;
-                    columns.map(|cz| {
-                        cz.map(|cx| {
-                            cx.and_then(|c| c.chunks[..].get(y as usize))
-                                .unwrap_or(EMPTY_CHUNK)
-                        })
-                    })
-                });
-
-                f(
-                    ivec3(position.x, y as i32, position.y),
-                    &central.buffers[y],
-                    chunks,
-                    columns.map(|cz| cz.map(|cx| cx.map(|c| &c.biomes))),
-                )
+        if column
+            .get_block(x, position.y, z)
+            .is_none_or(|block| block.value == state.value)
+        {
+            return false;
+        }
+
+        column.set_block(x, position.y, z, state);
+
+        // Faces next to the blocks the sky light changed for have to be remeshed.
+        if let Some(previous) = column.update_heightmaps(block_states, x, position.y, z) {
+            column.update_sky_light(x, z, previous);
+
+            let height = column.heightmap(HeightmapKind::MotionBlocking).get(x, z);
+
+            for y in previous.min(height) - 1..=previous.max(height) {
+                for dz in -1..=1 {
+                    for dx in -1..=1 {
+                        let (neighbor, _) =
+                            section_position(ivec3(position.x + dx, y, position.z + dz));
+
+                        self.dirty_chunks.insert(neighbor);
+                    }
+                }
+            }
+        }
+
+        self.unsaved_columns.insert(ivec2(section.x, section.z));
+
+        // Faces of the neighboring sections are culled against this block too.
+        for dy in -1..=1 {
+            for dz in -1..=1 {
+                for dx in -1..=1 {
+                    let (neighbor, _) = section_position(position + ivec3(dx, dy, dz));
+
+                    self.dirty_chunks.insert(neighbor);
+                }
             }
         }
+
+        true
+    }
+
+    /// Returns sections which need to be remeshed since the last call.
+    pub fn take_dirty_chunks(&mut self) -> Vec<IVec3> {
+        self.dirty_chunks.drain().collect()
+    }
+
+    /// Returns columns changed since the last call, which have to be saved.
+    pub fn take_unsaved_columns(&mut self) -> Vec<IVec2> {
+        self.unsaved_columns.drain().collect()
     }
 
+    /// Makes the next save write the column even though no block was set in it.
+    pub fn mark_unsaved(&mut self, position: IVec2) {
+        self.unsaved_columns.insert(position);
+    }
+
+    /// Positions of every loaded column.
+    pub fn column_positions(&self) -> Vec<IVec2> {
+        self.chunk_columns.keys().copied().collect()
+    }
+
+    /// Removes a column, together with whether it changed since it was last
+    /// saved. Sections next to it get meshed again.
+    pub fn remove_chunk_column(&mut self, position: IVec2) -> Option<(ChunkColumn, bool)> {
+        let column = self.chunk_columns.remove(&position)?;
+        let height = column.height();
+
+        for section in height.min_section()..height.min_section() + height.section_count() as i32 {
+            for dz in -1..=1 {
+                for dx in -1..=1 {
+                    if dx != 0 || dz != 0 {
+                        self.dirty_chunks
+                            .insert(ivec3(position.x + dx, section, position.y + dz));
+                    }
+                }
+            }
+        }
+
+        Some((column, self.unsaved_columns.remove(&position)))
+    }
+
+    pub fn get_chunk_column(&self, position: IVec2) -> Option<&ChunkColumn> {
+        self.chunk_columns.get(&position)
+    }
+
+    /// Adds a column, its sections and the sections next to it get meshed.
     pub fn add_chunk_column(&mut self, position: IVec2, c: ChunkColumn) {
+        let height = c.height();
+
+        for section in height.min_section()..height.min_section() + height.section_count() as i32 {
+            for dz in -1..=1 {
+                for dx in -1..=1 {
+                    self.dirty_chunks
+                        .insert(ivec3(position.x + dx, section, position.y + dz));
+                }
+            }
+        }
+
         self.chunk_columns.insert(position, c);
     }
 
+    pub fn get_buffer(&self, position: IVec3) -> Option<&RefCell<SectionMesh>> {
+        self.chunk_columns
+            .get(&ivec2(position.x, position.z))?
+            .get_buffer(position.y)
+    }
+
+    /// Visibility of a loaded section, empty ones can be seen through entirely.
+    pub fn get_visibility(&self, position: IVec3) -> Option<SectionVisibility> {
+        let column = self.chunk_columns.get(&ivec2(position.x, position.z))?;
+
+        Some(column.visibility[column.section_index(position.y)?])
+    }
+
+    pub fn set_visibility(&mut self, position: IVec3, visibility: SectionVisibility) {
+        if let Some(column) = self.chunk_columns.get_mut(&ivec2(position.x, position.z)) {
+            if let Some(index) = column.section_index(position.y) {
+                column.visibility[index] = visibility;
+            }
+        }
+    }
+
     pub fn each_chunk<F>(&self, mut f: F)
     where
         F: FnMut(
             /* position: */ IVec3,
             /* chunk: */ &Chunk,
-            /* buffer: */ &RefCell<Vec<(Vec3, Mesh)>>,
+            /* buffer: */ &RefCell<SectionMesh>,
         ),
     {
         for (&position, c) in self.chunk_columns.iter() {
-            for (y, (c, b)) in c.chunks.iter().zip(c.buffers.iter()).enumerate() {
-                f(ivec3(position.x, y as i32, position.y), c, b)
+            for (y, chunk) in c.sections() {
+                if let Some(buffer) = c.get_buffer(y) {
+                    f(ivec3(position.x, y, position.y), chunk, buffer)
+                }
             }
         }
     }
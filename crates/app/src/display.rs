@@ -0,0 +1,72 @@
+//! Models drawn on their own instead of as part of the world, like the icons
+//! of the inventory or the item held in first person. They are transformed
+//! by the `display` section of the model for where they are shown.
+
+use crate::{
+    ao,
+    block_states::Tint,
+    loaders::{Biome, Model, TextureLoader},
+};
+use data::model::DisplayContext;
+use macroquad::{
+    color::{Color, WHITE},
+    math::Mat4,
+    models::{draw_mesh, Mesh, Vertex},
+};
+
+/// Draws `model` as shown in `context`, `transform` places it in the current
+/// camera afterwards. Faces are tinted with the colors of `biome`.
+///
+/// The faces are drawn from back to front for a camera looking down the
+/// negative z axis, so no depth buffer is needed and they can be drawn over
+/// the world.
+pub fn draw_model(
+    model: &Model,
+    textures: &TextureLoader,
+    biome: &Biome,
+    context: DisplayContext,
+    transform: Mat4,
+) {
+    let matrix = transform * model.display.matrix(context);
+
+    let mut faces: Vec<_> = model
+        .faces
+        .iter()
+        .map(|face| {
+            let positions = face
+                .vertices
+                .map(|vertex| matrix.transform_point3(vertex.position));
+            let depth = positions.iter().map(|position| position.z).sum::<f32>();
+
+            (depth, face, positions)
+        })
+        .collect();
+
+    faces.sort_by(|(a, ..), (b, ..)| a.total_cmp(b));
+
+    for (_, face, positions) in faces {
+        let tint = match face.tint {
+            Some(Tint::Color(color)) => color,
+            Some(Tint::Grass) => biome.grass_color,
+            Some(Tint::Foliage) => biome.foliage_color,
+            Some(Tint::Water) => biome.water_color,
+            None => WHITE,
+        };
+        let shade = face.ao_face.map_or(1.0, ao::shade);
+        let color = Color::new(tint.r * shade, tint.g * shade, tint.b * shade, tint.a);
+
+        draw_mesh(&Mesh {
+            vertices: positions
+                .iter()
+                .zip(&face.vertices)
+                .map(|(&position, vertex)| Vertex {
+                    position,
+                    uv: vertex.uv,
+                    color,
+                })
+                .collect(),
+            indices: vec![0, 1, 2, 2, 3, 0],
+            texture: textures.get(&face.texture).cloned(),
+        });
+    }
+}
@@ -0,0 +1,789 @@
+use crate::{
+    block_states::{BaseBlock, Block, BlockProperties, BlockRenderLayer, Tint},
+    chunk::EMPTY_BLOCK,
+    loaders::{Face, Model},
+    world::World,
+};
+use data::{block_states::Property, model::Direction};
+use macroquad::{
+    color::WHITE,
+    math::{ivec3, vec2, vec3, vec4, IVec3, Vec2, Vec3},
+    models::Vertex,
+};
+use maqo_macros::BlockProperties;
+use std::{array, collections::HashMap, f32::consts::FRAC_PI_2};
+
+/// Amount of fluid in a source block.
+pub const SOURCE_AMOUNT: u8 = 8;
+
+const UP: IVec3 = ivec3(0, 1, 0);
+const DOWN: IVec3 = ivec3(0, -1, 0);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FluidKind {
+    Water,
+    Lava,
+}
+
+impl FluidKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Water => "minecraft:water",
+            Self::Lava => "minecraft:lava",
+        }
+    }
+
+    /// Game ticks between two flow updates.
+    pub fn tick_delay(self) -> u64 {
+        match self {
+            Self::Water => 5,
+            Self::Lava => 30,
+        }
+    }
+
+    /// How much the amount decreases with each block the fluid flows sideways.
+    pub fn drop_off(self) -> u8 {
+        match self {
+            Self::Water => 1,
+            Self::Lava => 2,
+        }
+    }
+
+    /// How far the fluid looks for a hole to flow towards.
+    pub fn slope_find_distance(self) -> usize {
+        match self {
+            Self::Water => 4,
+            Self::Lava => 2,
+        }
+    }
+
+    /// Whether two sources next to each other turn the fluid between them into a source.
+    pub fn can_convert_to_source(self) -> bool {
+        self == Self::Water
+    }
+
+    pub fn still_texture(self) -> &'static str {
+        match self {
+            Self::Water => "minecraft:block/water_still",
+            Self::Lava => "minecraft:block/lava_still",
+        }
+    }
+
+    pub fn flow_texture(self) -> &'static str {
+        match self {
+            Self::Water => "minecraft:block/water_flow",
+            Self::Lava => "minecraft:block/lava_flow",
+        }
+    }
+
+    pub fn tint(self) -> Option<Tint> {
+        match self {
+            Self::Water => Some(Tint::Water),
+            Self::Lava => None,
+        }
+    }
+}
+
+/// Fluid inside of a block, its amount goes from 1 to [`SOURCE_AMOUNT`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FluidState {
+    pub kind: FluidKind,
+    pub amount: u8,
+    pub falling: bool,
+}
+
+impl FluidState {
+    pub fn source(kind: FluidKind) -> Self {
+        Self {
+            kind,
+            amount: SOURCE_AMOUNT,
+            falling: false,
+        }
+    }
+
+    pub fn flowing(kind: FluidKind, amount: u8) -> Self {
+        Self {
+            kind,
+            amount,
+            falling: false,
+        }
+    }
+
+    pub fn falling(kind: FluidKind) -> Self {
+        Self {
+            kind,
+            amount: SOURCE_AMOUNT,
+            falling: true,
+        }
+    }
+
+    /// Reads the `level` property of a fluid block: 0 is a source, 1 to 7 is
+    /// flowing fluid getting thinner and 8 and above is falling.
+    pub fn from_level(kind: FluidKind, level: i64) -> Self {
+        match level {
+            0 => Self::source(kind),
+            1..=7 => Self::flowing(kind, SOURCE_AMOUNT - level as u8),
+            _ => Self {
+                kind,
+                amount: SOURCE_AMOUNT - (level.min(15) as u8 - 8),
+                falling: true,
+            },
+        }
+    }
+
+    pub fn level(self) -> i64 {
+        if self.is_source() {
+            0
+        } else {
+            i64::from(SOURCE_AMOUNT - self.amount) + if self.falling { 8 } else { 0 }
+        }
+    }
+
+    pub fn is_source(self) -> bool {
+        self.amount == SOURCE_AMOUNT && !self.falling
+    }
+
+    /// Height of the fluid surface inside of its block.
+    pub fn height(self) -> f32 {
+        self.amount as f32 / 9.0
+    }
+}
+
+fn fluid_at(world: &World, position: IVec3, kind: FluidKind) -> Option<FluidState> {
+    world
+        .get_base_block(position)?
+        .block
+        .fluid()
+        .filter(|fluid| fluid.kind == kind)
+}
+
+/// Whether fluid can pass through the block at `position`, which is only
+/// true for air and flowing fluid of the same kind.
+fn can_pass_through(world: &World, position: IVec3, kind: FluidKind) -> bool {
+    match world.get_block(position) {
+        None => return false,
+        // Air is registered as a block as well.
+        Some(state) if state.value == EMPTY_BLOCK.value => return true,
+        Some(_) => {}
+    }
+
+    match world.get_base_block(position) {
+        Some(block) => block
+            .block
+            .fluid()
+            .is_some_and(|fluid| fluid.kind == kind && !fluid.is_source()),
+        None => true,
+    }
+}
+
+/// Whether `fluid` would replace what's at `position`.
+fn can_flow_into(world: &World, position: IVec3, fluid: FluidState) -> bool {
+    if !can_pass_through(world, position, fluid.kind) {
+        return false;
+    }
+
+    fluid_at(world, position, fluid.kind)
+        .is_none_or(|other| other.amount < fluid.amount || fluid.falling && !other.falling)
+}
+
+fn is_hole(world: &World, position: IVec3, kind: FluidKind) -> bool {
+    can_pass_through(world, position + DOWN, kind)
+        || fluid_at(world, position + DOWN, kind).is_some()
+}
+
+fn count_sources(world: &World, position: IVec3, kind: FluidKind) -> usize {
+    Direction::HORIZONTAL
+        .iter()
+        .filter(|direction| {
+            fluid_at(world, position + direction.as_vec3(), kind).is_some_and(FluidState::is_source)
+        })
+        .count()
+}
+
+fn place_fluid(world: &mut World, position: IVec3, fluid: FluidState) {
+    if let Some(state) = world
+        .block_states
+        .get_default_state(fluid.kind.name())
+        .and_then(|state| {
+            world
+                .block_states
+                .with_property(state, "level", fluid.level())
+        })
+    {
+        world.set_block(position, state);
+    }
+}
+
+/// Fluid the block at `position` should contain based on its neighbors.
+fn get_new_fluid(world: &World, position: IVec3, kind: FluidKind) -> Option<FluidState> {
+    let mut amount = 0;
+    let mut sources = 0;
+
+    for direction in Direction::HORIZONTAL {
+        if let Some(neighbor) = fluid_at(world, position + direction.as_vec3(), kind) {
+            if neighbor.is_source() {
+                sources += 1;
+            }
+
+            amount = amount.max(neighbor.amount);
+        }
+    }
+
+    if sources >= 2 && kind.can_convert_to_source() {
+        let below = position + DOWN;
+
+        if world
+            .get_base_block(below)
+            .is_some_and(|block| block.is_full_cube && block.is_opaque_cube)
+            || fluid_at(world, below, kind).is_some_and(FluidState::is_source)
+        {
+            return Some(FluidState::source(kind));
+        }
+    }
+
+    if fluid_at(world, position + UP, kind).is_some() {
+        return Some(FluidState::falling(kind));
+    }
+
+    let amount = amount.saturating_sub(kind.drop_off());
+
+    (amount > 0).then(|| FluidState::flowing(kind, amount))
+}
+
+/// Distance to the nearest hole the fluid could flow into, walking
+/// sideways from `position` without going back to where it came `from`.
+fn slope_distance(
+    world: &World,
+    position: IVec3,
+    depth: usize,
+    from: Direction,
+    kind: FluidKind,
+) -> usize {
+    let mut distance = usize::MAX;
+
+    for direction in Direction::HORIZONTAL {
+        if direction == from {
+            continue;
+        }
+
+        let neighbor = position + direction.as_vec3();
+
+        if !can_pass_through(world, neighbor, kind) {
+            continue;
+        }
+
+        if is_hole(world, neighbor, kind) {
+            return depth;
+        }
+
+        if depth < kind.slope_find_distance() {
+            distance = distance.min(slope_distance(
+                world,
+                neighbor,
+                depth + 1,
+                direction.opposite(),
+                kind,
+            ));
+        }
+    }
+
+    distance
+}
+
+/// Horizontal directions the fluid flows to, which are the ones leading to
+/// the nearest hole, or all of them if there is none.
+fn spread_directions(world: &World, position: IVec3, kind: FluidKind) -> Vec<Direction> {
+    let mut directions = Vec::new();
+    let mut best = usize::MAX;
+
+    for direction in Direction::HORIZONTAL {
+        let neighbor = position + direction.as_vec3();
+
+        if !can_pass_through(world, neighbor, kind) {
+            continue;
+        }
+
+        let distance = if is_hole(world, neighbor, kind) {
+            0
+        } else {
+            slope_distance(world, neighbor, 1, direction.opposite(), kind)
+        };
+
+        if distance < best {
+            best = distance;
+            directions.clear();
+        }
+
+        if distance == best {
+            directions.push(direction);
+        }
+    }
+
+    directions
+}
+
+fn spread_to_sides(world: &mut World, position: IVec3, fluid: FluidState) {
+    let amount = if fluid.falling {
+        SOURCE_AMOUNT - 1
+    } else {
+        fluid.amount.saturating_sub(fluid.kind.drop_off())
+    };
+
+    if amount == 0 {
+        return;
+    }
+
+    let flowing = FluidState::flowing(fluid.kind, amount);
+
+    for direction in spread_directions(world, position, fluid.kind) {
+        let neighbor = position + direction.as_vec3();
+
+        if can_flow_into(world, neighbor, flowing) {
+            place_fluid(world, neighbor, flowing);
+        }
+    }
+}
+
+fn spread(world: &mut World, position: IVec3, fluid: FluidState) {
+    let below = position + DOWN;
+    let falling = FluidState::falling(fluid.kind);
+
+    if can_flow_into(world, below, falling) {
+        place_fluid(world, below, falling);
+
+        // A pool of sources keeps spreading even while it drains downwards.
+        if fluid.is_source() && count_sources(world, position, fluid.kind) >= 3 {
+            spread_to_sides(world, position, fluid);
+        }
+    } else if fluid.is_source() || fluid_at(world, below, fluid.kind).is_none() {
+        spread_to_sides(world, position, fluid);
+    }
+}
+
+fn on_fluid_tick(world: &mut World, position: IVec3, mut fluid: FluidState) {
+    if !fluid.is_source() {
+        match get_new_fluid(world, position, fluid.kind) {
+            None => {
+                world.set_block(position, EMPTY_BLOCK);
+
+                return;
+            }
+            Some(new_fluid) if new_fluid != fluid => {
+                fluid = new_fluid;
+
+                place_fluid(world, position, fluid);
+            }
+            _ => {}
+        }
+    }
+
+    spread(world, position, fluid);
+}
+
+#[derive(Default, BlockProperties)]
+#[block(full_cube = false, opaque_cube = false)]
+pub struct WaterBlock {
+    pub level: i64,
+}
+
+impl Block for WaterBlock {
+    fn on_scheduled_tick(&self, world: &mut World, position: IVec3) {
+        on_fluid_tick(
+            world,
+            position,
+            FluidState::from_level(FluidKind::Water, self.level),
+        );
+    }
+
+    fn on_neighbor_changed(&self, world: &mut World, position: IVec3, _neighbor: IVec3) {
+        world.schedule_tick(position, FluidKind::Water.tick_delay());
+    }
+
+    fn on_place(&self, world: &mut World, position: IVec3) {
+        world.schedule_tick(position, FluidKind::Water.tick_delay());
+    }
+
+    fn render_layer(&self) -> BlockRenderLayer {
+        BlockRenderLayer::Translucent
+    }
+
+    fn fluid(&self) -> Option<FluidState> {
+        Some(FluidState::from_level(FluidKind::Water, self.level))
+    }
+}
+
+#[derive(Default, BlockProperties)]
+#[block(full_cube = false, opaque_cube = false)]
+pub struct LavaBlock {
+    pub level: i64,
+}
+
+impl Block for LavaBlock {
+    fn on_scheduled_tick(&self, world: &mut World, position: IVec3) {
+        on_fluid_tick(
+            world,
+            position,
+            FluidState::from_level(FluidKind::Lava, self.level),
+        );
+    }
+
+    fn on_neighbor_changed(&self, world: &mut World, position: IVec3, _neighbor: IVec3) {
+        world.schedule_tick(position, FluidKind::Lava.tick_delay());
+    }
+
+    fn on_place(&self, world: &mut World, position: IVec3) {
+        world.schedule_tick(position, FluidKind::Lava.tick_delay());
+    }
+
+    fn fluid(&self) -> Option<FluidState> {
+        Some(FluidState::from_level(FluidKind::Lava, self.level))
+    }
+}
+
+/// Builds the faces of a fluid block, `neighbor` returns the block at an
+/// offset from it.
+///
+/// Unlike block models the surface is sloped, every corner is as high as the
+/// fluid around it and the flowing texture is turned towards the direction
+/// the fluid flows in.
+pub fn get_fluid_faces<'a>(
+    fluid: FluidState,
+    neighbor: impl Fn(IVec3) -> Option<&'a (BaseBlock, Model)>,
+) -> Vec<Face> {
+    let kind = fluid.kind;
+
+    let fluid_at = |offset: IVec3| {
+        neighbor(offset)
+            .and_then(|(block, _)| block.block.fluid())
+            .filter(|other| other.kind == kind)
+    };
+    let is_solid =
+        |offset: IVec3| neighbor(offset).is_some_and(|(_, model)| model.opacity.is_solid());
+    let is_opaque =
+        |offset: IVec3| neighbor(offset).is_some_and(|(_, model)| model.opacity.is_opaque());
+    let height_at = |offset: IVec3| match fluid_at(offset) {
+        Some(_) if fluid_at(offset + UP).is_some() => 1.0,
+        Some(other) => other.height(),
+        None => 0.0,
+    };
+
+    // Corners are averaged over the four blocks sharing them, blocks almost
+    // full of fluid weigh more so sources stay level next to flowing fluid.
+    let corner_height = |x: i32, z: i32| {
+        let (mut sum, mut weight) = (0.0, 0.0);
+
+        for (dx, dz) in [(0, 0), (-1, 0), (0, -1), (-1, -1)] {
+            let offset = ivec3(x + dx, 0, z + dz);
+
+            if fluid_at(offset + UP).is_some() {
+                return 1.0;
+            }
+
+            if fluid_at(offset).is_some() {
+                let height = height_at(offset);
+
+                if height >= 0.8 {
+                    sum += height * 10.0;
+                    weight += 10.0;
+                } else {
+                    sum += height;
+                    weight += 1.0;
+                }
+            } else if !is_solid(offset) {
+                weight += 1.0;
+            }
+        }
+
+        sum / weight
+    };
+
+    let mut flow = Vec3::ZERO;
+
+    for direction in Direction::HORIZONTAL {
+        let offset = direction.as_vec3();
+
+        let difference = if fluid_at(offset).is_some() {
+            fluid.height() - height_at(offset)
+        } else if !is_solid(offset) && fluid_at(offset + DOWN).is_some() {
+            fluid.height() - (height_at(offset + DOWN) - 8.0 / 9.0)
+        } else {
+            0.0
+        };
+
+        flow += offset.as_vec3() * difference;
+    }
+
+    let (h00, h01, h11, h10) = (
+        corner_height(0, 0),
+        corner_height(0, 1),
+        corner_height(1, 1),
+        corner_height(1, 0),
+    );
+
+    // Same corner order as the elements of block models.
+    let corners = [
+        vec3(0.0, 0.0, 0.0),
+        vec3(0.0, h00, 0.0),
+        vec3(0.0, h01, 1.0),
+        vec3(0.0, 0.0, 1.0),
+        vec3(1.0, 0.0, 1.0),
+        vec3(1.0, h11, 1.0),
+        vec3(1.0, h10, 0.0),
+        vec3(1.0, 0.0, 0.0),
+    ]
+    .map(|corner| corner - 0.5);
+
+    let face = |direction: Direction, texture: &str, uvs: [Vec2; 4]| {
+        let index = direction.vertices();
+
+        Face {
+            vertices: array::from_fn(|i| Vertex {
+                position: corners[index[i]],
+                uv: uvs[i],
+                color: WHITE,
+            }),
+            cull_face: None,
+            ao_face: Some(direction),
+            tint: kind.tint(),
+            texture: texture.into(),
+        }
+    };
+
+    let mut faces = Vec::new();
+
+    if fluid_at(UP).is_none() {
+        let top = if flow.x == 0.0 && flow.z == 0.0 {
+            face(
+                Direction::Top,
+                kind.still_texture(),
+                Direction::Top.uvs(vec4(0.0, 0.0, 1.0, 1.0)),
+            )
+        } else {
+            // The flowing texture is twice as big, so a turned square in
+            // its middle is sampled.
+            let angle = flow.z.atan2(flow.x) - FRAC_PI_2;
+            let (s, c) = (angle.sin() * 0.25, angle.cos() * 0.25);
+
+            face(
+                Direction::Top,
+                kind.flow_texture(),
+                [
+                    vec2(0.5 - c - s, 0.5 - c + s),
+                    vec2(0.5 - c + s, 0.5 + c + s),
+                    vec2(0.5 + c + s, 0.5 + c - s),
+                    vec2(0.5 + c - s, 0.5 - c - s),
+                ],
+            )
+        };
+
+        // Water surfaces can be seen from below as well.
+        if kind == FluidKind::Water {
+            let mut back = top.clone();
+
+            back.vertices.reverse();
+            faces.push(back);
+        }
+
+        faces.push(top);
+    }
+
+    if fluid_at(DOWN).is_none() && !is_opaque(DOWN) {
+        faces.push(face(
+            Direction::Bottom,
+            kind.still_texture(),
+            Direction::Bottom.uvs(vec4(0.0, 0.0, 1.0, 1.0)),
+        ));
+    }
+
+    for direction in Direction::HORIZONTAL {
+        let offset = direction.as_vec3();
+
+        if fluid_at(offset).is_some() || is_opaque(offset) {
+            continue;
+        }
+
+        // Only the upper half of the flowing texture is used, cut off at the
+        // height of the fluid.
+        let uvs = direction.vertices().map(|i| {
+            let corner = corners[i] + 0.5;
+            let along = match direction {
+                Direction::Front | Direction::Back => corner.x,
+                _ => corner.z,
+            };
+
+            vec2(along * 0.5, (1.0 - corner.y) * 0.5)
+        });
+
+        faces.push(face(direction, kind.flow_texture(), uvs));
+    }
+
+    faces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        block_states::{AirBlock, DirtBlock},
+        chunk::{BiomeId, ChunkColumn, ChunkManager, WorldHeight, SIZE},
+        loaders::BlockStatesLoader,
+    };
+    use macroquad::math::ivec2;
+
+    /// Registers air, dirt and every level of water, without their models.
+    fn block_states() -> BlockStatesLoader {
+        let mut block_states = BlockStatesLoader::default();
+
+        block_states.register_block_with_model(0, "air", AirBlock, Model::empty());
+        block_states.register_block_with_model(1, "dirt", DirtBlock::default(), Model::empty());
+
+        for level in 0..=15 {
+            block_states.register_block_with_model(
+                2 + level as usize,
+                "water",
+                WaterBlock { level },
+                Model::empty(),
+            );
+        }
+
+        block_states
+    }
+
+    /// A single column with a floor of dirt at a height of 0.
+    fn chunks(block_states: &BlockStatesLoader) -> ChunkManager {
+        let mut chunks = ChunkManager::new();
+
+        chunks.add_chunk_column(
+            ivec2(0, 0),
+            ChunkColumn::new(WorldHeight::default(), [[BiomeId { value: 0 }; SIZE]; SIZE]),
+        );
+        chunks.ticks.random_tick_speed = 0;
+
+        fill(block_states, &mut chunks, 0, 0..SIZE as i32);
+
+        chunks
+    }
+
+    /// Puts dirt at the height `y` for the given range of x.
+    fn fill(
+        block_states: &BlockStatesLoader,
+        chunks: &mut ChunkManager,
+        y: i32,
+        xs: std::ops::Range<i32>,
+    ) {
+        let dirt = block_states.get_default_state("dirt").unwrap();
+
+        for z in 0..SIZE as i32 {
+            for x in xs.clone() {
+                chunks.set_block(ivec3(x, y, z), dirt, block_states);
+            }
+        }
+    }
+
+    fn place_source(world: &mut World, position: IVec3) {
+        place_fluid(world, position, FluidState::source(FluidKind::Water));
+    }
+
+    fn run_ticks(world: &mut World, count: usize) {
+        for _ in 0..count {
+            world.tick();
+        }
+    }
+
+    fn water_at(world: &World, position: IVec3) -> Option<FluidState> {
+        fluid_at(world, position, FluidKind::Water)
+    }
+
+    #[test]
+    fn levels_round_trip() {
+        for level in 0..=15 {
+            assert_eq!(
+                FluidState::from_level(FluidKind::Water, level).level(),
+                level
+            );
+        }
+
+        assert!(FluidState::from_level(FluidKind::Water, 0).is_source());
+        assert!(FluidState::from_level(FluidKind::Water, 8).falling);
+        assert_eq!(FluidState::from_level(FluidKind::Water, 7).amount, 1);
+    }
+
+    #[test]
+    fn sources_spread_seven_blocks_and_stop() {
+        let block_states = block_states();
+        let mut chunks = chunks(&block_states);
+        let mut world = World::new(&block_states, &mut chunks);
+        let source = ivec3(8, 1, 8);
+
+        place_source(&mut world, source);
+        run_ticks(&mut world, 100);
+
+        for distance in 1..=7 {
+            let fluid = water_at(&world, source - ivec3(distance, 0, 0));
+
+            assert_eq!(
+                fluid,
+                Some(FluidState::flowing(
+                    FluidKind::Water,
+                    SOURCE_AMOUNT - distance as u8
+                )),
+                "{distance}"
+            );
+        }
+
+        assert_eq!(water_at(&world, source - ivec3(8, 0, 0)), None);
+        assert_eq!(water_at(&world, source + ivec3(4, 0, -4)), None);
+
+        // Every block at most seven steps away is covered.
+        let covered = (0..SIZE as i32)
+            .flat_map(|z| (0..SIZE as i32).map(move |x| ivec3(x, 1, z)))
+            .filter(|&position| water_at(&world, position).is_some())
+            .count();
+
+        assert_eq!(covered, 1 + 2 * 7 * 8);
+    }
+
+    #[test]
+    fn two_sources_form_a_third() {
+        let block_states = block_states();
+        let mut chunks = chunks(&block_states);
+        let mut world = World::new(&block_states, &mut chunks);
+
+        place_source(&mut world, ivec3(7, 1, 8));
+        place_source(&mut world, ivec3(9, 1, 8));
+        run_ticks(&mut world, 20);
+
+        assert!(water_at(&world, ivec3(8, 1, 8)).is_some_and(FluidState::is_source));
+        assert!(!water_at(&world, ivec3(10, 1, 8)).is_some_and(FluidState::is_source));
+    }
+
+    #[test]
+    fn fluid_falls_off_an_edge() {
+        let block_states = block_states();
+        let mut chunks = chunks(&block_states);
+
+        fill(&block_states, &mut chunks, 4, 8..SIZE as i32);
+
+        let mut world = World::new(&block_states, &mut chunks);
+
+        place_source(&mut world, ivec3(10, 5, 8));
+        run_ticks(&mut world, 100);
+
+        // It only flows towards the edge, which is the closest hole.
+        assert_eq!(
+            water_at(&world, ivec3(7, 5, 8)),
+            Some(FluidState::flowing(FluidKind::Water, 5))
+        );
+        assert_eq!(water_at(&world, ivec3(12, 5, 8)), None);
+
+        for y in 1..5 {
+            assert!(
+                water_at(&world, ivec3(7, y, 8)).is_some_and(|fluid| fluid.falling),
+                "{y}"
+            );
+        }
+
+        // And spreads again once it hits the ground.
+        assert!(water_at(&world, ivec3(6, 1, 8)).is_some_and(|fluid| !fluid.falling));
+    }
+}
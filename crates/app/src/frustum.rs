@@ -0,0 +1,122 @@
+//! View frustum of a camera, for skipping everything outside of the view
+//! before it gets drawn.
+
+use macroquad::math::{Mat4, Vec3, Vec4, Vec4Swizzles};
+
+/// The six planes bounding what a camera sees, with their normals pointing
+/// inwards.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Frustum {
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes out of a view-projection matrix with OpenGL's clip
+    /// space, like the one of [`Camera3D`](macroquad::camera::Camera3D).
+    pub fn from_matrix(matrix: Mat4) -> Self {
+        let [x, y, z, w] = [0, 1, 2, 3].map(|i| matrix.row(i));
+
+        let planes = [w + x, w - x, w + y, w - y, w + z, w - z].map(|plane| {
+            let length = plane.xyz().length();
+
+            if length > 0.0 {
+                plane / length
+            } else {
+                plane
+            }
+        });
+
+        Self { planes }
+    }
+
+    /// Whether `point` is inside of the frustum or on one of its planes.
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.xyz().dot(point) + plane.w >= 0.0)
+    }
+
+    /// Whether any part of the sphere around `center` may be visible, with
+    /// the same false positives near the corners as [`Self::intersects_aabb`].
+    pub fn intersects_sphere(&self, center: Vec3, radius: f32) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.xyz().dot(center) + plane.w >= -radius)
+    }
+
+    /// Whether any part of the box between `min` and `max` may be visible.
+    ///
+    /// A box is only rejected if it's entirely behind one of the planes, so
+    /// boxes near the corners of the frustum are sometimes kept even though
+    /// they are outside of it.
+    pub fn intersects_aabb(&self, min: Vec3, max: Vec3) -> bool {
+        self.planes.iter().all(|plane| {
+            // The corner furthest along the normal is the last one to leave.
+            let corner = Vec3::select(plane.xyz().cmpge(Vec3::ZERO), max, min);
+
+            plane.xyz().dot(corner) + plane.w >= 0.0
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Camera at the origin looking along -z with a 90° field of view, seeing
+    /// from 0.1 to 100 blocks away.
+    fn frustum() -> Frustum {
+        let projection = Mat4::perspective_rh_gl(90f32.to_radians(), 1.0, 0.1, 100.0);
+        let view = Mat4::look_at_rh(Vec3::ZERO, Vec3::NEG_Z, Vec3::Y);
+
+        Frustum::from_matrix(projection * view)
+    }
+
+    #[test]
+    fn points_inside_and_outside() {
+        let frustum = frustum();
+
+        assert!(frustum.contains_point(Vec3::new(0.0, 0.0, -10.0)));
+        assert!(frustum.contains_point(Vec3::new(9.0, -9.0, -10.0)));
+        assert!(!frustum.contains_point(Vec3::new(11.0, 0.0, -10.0)));
+        assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, 10.0)));
+        assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, -0.05)));
+    }
+
+    #[test]
+    fn box_straddling_the_near_plane_is_kept() {
+        let frustum = frustum();
+
+        assert!(frustum.intersects_aabb(Vec3::new(-0.5, -0.5, -0.5), Vec3::new(0.5, 0.5, 0.5)));
+        assert!(frustum.intersects_sphere(Vec3::ZERO, 0.5));
+    }
+
+    #[test]
+    fn box_behind_the_camera_is_culled() {
+        let frustum = frustum();
+
+        assert!(!frustum.intersects_aabb(Vec3::new(-1.0, -1.0, 2.0), Vec3::new(1.0, 1.0, 4.0)));
+        assert!(!frustum.intersects_sphere(Vec3::new(0.0, 0.0, 3.0), 1.0));
+    }
+
+    #[test]
+    fn box_past_the_far_plane_is_culled() {
+        let frustum = frustum();
+
+        assert!(
+            !frustum.intersects_aabb(Vec3::new(-1.0, -1.0, -104.0), Vec3::new(1.0, 1.0, -102.0))
+        );
+        assert!(frustum.intersects_aabb(Vec3::new(-1.0, -1.0, -101.0), Vec3::new(1.0, 1.0, -99.0)));
+    }
+
+    #[test]
+    fn box_beside_a_side_plane_is_culled() {
+        let frustum = frustum();
+
+        // The right plane goes through x = -z.
+        assert!(!frustum.intersects_aabb(Vec3::new(12.0, -1.0, -11.0), Vec3::new(14.0, 1.0, -9.0)));
+        assert!(frustum.intersects_aabb(Vec3::new(9.0, -1.0, -11.0), Vec3::new(11.0, 1.0, -9.0)));
+        assert!(!frustum.intersects_sphere(Vec3::new(14.0, 0.0, -10.0), 2.0));
+        assert!(frustum.intersects_sphere(Vec3::new(12.0, 0.0, -10.0), 2.0));
+    }
+}
@@ -0,0 +1,111 @@
+//! Heights of the highest blocks of a column, kept up to date as blocks
+//! change so the top of the world doesn't have to be searched for.
+
+use crate::{block_states::BaseBlock, chunk::SIZE};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HeightmapKind {
+    /// Highest block which isn't air.
+    WorldSurface,
+    /// Highest block which has a collision shape or contains a fluid.
+    MotionBlocking,
+    /// Highest block which has a collision shape, fluids are looked through.
+    OceanFloor,
+}
+
+impl HeightmapKind {
+    pub const ALL: [Self; 3] = [Self::WorldSurface, Self::MotionBlocking, Self::OceanFloor];
+
+    /// Whether the heightmap stops at `block`, which is `None` for air.
+    pub fn is_opaque(self, block: Option<&BaseBlock>) -> bool {
+        let Some(block) = block else {
+            return false;
+        };
+
+        match self {
+            Self::WorldSurface => true,
+            Self::MotionBlocking => block.is_full_cube || block.block.fluid().is_some(),
+            Self::OceanFloor => block.is_full_cube && block.block.fluid().is_none(),
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// First free height above the top of every position of a column.
+#[derive(Debug, Copy, Clone)]
+pub struct Heightmap {
+    heights: [[i32; SIZE]; SIZE],
+    /// Height of positions without any block, the bottom of the world.
+    min_y: i32,
+}
+
+impl Heightmap {
+    pub fn new(min_y: i32) -> Self {
+        Self {
+            heights: [[min_y; SIZE]; SIZE],
+            min_y,
+        }
+    }
+
+    pub fn get(&self, x: usize, z: usize) -> i32 {
+        self.heights[z][x]
+    }
+
+    pub fn set(&mut self, x: usize, z: usize, height: i32) {
+        self.heights[z][x] = height;
+    }
+
+    /// Updates the height after the block at `y` changed, `is_opaque` tells
+    /// whether the block at a height is opaque now. Returns the previous
+    /// height if it changed.
+    pub fn update(
+        &mut self,
+        x: usize,
+        y: i32,
+        z: usize,
+        is_opaque: impl Fn(i32) -> bool,
+    ) -> Option<i32> {
+        let height = self.get(x, z);
+
+        let new_height = if y >= height && is_opaque(y) {
+            y + 1
+        } else if y + 1 == height && !is_opaque(y) {
+            // The top block is gone, the new one is somewhere below it.
+            (self.min_y..y)
+                .rev()
+                .find(|&y| is_opaque(y))
+                .map_or(self.min_y, |y| y + 1)
+        } else {
+            return None;
+        };
+
+        self.set(x, z, new_height);
+
+        Some(height)
+    }
+}
+
+/// One heightmap of every kind.
+#[derive(Debug, Copy, Clone)]
+pub struct Heightmaps {
+    maps: [Heightmap; 3],
+}
+
+impl Heightmaps {
+    pub fn new(min_y: i32) -> Self {
+        Self {
+            maps: [Heightmap::new(min_y); 3],
+        }
+    }
+
+    pub fn get(&self, kind: HeightmapKind) -> &Heightmap {
+        &self.maps[kind.index()]
+    }
+
+    pub fn get_mut(&mut self, kind: HeightmapKind) -> &mut Heightmap {
+        &mut self.maps[kind.index()]
+    }
+}
@@ -0,0 +1,103 @@
+//! Items, registered like blocks with an id and a name. Their models are the
+//! ones under `models/item`, which for blocks usually have the block model
+//! as their parent.
+
+use crate::{block_states::Tint, Minecraft};
+use macroquad::color::Color;
+use std::fmt;
+
+/// Color of grass in the inventory, the grass color map at a temperature of
+/// 0.5 and a downfall of 1.
+pub const GRASS_ITEM_COLOR: u32 = 0x7CBD6B;
+/// Color of foliage in the inventory, vanilla's default foliage color.
+pub const FOLIAGE_ITEM_COLOR: u32 = 0x48B518;
+
+pub trait Item {
+    /// Colors multiplied into the faces with a `tintindex`, which are the
+    /// layers for generated models. Items aren't in a biome, so they are
+    /// fixed colors.
+    fn tints(&self) -> Vec<Tint> {
+        Vec::new()
+    }
+
+    /// Block the item places.
+    fn block(&self) -> Option<&str> {
+        None
+    }
+}
+
+pub struct BaseItem {
+    pub tints: Vec<Tint>,
+    pub item: Box<dyn Item>,
+}
+
+impl fmt::Debug for BaseItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BaseItem")
+            .field("tints", &self.tints)
+            .field("block", &self.item.block())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Item + 'static> From<T> for BaseItem {
+    fn from(value: T) -> Self {
+        Self {
+            tints: value.tints(),
+            item: Box::new(value),
+        }
+    }
+}
+
+/// Item of a block, named like it.
+pub struct BlockItem {
+    pub block: &'static str,
+    pub tint: Option<u32>,
+}
+
+impl BlockItem {
+    pub fn new(block: &'static str) -> Self {
+        Self { block, tint: None }
+    }
+
+    pub fn tinted(block: &'static str, color: u32) -> Self {
+        Self {
+            block,
+            tint: Some(color),
+        }
+    }
+}
+
+impl Item for BlockItem {
+    fn tints(&self) -> Vec<Tint> {
+        self.tint
+            .map(|color| Tint::Color(Color::from_hex(color)))
+            .into_iter()
+            .collect()
+    }
+
+    fn block(&self) -> Option<&str> {
+        Some(self.block)
+    }
+}
+
+impl Minecraft {
+    pub fn init_items(&mut self) {
+        let items = [
+            BlockItem::new("dirt"),
+            BlockItem::tinted("grass_block", GRASS_ITEM_COLOR),
+            BlockItem::new("oak_log"),
+            BlockItem::tinted("oak_leaves", FOLIAGE_ITEM_COLOR),
+            BlockItem::new("glass"),
+            BlockItem::tinted("short_grass", GRASS_ITEM_COLOR),
+            BlockItem::new("dandelion"),
+            BlockItem::new("poppy"),
+            BlockItem::new("lever"),
+            BlockItem::new("black_stained_glass"),
+        ];
+
+        for (id, item) in items.into_iter().enumerate() {
+            self.register_item(id, item.block, item);
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Brightness of every combination of block light and sky light, looked up
+//! by the block shader. It's rebuilt when the sky darkens, so the meshes
+//! only store the light levels.
+
+use macroquad::{
+    color::{Color, BLANK},
+    math::{vec3, Vec3},
+    texture::{FilterMode, Image, Texture2D},
+};
+
+/// Light levels, the lightmap is as wide and high.
+pub const LIGHT_LEVELS: usize = 16;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LightmapSettings {
+    /// Vanilla's brightness option (called gamma in its code), 0 is moody and
+    /// 1 bright. It lifts the dark light levels.
+    pub brightness: f32,
+    /// Gamma of the display the colors get corrected for.
+    pub gamma: f32,
+}
+
+impl Default for LightmapSettings {
+    fn default() -> Self {
+        Self {
+            brightness: 0.5,
+            gamma: 1.0,
+        }
+    }
+}
+
+/// Brightness of a light level, vanilla's curve without ambient light.
+fn light_brightness(level: usize) -> f32 {
+    let f = level as f32 / 15.0;
+
+    f / (4.0 - 3.0 * f)
+}
+
+fn not_gamma(value: f32) -> f32 {
+    1.0 - (1.0 - value).powi(4)
+}
+
+/// Color of a block with `block_light` and `sky_light`, `sky_darken` is the
+/// share of the sky light left, see [`sky::sky_darken`](crate::sky::sky_darken).
+pub fn light_color(
+    block_light: usize,
+    sky_light: usize,
+    sky_darken: f32,
+    settings: &LightmapSettings,
+) -> Color {
+    let sky_brightness = light_brightness(sky_light) * (sky_darken * 0.95 + 0.05);
+    // Torches flicker in vanilla, this is the average of it.
+    let block_brightness = light_brightness(block_light) * 1.5;
+
+    // Block light is warm, the sky gets blue at night.
+    let block_color = vec3(
+        block_brightness,
+        block_brightness * ((block_brightness * 0.6 + 0.4) * 0.6 + 0.4),
+        block_brightness * (block_brightness * block_brightness * 0.6 + 0.4),
+    );
+    let sky_color = vec3(sky_darken, sky_darken, 1.0).lerp(Vec3::ONE, 0.35);
+    let gray = Vec3::splat(0.75);
+
+    let color = (block_color + sky_color * sky_brightness)
+        .lerp(gray, 0.04)
+        .clamp(Vec3::ZERO, Vec3::ONE);
+    let brightened = vec3(not_gamma(color.x), not_gamma(color.y), not_gamma(color.z));
+    let color = color
+        .lerp(brightened, settings.brightness.max(0.0))
+        .lerp(gray, 0.04)
+        .clamp(Vec3::ZERO, Vec3::ONE)
+        .powf(settings.gamma.recip());
+
+    Color::new(color.x, color.y, color.z, 1.0)
+}
+
+pub struct Lightmap {
+    pub settings: LightmapSettings,
+    image: Image,
+    texture: Texture2D,
+    /// Inputs of the current image, to skip rebuilding it.
+    built: Option<(f32, LightmapSettings)>,
+}
+
+impl Default for Lightmap {
+    fn default() -> Self {
+        let image = Image::gen_image_color(LIGHT_LEVELS as u16, LIGHT_LEVELS as u16, BLANK);
+        let texture = Texture2D::from_image(&image);
+
+        // Vertices between light levels blend smoothly.
+        texture.set_filter(FilterMode::Linear);
+
+        Self {
+            settings: LightmapSettings::default(),
+            image,
+            texture,
+            built: None,
+        }
+    }
+}
+
+impl Lightmap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Texture with the block light going right and the sky light going down.
+    pub fn texture(&self) -> &Texture2D {
+        &self.texture
+    }
+
+    pub fn update(&mut self, sky_darken: f32) {
+        if self.built == Some((sky_darken, self.settings)) {
+            return;
+        }
+
+        for sky_light in 0..LIGHT_LEVELS {
+            for block_light in 0..LIGHT_LEVELS {
+                self.image.set_pixel(
+                    block_light as u32,
+                    sky_light as u32,
+                    light_color(block_light, sky_light, sky_darken, &self.settings),
+                );
+            }
+        }
+
+        self.texture.update(&self.image);
+        self.built = Some((sky_darken, self.settings));
+    }
+}
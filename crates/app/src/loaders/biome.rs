@@ -1,4 +1,7 @@
-use crate::{chunk::BiomeId, util::colors::ColorMap};
+use crate::{
+    chunk::BiomeId,
+    util::{colors::ColorMap, string::StrExt},
+};
 use macroquad::color::Color;
 use std::ops::Index;
 
@@ -9,6 +12,11 @@ pub struct Biome {
     pub humidity: f32,
     pub grass_color: Color,
     pub foliage_color: Color,
+    pub water_color: Color,
+    /// Color of the sky straight up during the day.
+    pub sky_color: Color,
+    /// Color of the sky at the horizon, which distant blocks fade into.
+    pub fog_color: Color,
 }
 
 pub struct BiomeLoader {
@@ -44,8 +52,20 @@ impl BiomeLoader {
             humidity: 0.4,
             grass_color: grass_colors.get(0.8, 0.4),
             foliage_color: foliage_colors.get(0.8, 0.4),
+            water_color: Color::from_hex(0x3F76E4),
+            sky_color: Color::from_hex(0x78A7FF),
+            fog_color: Color::from_hex(0xC0D8FF),
         });
     }
+
+    pub fn get_id_by_name(&self, name: &str) -> Option<BiomeId> {
+        let name = name.strip_id();
+
+        self.biomes
+            .iter()
+            .position(|biome| biome.is_some_and(|biome| biome.name == name))
+            .map(|id| BiomeId { value: id as u8 })
+    }
 }
 
 impl Index<BiomeId> for BiomeLoader {
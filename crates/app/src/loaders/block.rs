@@ -3,6 +3,9 @@ use crate::util::string::StrExt;
 use data::model::Model;
 use std::{collections::HashMap, fs};
 
+/// Parent of models made of their `layerN` textures, like most items.
+pub const GENERATED: &str = "builtin/generated";
+
 #[derive(Default)]
 pub struct BlockLoader {
     blocks: HashMap<String, Model>,
@@ -17,6 +20,21 @@ impl BlockLoader {
         self.blocks.keys().collect()
     }
 
+    /// Whether the model or any of its parents is [`GENERATED`].
+    pub fn is_generated(&self, name: &str) -> bool {
+        let mut model = self.get(&name.as_id());
+
+        while let Some(parent) = model.and_then(|model| model.parent.as_deref()) {
+            if parent.strip_id() == GENERATED {
+                return true;
+            }
+
+            model = self.get(&parent.as_id());
+        }
+
+        false
+    }
+
     pub fn load(&mut self, textures: &mut TextureLoader, name: &str) -> Option<&Model> {
         let name = name.strip_id();
         let path = crate::asset!("models/{name}.json");
@@ -26,7 +44,12 @@ impl BlockLoader {
         )
         .expect("can't parse block model");
 
-        let parent = if let Some(parent) = block.parent.as_ref() {
+        // Built in parents have no file, their models are made in code.
+        let parent = if let Some(parent) = block
+            .parent
+            .as_ref()
+            .filter(|parent| !parent.strip_id().starts_with("builtin/"))
+        {
             if let Some(model) = self.blocks.get(parent) {
                 Some(model)
             } else {
@@ -38,6 +61,7 @@ impl BlockLoader {
 
         if let Some(parent) = parent {
             block.textures.extend(parent.textures.clone());
+            block.display.inherit(&parent.display);
 
             if block.elements.is_empty() {
                 block.elements = parent.elements.clone();
@@ -1,9 +1,10 @@
 use super::{BlockLoader, Model, ModelLoader, TextureLoader};
 use crate::{
     block_states::{BaseBlock, Block},
+    chunk::BlockState,
     util::string::StrExt,
 };
-use data::block_states::{BlockStates, Variant};
+use data::block_states::{BlockStates, Property, PropertyValue, Variant};
 use macroquad::{math::u16vec2, rand};
 use std::{collections::HashMap, fs};
 
@@ -11,7 +12,8 @@ use std::{collections::HashMap, fs};
 pub struct BlockStatesLoader {
     block_states: HashMap<String, BlockStates>,
     alias: HashMap<usize, String>,
-    registry: HashMap<String, (BaseBlock, Model)>,
+    states: HashMap<String, Vec<usize>>,
+    registry: HashMap<usize, (BaseBlock, Model)>,
 }
 
 impl BlockStatesLoader {
@@ -19,14 +21,100 @@ impl BlockStatesLoader {
         self.alias.get(&id).map(String::as_str)
     }
 
+    /// Returns the first registered state of the block.
+    pub fn get_id_by_name(&self, name: &str) -> Option<usize> {
+        self.states
+            .get(&name.as_id())
+            .and_then(|ids| ids.first().copied())
+    }
+
+    pub fn get_default_state(&self, name: &str) -> Option<BlockState> {
+        self.get_id_by_name(name)
+            .map(|id| BlockState { value: id as u16 })
+    }
+
     pub fn get_by_name(&self, name: &str) -> Option<&(BaseBlock, Model)> {
-        self.registry.get(&name.as_id())
+        self.get_id_by_name(name)
+            .and_then(|id| self.registry.get(&id))
     }
 
     pub fn get_by_id(&self, id: usize) -> Option<&(BaseBlock, Model)> {
-        self.alias
-            .get(&id)
-            .and_then(|alias| self.registry.get(alias))
+        self.registry.get(&id)
+    }
+
+    /// Whether both states are of the same block, whatever their properties.
+    pub fn is_same_block(&self, state: BlockState, other: BlockState) -> bool {
+        let name = self.get_name_by_id(state.value.into());
+
+        name.is_some() && name == self.get_name_by_id(other.value.into())
+    }
+
+    /// Returns all registered states of the block.
+    pub fn get_states(&self, name: &str) -> &[usize] {
+        self.states
+            .get(&name.as_id())
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Finds the state of the same block which differs from `state` only by the `key` property.
+    pub fn with_property(
+        &self,
+        state: BlockState,
+        key: &str,
+        value: impl Into<PropertyValue>,
+    ) -> Option<BlockState> {
+        let id = usize::from(state.value);
+        let (block, _) = self.registry.get(&id)?;
+        let property = Property::from(value);
+
+        if block.properties.get(key)? == &property {
+            return Some(state);
+        }
+
+        self.get_states(self.alias.get(&id)?)
+            .iter()
+            .find(|other| {
+                self.registry.get(other).is_some_and(|(other, _)| {
+                    other.properties.len() == block.properties.len()
+                        && other.properties.iter().all(|(name, value)| {
+                            if name == key {
+                                value == &property
+                            } else {
+                                block.properties.get(name) == Some(value)
+                            }
+                        })
+                })
+            })
+            .map(|&id| BlockState { value: id as u16 })
+    }
+
+    /// Finds the state of the block whose properties have the given values,
+    /// compared in their string form like in `facing=north`.
+    ///
+    /// Properties the block doesn't have are ignored and the ones not given
+    /// match any value, so a close state is found even if the block was
+    /// registered with fewer properties than vanilla has.
+    pub fn find_state<'a>(
+        &self,
+        name: &str,
+        properties: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Option<BlockState> {
+        let properties: Vec<_> = properties.into_iter().collect();
+
+        self.get_states(name)
+            .iter()
+            .find(|id| {
+                self.registry.get(id).is_some_and(|(block, _)| {
+                    properties.iter().all(|&(key, value)| {
+                        block
+                            .properties
+                            .get(key)
+                            .is_none_or(|property| property.value.to_string() == value)
+                    })
+                })
+            })
+            .map(|&id| BlockState { value: id as u16 })
     }
 
     pub fn register_block(
@@ -40,11 +128,24 @@ impl BlockStatesLoader {
         let block = block.into();
 
         if let Some(model) = self.load_model_with_state(blocks, textures, name, &block) {
-            self.alias.insert(id, name.to_string());
-            self.registry.insert(name.into(), (block, model));
+            self.register_block_with_model(id, name, block, model);
         }
     }
 
+    /// Registers a block drawn with `model` instead of the model its block
+    /// states pick.
+    pub fn register_block_with_model(
+        &mut self,
+        id: usize,
+        name: &str,
+        block: impl Into<BaseBlock>,
+        model: Model,
+    ) {
+        self.alias.insert(id, name.as_id());
+        self.states.entry(name.as_id()).or_default().push(id);
+        self.registry.insert(id, (block.into(), model));
+    }
+
     pub fn load_model_with_state(
         &self,
         blocks: &BlockLoader,
@@ -54,80 +155,44 @@ impl BlockStatesLoader {
     ) -> Option<Model> {
         let states = self.block_states.get(&name.as_id())?;
 
-        let mut model = None;
+        let load_model = |value: &data::block_states::Model| {
+            ModelLoader::load_rotated(
+                blocks,
+                textures,
+                &block.tints,
+                &value.model,
+                u16vec2(value.x, value.y),
+                value.uvlock,
+            )
+        };
+
+        let load_variant = |variant: &Variant| match variant {
+            Variant::One(value) => load_model(value),
+            Variant::Many(values) => load_model(&values[rand::gen_range(0, values.len())]),
+        };
 
         match states {
-            data::block_states::BlockStates::Variants(variants) => {
-                for (condition, variant) in variants {
-                    if model.is_some() {
-                        break;
-                    }
-
-                    if condition.properties.is_empty() {
-                        model = Some(match variant {
-                            Variant::One(value) => ModelLoader::load_rotated(
-                                blocks,
-                                textures,
-                                &block.tints,
-                                &value.model,
-                                u16vec2(value.x, value.y),
-                                value.uvlock,
-                            ),
-                            Variant::Many(values) => {
-                                let index = rand::gen_range(0, values.len());
-
-                                let value = &values[index];
-
-                                ModelLoader::load_rotated(
-                                    blocks,
-                                    textures,
-                                    &block.tints,
-                                    &value.model,
-                                    u16vec2(value.x, value.y),
-                                    value.uvlock,
-                                )
-                            }
-                        });
-                    } else {
-                        for (key, property) in &condition.properties {
-                            let prop = block.properties.get(key)?;
-
-                            if prop.value == property.value {
-                                model = Some(match variant {
-                                    Variant::One(value) => ModelLoader::load_rotated(
-                                        blocks,
-                                        textures,
-                                        &block.tints,
-                                        &value.model,
-                                        u16vec2(value.x, value.y),
-                                        value.uvlock,
-                                    ),
-                                    Variant::Many(values) => {
-                                        let index = rand::gen_range(0, values.len());
-
-                                        let value = &values[index];
-
-                                        ModelLoader::load_rotated(
-                                            blocks,
-                                            textures,
-                                            &block.tints,
-                                            &value.model,
-                                            u16vec2(value.x, value.y),
-                                            value.uvlock,
-                                        )
-                                    }
-                                });
-                            } else {
-                                break;
-                            }
-                        }
-                    }
-                }
-            }
-            data::block_states::BlockStates::Multipart(_) => todo!(),
+            BlockStates::Variants(variants) => variants
+                .iter()
+                .find(|(condition, _)| {
+                    condition
+                        .properties
+                        .iter()
+                        .all(|(key, property)| block.properties.get(key) == Some(property))
+                })
+                .map(|(_, variant)| load_variant(variant)),
+            BlockStates::Multipart(parts) => Some(
+                parts
+                    .iter()
+                    .filter(|part| part.when.matches(&block.properties))
+                    .map(|part| load_model(&part.apply))
+                    .reduce(|mut model, part| {
+                        model.extend(part);
+                        model
+                    })
+                    .unwrap_or_else(Model::empty),
+            ),
         }
-
-        model
     }
 
     pub fn load(&mut self, name: &str) {
@@ -0,0 +1,124 @@
+//! Elements of [`GENERATED`](super::block::GENERATED) models, like vanilla's
+//! item model generator: every `layerN` texture becomes a sprite one pixel
+//! thick, with sides along the edges of its opaque pixels.
+
+use super::TextureLoader;
+use data::model::{Direction, Element, Face, Model};
+use indexmap::IndexMap;
+use macroquad::math::{vec3, vec4, Vec4};
+
+/// Sprites are in the middle of the block, one pixel thick.
+const FRONT: f32 = 7.5;
+const BACK: f32 = 8.5;
+
+/// Faces of layer `layer`, tinted by the tint of the same index.
+fn layer_face(layer: usize, uv: Vec4) -> Face {
+    Face {
+        uv: Some(uv),
+        texture: format!("#layer{layer}"),
+        cullface: None,
+        rotation: 0,
+        tint_index: Some(layer),
+    }
+}
+
+pub fn generate_elements(model: &Model, textures: &TextureLoader) -> Vec<Element> {
+    let mut elements = Vec::new();
+
+    for layer in (0..).take_while(|layer| model.textures.contains_key(&format!("layer{layer}"))) {
+        elements.push(Element {
+            from: vec3(0.0, 0.0, FRONT),
+            to: vec3(16.0, 16.0, BACK),
+            rotation: None,
+            faces: IndexMap::from([
+                (
+                    Direction::Back,
+                    layer_face(layer, vec4(0.0, 0.0, 16.0, 16.0)),
+                ),
+                (
+                    Direction::Front,
+                    layer_face(layer, vec4(16.0, 0.0, 0.0, 16.0)),
+                ),
+            ]),
+        });
+
+        let Some(image) = textures
+            .get(&model.get_texture(format!("layer{layer}")))
+            .map(|texture| texture.get_texture_data())
+        else {
+            continue;
+        };
+
+        let (width, height) = (image.width() as i32, image.height() as i32);
+        let opaque = |x: i32, y: i32| {
+            (0..width).contains(&x)
+                && (0..height).contains(&y)
+                && image.get_pixel(x as u32, y as u32).a > 0.0
+        };
+        let (pixel_width, pixel_height) = (16.0 / width as f32, 16.0 / height as f32);
+
+        for y in 0..height {
+            for x in 0..width {
+                if !opaque(x, y) {
+                    continue;
+                }
+
+                // The texture goes down, the model up.
+                let (x1, x2) = (x as f32 * pixel_width, (x + 1) as f32 * pixel_width);
+                let (y1, y2) = (
+                    16.0 - (y + 1) as f32 * pixel_height,
+                    16.0 - y as f32 * pixel_height,
+                );
+                // Inside of the pixel, so the ones next to it don't bleed in.
+                let uv = vec4(
+                    x as f32 + 0.1,
+                    y as f32 + 0.1,
+                    x as f32 + 0.9,
+                    y as f32 + 0.9,
+                ) * vec4(pixel_width, pixel_height, pixel_width, pixel_height);
+
+                let sides = [
+                    (
+                        Direction::Top,
+                        (x, y - 1),
+                        vec3(x1, y2, FRONT),
+                        vec3(x2, y2, BACK),
+                    ),
+                    (
+                        Direction::Bottom,
+                        (x, y + 1),
+                        vec3(x1, y1, FRONT),
+                        vec3(x2, y1, BACK),
+                    ),
+                    (
+                        Direction::Left,
+                        (x - 1, y),
+                        vec3(x1, y1, FRONT),
+                        vec3(x1, y2, BACK),
+                    ),
+                    (
+                        Direction::Right,
+                        (x + 1, y),
+                        vec3(x2, y1, FRONT),
+                        vec3(x2, y2, BACK),
+                    ),
+                ];
+
+                for (direction, (x, y), from, to) in sides {
+                    if opaque(x, y) {
+                        continue;
+                    }
+
+                    elements.push(Element {
+                        from,
+                        to,
+                        rotation: None,
+                        faces: IndexMap::from([(direction, layer_face(layer, uv))]),
+                    });
+                }
+            }
+        }
+    }
+
+    elements
+}
@@ -0,0 +1,55 @@
+use super::{BlockLoader, Model, ModelLoader, TextureLoader};
+use crate::{
+    item::{BaseItem, Item},
+    util::string::StrExt,
+};
+use std::collections::HashMap;
+
+#[derive(Default)]
+pub struct ItemLoader {
+    alias: HashMap<usize, String>,
+    ids: HashMap<String, usize>,
+    registry: HashMap<usize, (BaseItem, Model)>,
+}
+
+impl ItemLoader {
+    pub fn get_name_by_id(&self, id: usize) -> Option<&str> {
+        self.alias.get(&id).map(String::as_str)
+    }
+
+    pub fn get_id_by_name(&self, name: &str) -> Option<usize> {
+        self.ids.get(&name.as_id()).copied()
+    }
+
+    pub fn get_by_name(&self, name: &str) -> Option<&(BaseItem, Model)> {
+        self.get_id_by_name(name)
+            .and_then(|id| self.registry.get(&id))
+    }
+
+    pub fn get_by_id(&self, id: usize) -> Option<&(BaseItem, Model)> {
+        self.registry.get(&id)
+    }
+
+    /// Registers an item with its model `models/item/{name}.json`, which has
+    /// to be loaded already.
+    pub fn register_item(
+        &mut self,
+        blocks: &BlockLoader,
+        textures: &TextureLoader,
+        id: usize,
+        name: &str,
+        item: impl Item + 'static,
+    ) {
+        let item: BaseItem = item.into();
+        let model = ModelLoader::load_item(
+            blocks,
+            textures,
+            &item.tints,
+            &format!("minecraft:item/{}", name.strip_id()),
+        );
+
+        self.alias.insert(id, name.as_id());
+        self.ids.insert(name.as_id(), id);
+        self.registry.insert(id, (item, model));
+    }
+}
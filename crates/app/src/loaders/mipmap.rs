@@ -0,0 +1,162 @@
+//! Mipmaps of textures, smaller copies sampled for faces far away from the
+//! camera so their pixels don't shimmer. Transparent pixels don't bleed their
+//! color into the ones next to them, and cutout textures like leaves keep as
+//! many of their pixels covered as the texture itself instead of fading away.
+
+use macroquad::{
+    miniquad::{gl, FilterMode, MipmapFilterMode, RawId},
+    texture::{Image, Texture2D},
+    window::get_internal_gl,
+};
+
+/// Alpha from which a pixel of a cutout texture counts as covered.
+const COVERAGE_CUTOFF: f32 = 128.0;
+
+/// Share of the pixels of `image` which are covered once their alpha is
+/// multiplied with `scale`.
+fn coverage(image: &Image, scale: f32) -> f32 {
+    let pixels = image.bytes.len() / 4;
+    let covered = image
+        .bytes
+        .chunks_exact(4)
+        .filter(|pixel| pixel[3] as f32 * scale >= COVERAGE_CUTOFF)
+        .count();
+
+    covered as f32 / pixels.max(1) as f32
+}
+
+/// Scales the alpha of `image` so that `target` of its pixels are covered.
+fn preserve_coverage(image: &mut Image, target: f32) {
+    let (mut low, mut high) = (0.0, 4.0);
+
+    for _ in 0..10 {
+        let scale = (low + high) / 2.0;
+
+        if coverage(image, scale) < target {
+            low = scale;
+        } else {
+            high = scale;
+        }
+    }
+
+    for pixel in image.bytes.chunks_exact_mut(4) {
+        pixel[3] = (pixel[3] as f32 * high).round().min(255.0) as u8;
+    }
+}
+
+/// Halves the size of `image` by averaging every 2x2 block of pixels. Colors
+/// are weighted by their alpha, so transparent pixels don't darken the edges.
+fn downsample(image: &Image) -> Image {
+    let (width, height) = ((image.width / 2).max(1), (image.height / 2).max(1));
+    let mut bytes = Vec::with_capacity(width as usize * height as usize * 4);
+
+    for y in 0..height {
+        for x in 0..width {
+            let (mut weighted, mut plain, mut alpha) = ([0.0; 3], [0.0; 3], 0.0);
+
+            for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                let x = (x * 2 + dx).min(image.width - 1) as usize;
+                let y = (y * 2 + dy).min(image.height - 1) as usize;
+                let i = (y * image.width as usize + x) * 4;
+                let pixel = &image.bytes[i..i + 4];
+
+                for channel in 0..3 {
+                    weighted[channel] += pixel[channel] as f32 * pixel[3] as f32;
+                    plain[channel] += pixel[channel] as f32;
+                }
+
+                alpha += pixel[3] as f32;
+            }
+
+            // Fully transparent blocks have no weights, they keep their color
+            // for linear filtering.
+            let rgb = if alpha > 0.0 {
+                weighted.map(|sum| sum / alpha)
+            } else {
+                plain.map(|sum| sum / 4.0)
+            };
+
+            bytes.extend(rgb.map(|channel| channel.round() as u8));
+            bytes.push((alpha / 4.0).round() as u8);
+        }
+    }
+
+    Image {
+        bytes,
+        width,
+        height,
+    }
+}
+
+/// Mipmaps of `image` from half its size down, at most `levels` of them and
+/// none smaller than a single pixel. Those of a `cutout` texture keep its
+/// coverage.
+pub fn generate(image: &Image, levels: u32, cutout: bool) -> Vec<Image> {
+    let target = cutout.then(|| coverage(image, 1.0));
+    let mut mipmaps: Vec<Image> = Vec::new();
+
+    for _ in 0..levels {
+        let previous = mipmaps.last().unwrap_or(image);
+
+        if previous.width == 1 && previous.height == 1 {
+            break;
+        }
+
+        let mut mipmap = downsample(previous);
+
+        if let Some(target) = target {
+            preserve_coverage(&mut mipmap, target);
+        }
+
+        mipmaps.push(mipmap);
+    }
+
+    mipmaps
+}
+
+/// Uploads `mipmaps` as the levels below `texture`, which is sampled from the
+/// nearest pixel of them like vanilla's block atlas. Without any, the texture
+/// isn't mipmapped.
+pub fn upload(texture: &Texture2D, mipmaps: &[Image]) {
+    let context = unsafe { get_internal_gl() }.quad_context;
+    let id = texture.raw_miniquad_id();
+
+    // miniquad can't upload levels of different sizes.
+    #[allow(irrefutable_let_patterns)]
+    let RawId::OpenGl(raw) = (unsafe { context.texture_raw_id(id) }) else {
+        return;
+    };
+
+    unsafe {
+        gl::glActiveTexture(gl::GL_TEXTURE0);
+        gl::glBindTexture(gl::GL_TEXTURE_2D, raw);
+        gl::glTexParameteri(
+            gl::GL_TEXTURE_2D,
+            gl::GL_TEXTURE_MAX_LEVEL,
+            mipmaps.len() as i32,
+        );
+
+        for (level, mipmap) in mipmaps.iter().enumerate() {
+            gl::glTexImage2D(
+                gl::GL_TEXTURE_2D,
+                level as i32 + 1,
+                gl::GL_RGBA as i32,
+                mipmap.width as i32,
+                mipmap.height as i32,
+                0,
+                gl::GL_RGBA,
+                gl::GL_UNSIGNED_BYTE,
+                mipmap.bytes.as_ptr() as *const _,
+            );
+        }
+    }
+
+    let mipmap_filter = if mipmaps.is_empty() {
+        MipmapFilterMode::None
+    } else {
+        MipmapFilterMode::Linear
+    };
+
+    // Also binds the texture miniquad thinks is bound again.
+    context.texture_set_min_filter(id, FilterMode::Nearest, mipmap_filter);
+}
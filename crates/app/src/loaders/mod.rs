@@ -1,18 +1,25 @@
 mod biome;
 mod block;
 mod block_states;
+mod generated;
+mod item;
+mod mipmap;
 mod model;
 mod partial_model;
+mod shader;
 mod texture;
 
 pub use self::{
     biome::{Biome, BiomeLoader},
     block::BlockLoader,
     block_states::BlockStatesLoader,
+    item::ItemLoader,
     model::{Model, ModelLoader},
     partial_model::{PartialModel, PartialModelLoader},
-    texture::TextureLoader,
+    shader::ShaderSources,
+    texture::{TextureLoader, TextureOpacity},
 };
+use crate::block_states::Tint;
 use data::model::Direction;
 use macroquad::models::Vertex;
 
@@ -39,5 +46,6 @@ pub struct Face {
     pub vertices: [Vertex; 4],
     pub cull_face: Option<Direction>,
     pub ao_face: Option<Direction>,
+    pub tint: Option<Tint>,
     pub texture: String,
 }
@@ -1,12 +1,18 @@
-use super::{BlockLoader, Face, Opacity, PartialModelLoader, TextureLoader};
-use data::model::Direction;
-use macroquad::{color::Color, math::U16Vec2};
+use super::{
+    generated, BlockLoader, Face, Opacity, PartialModel, PartialModelLoader, TextureLoader,
+    TextureOpacity,
+};
+use crate::{block_states::Tint, util::string::StrExt};
+use data::model::{Direction, Display};
+use macroquad::math::U16Vec2;
 
 #[derive(Debug, Clone)]
 pub struct Model {
     pub faces: Vec<Face>,
     pub tints: usize,
     pub opacity: Opacity,
+    /// Transforms of the first model it's made of.
+    pub display: Display,
 }
 
 impl Model {
@@ -15,6 +21,7 @@ impl Model {
             faces: Vec::new(),
             tints: 0,
             opacity: Opacity::Transparent,
+            display: Display::default(),
         }
     }
 
@@ -22,6 +29,17 @@ impl Model {
         self.faces.is_empty()
     }
 
+    /// Adds faces of another model, as done for each applied part of a multipart block state.
+    pub fn extend(&mut self, other: Model) {
+        if self.is_empty() {
+            self.display = other.display;
+        }
+
+        self.faces.extend(other.faces);
+        self.tints = self.tints.max(other.tints);
+        self.opacity = self.opacity.min(other.opacity);
+    }
+
     fn _rotate(&mut self, ix: usize, iy: usize, rot_mat: [i32; 4], uvlock: bool) {
         let (a, b, c, d) = (
             rot_mat[0] as f32,
@@ -68,18 +86,20 @@ impl Model {
                     continue;
                 }
 
+                // Turns the texture around the middle of the tile it's in, on top
+                // of the rotation of the face itself. The UVs are normalized.
                 let uvs = face.vertices.map(|x| x.uv);
                 let uv_min =
                     [0, 1].map(|i| (uvs[0][i]).min(uvs[1][i]).min(uvs[2][i]).min(uvs[3][i]));
-                let temp = uv_min.map(|x| (x / 16.0).floor() * 16.0);
+                let temp = uv_min.map(|x| x.floor());
                 let (u_base, v_base) = (temp[0], temp[1]);
 
                 for vertex in face.vertices.iter_mut() {
                     let uv = &mut vertex.uv;
-                    let (u, v) = (uv[0] - u_base - 8.0, uv[1] - v_base - 8.0);
+                    let (u, v) = (uv[0] - u_base - 0.5, uv[1] - v_base - 0.5);
 
-                    uv[0] = a * u - b * v + 8.0 + u_base;
-                    uv[1] = -c * u + d * v + 8.0 + v_base;
+                    uv[0] = a * u - b * v + 0.5 + u_base;
+                    uv[1] = -c * u + d * v + 0.5 + v_base;
                 }
             }
         }
@@ -102,7 +122,7 @@ impl ModelLoader {
     pub fn load_rotated(
         blocks: &BlockLoader,
         textures: &TextureLoader,
-        tints: &[Color],
+        tints: &[Tint],
         name: &str,
         rotation: U16Vec2,
         uvlock: bool,
@@ -118,11 +138,50 @@ impl ModelLoader {
     pub fn load(
         blocks: &BlockLoader,
         textures: &TextureLoader,
-        tints: &[Color],
+        tints: &[Tint],
+        name: &str,
+    ) -> Model {
+        Self::from_partial(
+            textures,
+            tints,
+            name,
+            PartialModelLoader::load(blocks, tints, name),
+        )
+    }
+
+    /// Loads a model of an item, the layers of `builtin/generated` models are
+    /// turned into sprites first.
+    pub fn load_item(
+        blocks: &BlockLoader,
+        textures: &TextureLoader,
+        tints: &[Tint],
         name: &str,
     ) -> Model {
-        let partial = PartialModelLoader::load(blocks, tints, name);
+        if !blocks.is_generated(name) {
+            return Self::load(blocks, textures, tints, name);
+        }
+
+        let mut item = blocks
+            .get(&name.as_id())
+            .unwrap_or_else(|| panic!("failed to get {name}"))
+            .clone();
 
+        item.elements = generated::generate_elements(&item, textures);
+
+        Self::from_partial(
+            textures,
+            tints,
+            name,
+            PartialModelLoader::from_model(&item, tints),
+        )
+    }
+
+    fn from_partial(
+        textures: &TextureLoader,
+        tints: &[Tint],
+        name: &str,
+        partial: PartialModel,
+    ) -> Model {
         let mut faces = partial.faces;
         let mut full_faces = [Opacity::Transparent; 6];
 
@@ -134,10 +193,11 @@ impl ModelLoader {
                     continue;
                 }
 
-                let opacity = match textures.get_alpha(&faces[i].texture) {
-                    0 => Opacity::TransparentSolid,
-                    255 => Opacity::Opaque,
-                    _ => Opacity::TranslucentSolid,
+                let opacity = match textures.get_opacity(&faces[i].texture) {
+                    TextureOpacity::Invisible => Opacity::Transparent,
+                    TextureOpacity::Cutout => Opacity::TransparentSolid,
+                    TextureOpacity::Translucent => Opacity::TranslucentSolid,
+                    TextureOpacity::Opaque => Opacity::Opaque,
                 };
 
                 if full_faces[face] < opacity {
@@ -161,6 +221,7 @@ impl ModelLoader {
             faces,
             tints: tints.len(),
             opacity: *full_faces.iter().min().unwrap(),
+            display: partial.display,
         }
     }
 }
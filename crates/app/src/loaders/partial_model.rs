@@ -1,9 +1,10 @@
 use super::{BlockLoader, Face};
-use crate::util::{string::StrExt, vectors::Vec4Ext};
-use macroquad::{
-    color::{Color, WHITE},
-    models::Vertex,
+use crate::{
+    block_states::Tint,
+    util::{string::StrExt, vectors::Vec4Ext},
 };
+use data::model::Display;
+use macroquad::{color::WHITE, models::Vertex};
 use std::array;
 
 #[derive(Clone)]
@@ -11,12 +12,13 @@ pub struct PartialModel {
     pub faces: Vec<Face>,
     pub full_faces: Vec<usize>,
     pub ambient_occlusion: bool,
+    pub display: Display,
 }
 
 pub struct PartialModelLoader;
 
 impl PartialModelLoader {
-    pub fn load(blocks: &BlockLoader, tints: &[Color], name: &str) -> PartialModel {
+    pub fn load(blocks: &BlockLoader, tints: &[Tint], name: &str) -> PartialModel {
         let block = blocks.get(&name.as_id()).unwrap_or_else(|| {
             panic!(
                 "failed to get {name}, available blocks: {:#?}",
@@ -24,10 +26,15 @@ impl PartialModelLoader {
             )
         });
 
+        Self::from_model(block, tints)
+    }
+
+    pub fn from_model(block: &data::model::Model, tints: &[Tint]) -> PartialModel {
         let mut model = PartialModel {
             faces: vec![],
             full_faces: vec![],
             ambient_occlusion: block.ambient_occlusion.unwrap_or(true),
+            display: block.display.clone(),
         };
 
         for element in &block.elements {
@@ -37,14 +44,14 @@ impl PartialModelLoader {
             for (direction, face) in &element.faces {
                 let index = direction.vertices();
                 let texture = block.get_texture(&face.texture);
-                let face_uvs = face.uv.unwrap_or_else(|| element.get_face_uvs(direction));
+                let face_uvs = face
+                    .uv
+                    .unwrap_or_else(|| element.get_face_uvs(direction))
+                    .normalized_uvs(16.0);
 
-                let uvs = direction.uvs(face_uvs.normalized_uvs(16.0));
+                let uvs = face.rotate_uvs(face_uvs, direction.uvs(face_uvs));
 
-                let color = face
-                    .tint_index
-                    .and_then(|index| tints.get(index).copied())
-                    .unwrap_or(WHITE);
+                let tint = face.tint_index.and_then(|index| tints.get(index).copied());
 
                 if face.cullface.as_ref() == Some(direction) && is_full_cube {
                     model.full_faces.push(model.faces.len());
@@ -54,7 +61,7 @@ impl PartialModelLoader {
                     vertices: array::from_fn(|i| Vertex {
                         uv: uvs[i],
                         position: corner_vertices[index[i]] / 16.0,
-                        color,
+                        color: WHITE,
                     }),
                     cull_face: face.cullface,
                     ao_face: if element.rotation.is_none() {
@@ -62,6 +69,7 @@ impl PartialModelLoader {
                     } else {
                         None
                     },
+                    tint,
                     texture,
                 });
             }
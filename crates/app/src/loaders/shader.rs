@@ -0,0 +1,23 @@
+use std::fs;
+
+/// GLSL sources of a shader, read from `shaders/{name}.vsh` and `.fsh`.
+#[derive(Debug, Clone)]
+pub struct ShaderSources {
+    pub vertex: String,
+    pub fragment: String,
+}
+
+impl ShaderSources {
+    pub fn load(name: &str) -> Self {
+        let read = |extension: &str| {
+            let path = crate::asset!("shaders/{name}.{extension}");
+
+            fs::read_to_string(&path).unwrap_or_else(|_| panic!("there is no shader called {path}"))
+        };
+
+        Self {
+            vertex: read("vsh"),
+            fragment: read("fsh"),
+        }
+    }
+}
@@ -1,13 +1,195 @@
+use super::mipmap;
 use crate::util::string::StrExt;
-use macroquad::texture::{FilterMode, Texture2D};
+use data::texture::{Animation, TextureMeta};
+use macroquad::{
+    math::Rect,
+    texture::{FilterMode, Image, Texture2D},
+};
 use std::{collections::HashMap, fs};
 
+/// Share of the pixels of a cutout texture which may be partially
+/// transparent, so a few smoothed edges don't make it translucent.
+const MAX_CUTOUT_PARTIAL: f32 = 1.0 / 64.0;
+
+/// How much of a texture can be seen through, from the alpha of all of its
+/// pixels.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TextureOpacity {
+    /// No pixel is visible, or the texture is missing.
+    Invisible,
+    /// Pixels are either opaque or transparent, like leaves.
+    Cutout,
+    /// Pixels blend with what's behind them, like stained glass.
+    Translucent,
+    Opaque,
+}
+
+impl TextureOpacity {
+    pub fn of(image: &Image) -> Self {
+        let mut histogram = [0usize; 256];
+
+        for pixel in image.bytes.chunks_exact(4) {
+            histogram[pixel[3] as usize] += 1;
+        }
+
+        let pixels: usize = histogram.iter().sum();
+        let (transparent, opaque) = (histogram[0], histogram[255]);
+        let partial = pixels - transparent - opaque;
+
+        if transparent == pixels {
+            Self::Invisible
+        } else if opaque == pixels {
+            Self::Opaque
+        } else if partial as f32 <= pixels as f32 * MAX_CUTOUT_PARTIAL {
+            Self::Cutout
+        } else {
+            Self::Translucent
+        }
+    }
+}
+
+/// Texture whose frames change over the game ticks, described by the
+/// `.mcmeta` file next to it.
+struct AnimatedTexture {
+    texture: Texture2D,
+    frames: Vec<Image>,
+    /// Indices into `frames` and the game ticks they are shown for, in order.
+    sequence: Vec<(usize, u32)>,
+    interpolate: bool,
+    opacity: TextureOpacity,
+    mip_levels: u32,
+    /// Position in `sequence`, and game ticks it has been shown for.
+    current: usize,
+    ticks: u32,
+}
+
+impl AnimatedTexture {
+    fn new(image: &Image, animation: &Animation, mip_levels: u32) -> Self {
+        let size = image.width.min(image.height) as u32;
+        let frame_width = animation.width.unwrap_or(size).max(1);
+        let frame_height = animation.height.unwrap_or(size).max(1);
+        let columns = (image.width as u32 / frame_width).max(1);
+        let count = columns * (image.height as u32 / frame_height).max(1);
+
+        // Frames go from left to right, then from top to bottom.
+        let frames: Vec<_> = (0..count)
+            .map(|i| {
+                image.sub_image(Rect::new(
+                    (i % columns * frame_width) as f32,
+                    (i / columns * frame_height) as f32,
+                    frame_width as f32,
+                    frame_height as f32,
+                ))
+            })
+            .collect();
+
+        let sequence: Vec<_> = match &animation.frames {
+            Some(frames) => frames
+                .iter()
+                .filter(|frame| frame.index() < count)
+                .map(|frame| {
+                    (
+                        frame.index() as usize,
+                        frame.time(animation.frame_time).max(1),
+                    )
+                })
+                .collect(),
+            None => (0..frames.len())
+                .map(|i| (i, animation.frame_time.max(1)))
+                .collect(),
+        };
+
+        let first = sequence.first().map_or(0, |&(frame, _)| frame);
+
+        Self {
+            texture: Texture2D::from_image(&frames[first]),
+            frames,
+            sequence,
+            interpolate: animation.interpolate,
+            opacity: TextureOpacity::of(image),
+            mip_levels,
+            current: 0,
+            ticks: 0,
+        }
+    }
+
+    /// Frame currently shown.
+    fn frame(&self) -> &Image {
+        let frame = self
+            .sequence
+            .get(self.current)
+            .map_or(0, |&(frame, _)| frame);
+
+        &self.frames[frame]
+    }
+
+    /// Shows `image` and the mipmaps of it.
+    fn show(&self, image: &Image) {
+        self.texture.update(image);
+
+        let mipmaps = mipmap::generate(
+            image,
+            self.mip_levels,
+            self.opacity == TextureOpacity::Cutout,
+        );
+
+        mipmap::upload(&self.texture, &mipmaps);
+    }
+
+    fn tick(&mut self) {
+        if self.sequence.len() < 2 {
+            return;
+        }
+
+        let (frame, time) = self.sequence[self.current];
+
+        self.ticks += 1;
+
+        if self.ticks >= time {
+            self.ticks = 0;
+            self.current = (self.current + 1) % self.sequence.len();
+
+            let (frame, _) = self.sequence[self.current];
+
+            self.show(&self.frames[frame]);
+        } else if self.interpolate {
+            let (next, _) = self.sequence[(self.current + 1) % self.sequence.len()];
+            let t = self.ticks as f32 / time as f32;
+            let mut image = self.frames[frame].clone();
+
+            // Alpha stays the one of the current frame, like in vanilla.
+            for (pixel, next) in image
+                .bytes
+                .chunks_exact_mut(4)
+                .zip(self.frames[next].bytes.chunks_exact(4))
+            {
+                for channel in 0..3 {
+                    let (a, b) = (pixel[channel] as f32, next[channel] as f32);
+
+                    pixel[channel] = (a + (b - a) * t).round() as u8;
+                }
+            }
+
+            self.show(&image);
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct TextureLoader {
-    textures: HashMap<String, (Texture2D, u8)>,
+    /// Textures with their opacity and whether they have mipmaps.
+    textures: HashMap<String, (Texture2D, TextureOpacity, bool)>,
+    animations: Vec<AnimatedTexture>,
+    mip_levels: u32,
 }
 
 impl TextureLoader {
+    /// Sets the levels of mipmaps generated for the textures loaded from now
+    /// on, none by default.
+    pub fn set_mip_levels(&mut self, levels: u32) {
+        self.mip_levels = levels;
+    }
+
     pub fn contains(&self, texture: &str) -> bool {
         self.textures.contains_key(&texture.as_id())
     }
@@ -17,35 +199,68 @@ impl TextureLoader {
 
         let path = crate::asset!("textures/{texture}.png");
 
-        let bytes = fs::read(path).expect("failed to read texture");
-        let data = Texture2D::from_file_with_format(&bytes[..], None);
+        let bytes = fs::read(&path).expect("failed to read texture");
+        let meta: TextureMeta = fs::read(format!("{path}.mcmeta")).map_or_else(
+            |_| TextureMeta::default(),
+            |meta| serde_json::from_slice(&meta).expect("can't parse texture metadata"),
+        );
+
+        let image =
+            Image::from_file_with_format(&bytes[..], None).expect("failed to decode texture");
+
+        // Of all frames, so it doesn't change while the texture is animated.
+        let opacity = TextureOpacity::of(&image);
+
+        let (data, first_frame) = match meta.animation {
+            Some(animation) => {
+                let animated = AnimatedTexture::new(&image, &animation, self.mip_levels);
+                let data = animated.texture.clone();
+                let first_frame = animated.frame().clone();
+
+                self.animations.push(animated);
+
+                (data, first_frame)
+            }
+            None => (Texture2D::from_image(&image), image),
+        };
 
         data.set_filter(FilterMode::Nearest);
 
-        let alpha = data
-            .get_texture_data()
-            .get_image_data()
-            .iter()
-            .map(|pixel| pixel[3])
-            .min()
-            .unwrap_or(0);
+        let mipmaps = mipmap::generate(
+            &first_frame,
+            self.mip_levels,
+            opacity == TextureOpacity::Cutout,
+        );
+
+        // After the filter, which turns the mipmaps off.
+        mipmap::upload(&data, &mipmaps);
 
-        self.textures.insert(texture.as_id(), (data, alpha));
+        self.textures
+            .insert(texture.as_id(), (data, opacity, !mipmaps.is_empty()));
+    }
+
+    /// Advances the animated textures by one game tick.
+    pub fn tick(&mut self) {
+        for animation in &mut self.animations {
+            animation.tick();
+        }
     }
 
     pub fn get(&self, texture: &str) -> Option<&Texture2D> {
         self.textures
             .get(&texture.as_id())
-            .map(|(texture, _)| texture)
+            .map(|(texture, ..)| texture)
     }
 
-    pub fn get_alpha(&self, texture: &str) -> u8 {
-        let texture = texture.as_id();
+    pub fn has_mipmaps(&self, texture: &str) -> bool {
+        self.textures
+            .get(&texture.as_id())
+            .is_some_and(|&(_, _, mipmapped)| mipmapped)
+    }
 
-        if let Some((_, alpha)) = self.textures.get(&texture) {
-            *alpha
-        } else {
-            0
-        }
+    pub fn get_opacity(&self, texture: &str) -> TextureOpacity {
+        self.textures
+            .get(&texture.as_id())
+            .map_or(TextureOpacity::Invisible, |&(_, opacity, _)| opacity)
     }
 }
@@ -1,20 +1,91 @@
+pub mod anvil;
+pub mod ao;
 pub mod block_states;
 pub mod chunk;
+pub mod display;
+pub mod fluid;
+pub mod frustum;
+pub mod heightmap;
+pub mod item;
+pub mod lightmap;
 pub mod loaders;
+pub mod map;
+pub mod mesh;
+pub mod plant;
+pub mod redstone;
+pub mod region;
+pub mod save;
+pub mod shader;
+pub mod sky;
+pub mod structure;
+pub mod tick;
+pub mod time;
 pub mod util;
-
-use block_states::Block;
-use chunk::{BiomeId, BlockState, Chunk, ChunkColumn, ChunkManager, LightLevel};
-use data::model::Direction;
-use loaders::{BiomeLoader, BlockLoader, BlockStatesLoader, TextureLoader};
-use macroquad::{models, prelude::*};
+pub mod visibility;
+pub mod world;
+pub mod worldgen;
+
+use anvil::{AnvilWorld, FALLBACK_BIOME};
+use ao::{AoSample, FaceLight};
+use block_states::{Block, Tint};
+use chunk::{BiomeId, Chunk, ChunkColumn, ChunkManager, WorldHeight};
+use data::model::DisplayContext;
+use frustum::Frustum;
+use lightmap::Lightmap;
+use loaders::{BiomeLoader, BlockLoader, BlockStatesLoader, ItemLoader, Model, TextureLoader};
+use macroquad::prelude::*;
+use map::MapRenderer;
+use mesh::{BlockVertex, MeshBuilder};
 use miniquad::gl;
-use noise::{NoiseFn, SuperSimplex};
-use std::{array, cell::RefCell, env::current_dir};
+use save::{LevelData, PlayerData, WorldSave, AUTOSAVE_INTERVAL};
+use shader::{BlockShader, Fog};
+use sky::{SkyColors, SkyRenderer};
+use std::{
+    array,
+    collections::HashSet,
+    env::{self, current_dir},
+    io,
+    path::PathBuf,
+};
+use structure::StructureTool;
+use time::{ParseTimeError, TimeCommand};
 use util::string::StrExt;
+use visibility::SectionVisibility;
+use world::World;
+use worldgen::WorldgenSettings;
 
 const MOVE_SPEED: f32 = 0.1;
 const LOOK_SPEED: f32 = 0.1;
+/// Duration of a single game tick in seconds.
+const TICK_TIME: f32 = 0.05;
+/// How far away the player can interact with blocks.
+const REACH: f32 = 5.0;
+/// Size of the slots of the hotbar, in pixels.
+const SLOT_SIZE: f32 = 56.0;
+/// Keys selecting the slots of the hotbar.
+const HOTBAR_KEYS: [KeyCode; 9] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+];
+const DEFAULT_SEED: u32 = 0xFF0FE0;
+/// Height of the camera above the block the player spawns in.
+const EYE_HEIGHT: f32 = 1.1;
+/// Size of the map on the screen in pixels.
+const MAP_SCALE: f32 = 192.0;
+/// Columns loaded around the player in every direction.
+const LOAD_RADIUS: i32 = 1;
+/// Distance the fog hides blocks entirely at, the edge of the columns loaded
+/// around the player.
+const FOG_END: f32 = (LOAD_RADIUS * 16 + 8) as f32;
+/// Levels of mipmaps of the textures, like vanilla's default.
+const MIP_LEVELS: u32 = 4;
 
 #[macro_export]
 macro_rules! asset {
@@ -48,9 +119,19 @@ fn conf() -> Conf {
 #[derive(Default)]
 pub struct Minecraft {
     block_states: BlockStatesLoader,
+    items: ItemLoader,
     blocks: BlockLoader,
     textures: TextureLoader,
+    biomes: BiomeLoader,
     world: ChunkManager,
+    level: LevelData,
+    save: Option<WorldSave>,
+    import: Option<AnvilWorld>,
+    worldgen: WorldgenSettings,
+    structure_tool: StructureTool,
+    /// Columns which failed to load, they're left out until the player moves
+    /// away from them.
+    failed_columns: HashSet<IVec2>,
 }
 
 impl Minecraft {
@@ -59,6 +140,11 @@ impl Minecraft {
             .register_block(&self.blocks, &self.textures, id, name, block);
     }
 
+    pub fn register_item(&mut self, id: usize, name: &str, item: impl item::Item + 'static) {
+        self.items
+            .register_item(&self.blocks, &self.textures, id, name, item);
+    }
+
     pub fn load_block_model(&mut self, name: &str) {
         self.blocks.load(&mut self.textures, name);
     }
@@ -67,44 +153,277 @@ impl Minecraft {
         self.block_states.load(name);
     }
 
-    fn create_world(&mut self, seed: u32) {
-        let noise_fn = SuperSimplex::new(seed);
+    pub fn world_mut(&mut self) -> World<'_> {
+        World::new(&self.block_states, &mut self.world)
+    }
+
+    pub fn tick(&mut self) {
+        self.world_mut().tick();
+        self.textures.tick();
+    }
+
+    /// Runs the arguments of a `/time` command like `set noon` or `add 1000`,
+    /// returns the value vanilla reports back.
+    pub fn time_command(&mut self, command: &str) -> Result<u64, ParseTimeError> {
+        Ok(command
+            .parse::<TimeCommand>()?
+            .execute(&mut self.world.clock))
+    }
 
-        let mut chunks = vec![];
+    /// Interacts with the block the player is looking at.
+    pub fn use_block(&mut self, origin: Vec3, direction: Vec3) -> bool {
+        let mut world = self.world_mut();
 
-        chunks.push(Chunk {
-            blocks: array_16x16x16::<BlockState, 16, _>(|x, y, z| {
-                let (x, y, z) = (x as f64, y as f64, z as f64);
-                let noise = noise_fn.get([x / 30.0, y * 15.0, z / 30.0]) * 80.0 + 64.0 + y * 1.7;
+        match world.raycast(origin, direction, REACH) {
+            Some((position, _)) => world.use_block(position),
+            None => false,
+        }
+    }
 
-                if noise < 100.0 {
-                    if y == 15.0 {
-                        BlockState { value: 2 }
-                    } else {
-                        BlockState { value: 1 }
-                    }
-                } else {
-                    BlockState { value: 0 }
+    /// Marks the block the player is looking at as a corner of the box the
+    /// structure tool copies.
+    pub fn mark_structure_corner(&mut self, origin: Vec3, direction: Vec3) -> bool {
+        match self.world_mut().raycast(origin, direction, REACH) {
+            Some((position, _)) => {
+                self.structure_tool.mark(position);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Copies the box between the marked corners, returns its size.
+    pub fn copy_structure(&mut self) -> Option<IVec3> {
+        self.structure_tool
+            .copy(&self.block_states, &self.world)
+            .map(|template| template.size)
+    }
+
+    /// Pastes the copied structure onto the face of the block the player is
+    /// looking at, returns the number of changed blocks.
+    pub fn paste_structure(&mut self, origin: Vec3, direction: Vec3) -> io::Result<usize> {
+        let mut world = World::new(&self.block_states, &mut self.world);
+
+        match world.raycast(origin, direction, REACH) {
+            Some((position, face)) => self
+                .structure_tool
+                .paste(&mut world, position + face.as_vec3()),
+            None => Ok(0),
+        }
+    }
+
+    /// Draws `model` like an icon of the inventory, `size` pixels wide around
+    /// `center` on the screen.
+    fn draw_icon(&self, model: &Model, biome: BiomeId, center: Vec2, size: f32) {
+        display::draw_model(
+            model,
+            &self.textures,
+            &self.biomes[biome],
+            DisplayContext::Gui,
+            // The screen goes down.
+            Mat4::from_translation(center.extend(0.0)) * Mat4::from_scale(vec3(size, -size, size)),
+        );
+    }
+
+    /// Rebuilds meshes and visibility of the sections changed since the last call.
+    fn update_world_mesh(&mut self) {
+        for position in self.world.take_dirty_chunks() {
+            let Some((buffer, chunks, biome_columns)) =
+                self.world.get_chunk_and_neighbors(position)
+            else {
+                continue;
+            };
+
+            let mut mesh = MeshBuilder::new();
+
+            self.get_world_mesh(position, &mut mesh, chunks, biome_columns);
+
+            *buffer.borrow_mut() = mesh.build();
+
+            let visibility = SectionVisibility::compute(chunks[1][1][1], |state| {
+                self.block_states
+                    .get_by_id(state.value.into())
+                    .is_some_and(|(block, _)| block.is_opaque_cube)
+            });
+
+            self.world.set_visibility(position, visibility);
+        }
+    }
+
+    /// Takes columns missing from the save out of the vanilla world in
+    /// `directory`, has to be called before [`Minecraft::open_world`].
+    fn import_world(&mut self, directory: PathBuf) -> io::Result<()> {
+        self.import = Some(AnvilWorld::open(directory)?);
+
+        Ok(())
+    }
+
+    /// Opens the world saved in `directory`, creating it if it doesn't exist yet.
+    fn open_world(&mut self, directory: PathBuf) -> io::Result<&LevelData> {
+        let save = WorldSave::open(directory)?;
+        // Generated worlds spawn on their surface, which is only known once
+        // the columns around the spawn exist.
+        let saved_level = save.load_level()?;
+        let find_spawn = self.import.is_none() && saved_level.is_none();
+
+        self.level = match saved_level {
+            Some(level) => level,
+            None => {
+                let spawn = match &self.import {
+                    Some(import) => import.spawn()?,
+                    None => IVec3::ZERO,
+                };
+
+                LevelData {
+                    seed: DEFAULT_SEED,
+                    spawn,
+                    player: PlayerData {
+                        position: spawn.as_vec3() + vec3(0.0, EYE_HEIGHT, 0.0),
+                        yaw: 1.18,
+                        pitch: 0.0,
+                    },
+                    time: 0,
+                    day_time: 0,
+                    height: WorldHeight::default(),
                 }
-            }),
-            light_levels: [[[LightLevel { value: 0 }; 16]; 16]; 16],
-        });
-
-        self.world.add_chunk_column(
-            ivec2(0, 0),
-            ChunkColumn {
-                chunks,
-                biomes: [[BiomeId { value: 1 }; 16]; 16],
-                buffers: array::from_fn(|_| RefCell::new(Vec::new())),
+            }
+        };
+        self.world.ticks.set_time(self.level.time);
+        self.world.clock.set_day_time(self.level.day_time);
+        self.save = Some(save);
+
+        self.update_loaded_columns(self.level.player.position);
+
+        if find_spawn {
+            if let Some(spawn) = self.world_mut().find_spawn(IVec2::ZERO) {
+                self.level.spawn = spawn;
+                self.level.player.position = spawn.as_vec3() + vec3(0.0, EYE_HEIGHT, 0.0);
+            }
+        }
+
+        Ok(&self.level)
+    }
+
+    /// Loads the columns within [`LOAD_RADIUS`] of the one the player at
+    /// `player` is in, and saves and unloads the ones further away.
+    ///
+    /// A column which fails to load is reported once and then skipped, so
+    /// it's neither retried every tick nor replaced by a generated one.
+    fn update_loaded_columns(&mut self, player: Vec3) {
+        let center = player.floor().as_ivec3().div_euclid(IVec3::splat(16)).xz();
+
+        self.failed_columns
+            .retain(|&position| (position - center).abs().max_element() <= LOAD_RADIUS);
+
+        for position in self.world.column_positions() {
+            if (position - center).abs().max_element() <= LOAD_RADIUS {
+                continue;
+            }
+
+            if let Some((column, true)) = self.world.remove_chunk_column(position) {
+                if let Some(save) = &self.save {
+                    save.save_column(position, &column, &self.block_states);
+                }
+            }
+        }
+
+        for dz in -LOAD_RADIUS..=LOAD_RADIUS {
+            for dx in -LOAD_RADIUS..=LOAD_RADIUS {
+                let position = center + ivec2(dx, dz);
+
+                if self.world.get_chunk_column(position).is_some()
+                    || self.failed_columns.contains(&position)
+                {
+                    continue;
+                }
+
+                if let Err(err) = self.load_chunk_column(position) {
+                    eprintln!("failed to load column {position}: {err}");
+
+                    self.failed_columns.insert(position);
+                }
+            }
+        }
+    }
+
+    /// Streams in a column from the save, importing or generating it if it
+    /// was never saved.
+    fn load_chunk_column(&mut self, position: IVec2) -> io::Result<()> {
+        let saved = match &self.save {
+            Some(save) => save.load_column(position, self.level.height, &self.block_states)?,
+            None => None,
+        };
+
+        // Heightmaps aren't stored, generated columns come with them.
+        let column = match saved {
+            Some(mut column) => {
+                column.compute_heightmaps(&self.block_states);
+
+                column
+            }
+            None => match self.import_chunk_column(position)? {
+                Some(mut column) => {
+                    // Imported once, later changes only go to our own save.
+                    self.world.mark_unsaved(position);
+                    column.compute_heightmaps(&self.block_states);
+
+                    column
+                }
+                None => self.generate_chunk_column(position),
             },
-        );
+        };
+
+        self.world.add_chunk_column(position, column);
+
+        Ok(())
+    }
+
+    fn import_chunk_column(&mut self, position: IVec2) -> io::Result<Option<ChunkColumn>> {
+        match &mut self.import {
+            Some(import) => import.load_column(
+                position,
+                self.level.height,
+                &self.block_states,
+                &self.biomes,
+            ),
+            None => Ok(None),
+        }
+    }
+
+    /// Queues the columns changed since the last save and the level data to be written.
+    fn save(&mut self, player: PlayerData) {
+        let Some(save) = &self.save else {
+            return;
+        };
+
+        for position in self.world.take_unsaved_columns() {
+            if let Some(column) = self.world.get_chunk_column(position) {
+                save.save_column(position, column, &self.block_states);
+            }
+        }
+
+        self.level.player = player;
+        self.level.time = self.world.ticks.time();
+        self.level.day_time = self.world.clock.day_time();
+
+        save.save_level(self.level.clone());
+    }
+
+    fn generate_chunk_column(&self, position: IVec2) -> ChunkColumn {
+        worldgen::generate_column(
+            &self.block_states,
+            &self.biomes,
+            &self.worldgen,
+            self.level.seed,
+            self.level.height,
+            position,
+        )
     }
 
     fn get_world_mesh(
         &self,
         position: IVec3,
-        buffer: &mut Vec<(Vec3, Mesh)>,
-        biomes: &BiomeLoader,
+        buffer: &mut MeshBuilder,
         chunks: [[[&Chunk; 3]; 3]; 3],
         column_biomes: [[Option<&[[BiomeId; 16]; 16]>; 3]; 3],
     ) {
@@ -121,7 +440,8 @@ impl Minecraft {
                             z.wrapping_add(dz).wrapping_add(16),
                         );
 
-                        let chunk = chunks[x / 16][y / 16][z / 16];
+                        // The neighbors are ordered like the blocks of a section.
+                        let chunk = chunks[y / 16][z / 16][x / 16];
 
                         let (x, y, z) = (x % 16, y % 16, z % 16);
 
@@ -130,14 +450,27 @@ impl Minecraft {
 
                     let this_block = at(ivec3(0, 0, 0)).0;
 
-                    let (_, model) = match self.block_states.get_by_id(this_block.value.into()) {
+                    let (block, model) = match self.block_states.get_by_id(this_block.value.into())
+                    {
                         Some((name, model)) => (name, model),
                         None => continue,
                     };
 
                     let block_xyz = Vec3::from_array([x, y, z].map(|x| x as f32)) + chunk_xyz;
 
-                    for face in model.faces.iter() {
+                    let fluid_faces;
+                    let faces = match block.block.fluid() {
+                        Some(fluid) => {
+                            fluid_faces = fluid::get_fluid_faces(fluid, |offset| {
+                                self.block_states.get_by_id(at(offset).0.value.into())
+                            });
+
+                            &fluid_faces
+                        }
+                        None => &model.faces,
+                    };
+
+                    for face in faces.iter() {
                         if let Some(cull_face) = face.cull_face {
                             let (neighbor, _) = at(cull_face.as_vec3());
 
@@ -145,143 +478,119 @@ impl Minecraft {
                                 .block_states
                                 .get_by_id(neighbor.value.into())
                                 .is_some_and(|(_, model)| model.opacity.is_opaque())
+                                || block.block.skip_rendering(
+                                    &self.block_states,
+                                    this_block,
+                                    neighbor,
+                                    cull_face,
+                                )
                             {
                                 continue;
                             }
                         }
 
-                        let v = face.vertices.map(|vertex| {
-                            // Average tint and light around the vertex.
-                            let (mut rgb, mut num_colors) = (
-                                vertex.color,
-                                match vertex.color {
-                                    WHITE => 0.0,
-                                    _ => 1.0,
-                                },
-                            );
-                            let (mut sum_light_level, mut num_light_level) = (0.0, 0.0);
+                        let sample = |offset: IVec3| {
+                            let (neighbor, light_level) = at(offset);
+
+                            AoSample {
+                                occludes: self
+                                    .block_states
+                                    .get_by_id(neighbor.value.into())
+                                    .is_some_and(|(_, model)| model.opacity.is_solid()),
+                                block_light: light_level.block_light(),
+                                sky_light: light_level.sky_light(),
+                            }
+                        };
+
+                        let (light, shade) = match face.ao_face {
+                            Some(ao_face) => {
+                                let flush = ao::is_flush(
+                                    ao_face,
+                                    face.vertices.map(|vertex| vertex.position),
+                                );
+
+                                (
+                                    FaceLight::smooth(ao_face, flush, sample),
+                                    ao::shade(ao_face),
+                                )
+                            }
+                            // Lit by the block it looks into if it's on the side of the block.
+                            None => {
+                                let offset = face.cull_face.map_or(IVec3::ZERO, |d| d.as_vec3());
+
+                                (FaceLight::flat(sample(offset)), 1.0)
+                            }
+                        };
+
+                        let mut v = face.vertices.map(|vertex| {
+                            // Average biome tint around the vertex.
+                            let (mut sum_tint, mut num_tints) = (Vec4::ZERO, 0.0);
 
                             let rounded_xyz = IVec3::from_array(
                                 vertex.position.to_array().map(|x| x.round() as i32),
                             );
 
-                            let (dx, dy, dz) = (rounded_xyz[0], rounded_xyz[1], rounded_xyz[2]);
+                            let (dx, dz) = (rounded_xyz[0], rounded_xyz[2]);
 
                             for &dx in [dx - 1, dx].iter() {
                                 for &dz in [dz - 1, dz].iter() {
-                                    for &dy in [dy - 1, dy].iter() {
-                                        let (neighbor, light_level) = at(ivec3(dx, dy, dz));
-                                        let light_level =
-                                            light_level.block_light().max(light_level.sky_light());
-                                        let mut light_level = light_level as f32;
-
-                                        let use_block = match face.ao_face {
-                                            Some(ao_face) => {
-                                                let mut above = true;
-                                                for (i, &a) in
-                                                    ao_face.as_vec3().to_array().iter().enumerate()
-                                                {
-                                                    let da = [dx, dy, dz][i];
-                                                    let va = rounded_xyz[i];
-                                                    let above_da = match a {
-                                                        -1 => va - 1,
-                                                        1 => va,
-                                                        _ => da,
-                                                    };
-                                                    if da != above_da {
-                                                        above = false;
-                                                        break;
-                                                    }
-                                                }
-
-                                                if above
-                                                    && self
-                                                        .block_states
-                                                        .get_by_id(neighbor.value.into())
-                                                        .is_some_and(|(_, model)| {
-                                                            model.opacity.is_solid()
-                                                        })
-                                                {
-                                                    light_level = 0.0;
-                                                }
-
-                                                above
-                                            }
-                                            None => !self
-                                                .block_states
-                                                .get_by_id(neighbor.value.into())
-                                                .is_some_and(|(_, model)| {
-                                                    model.opacity.is_opaque()
-                                                }),
-                                        };
-
-                                        if use_block {
-                                            sum_light_level += light_level;
-                                            num_light_level += 1.0;
-                                        }
-                                    }
-
-                                    if vertex.color == WHITE {
+                                    let Some(tint) = face.tint else {
                                         continue;
-                                    }
+                                    };
 
                                     let (x, z) = (
                                         x.wrapping_add(dx as usize).wrapping_add(16),
                                         z.wrapping_add(dz as usize).wrapping_add(16),
                                     );
                                     let biome = match column_biomes[z / 16][x / 16] {
-                                        Some(biome) => biomes[biome[z % 16][x % 16]],
+                                        Some(biome) => self.biomes[biome[z % 16][x % 16]],
                                         None => continue,
                                     };
 
-                                    rgb = Color::from_vec(match vertex.color {
-                                        WHITE => continue,
-                                        _ => WHITE.to_vec() + biome.grass_color.to_vec(),
-                                        // model::Tint::Foliage => biome.foliage_color,
-                                    });
+                                    sum_tint += match tint {
+                                        Tint::Color(_) => continue,
+                                        Tint::Grass => biome.grass_color,
+                                        Tint::Foliage => biome.foliage_color,
+                                        Tint::Water => biome.water_color,
+                                    }
+                                    .to_vec();
 
-                                    num_colors += 1.0;
+                                    num_tints += 1.0;
                                 }
                             }
 
-                            let light_factor = 0.2
-                                + if num_light_level != 0.0 {
-                                    sum_light_level / num_light_level / 15.0 * 0.8
-                                } else {
-                                    0.0
-                                };
-
-                            // Up, North and South, East and West, Down have different lighting.
-                            let light_factor = light_factor
-                                * match face.ao_face {
-                                    Some(ao_face) => match ao_face {
-                                        Direction::Top => 1.0,
-                                        Direction::Front | Direction::Back => 0.8,
-                                        Direction::Right | Direction::Left => 0.6,
-                                        Direction::Bottom => 0.5,
-                                    },
-                                    None => 1.0,
-                                };
-
-                            let [r, g, b] = [rgb.r, rgb.g, rgb.b]
-                                .map(|x| x * light_factor / num_colors - 2.0 / 255.0);
-
-                            models::Vertex {
+                            let rgb = match face.tint {
+                                Some(Tint::Color(color)) => color,
+                                Some(_) if num_tints != 0.0 => {
+                                    Color::from_vec(sum_tint / num_tints)
+                                }
+                                _ => vertex.color,
+                            };
+
+                            let vertex_light = light.at(vertex.position);
+
+                            BlockVertex {
                                 position: block_xyz + vertex.position,
                                 uv: vertex.uv,
-                                // No clue why the difference of 2 exists.
-                                color: Color { r, g, b, a: rgb.a },
+                                tint: rgb.into(),
+                                // Scaled up so the averages keep their precision.
+                                light: [vertex_light.block_light, vertex_light.sky_light]
+                                    .map(|level| (level / 15.0 * 255.0).round() as u8),
+                                ao: (vertex_light.brightness * 255.0).round() as u8,
+                                shade: (shade * 255.0) as u8,
                             }
                         });
 
-                        buffer.push((
-                            block_xyz,
-                            Mesh {
-                                vertices: v.into(),
-                                indices: vec![0, 1, 2, 2, 3, 0],
-                                texture: self.textures.get(&face.texture.as_id()).cloned(),
-                            },
-                        ))
+                        if ao::flip_diagonal(v.map(|vertex| vertex.ao as f32)) {
+                            v.rotate_left(1);
+                        }
+
+                        buffer.push_quad(
+                            self.textures.get(&face.texture.as_id()),
+                            !block.block.render_layer().is_mipmapped()
+                                && self.textures.has_mipmaps(&face.texture),
+                            v,
+                        );
                     }
                 }
             }
@@ -328,48 +637,80 @@ impl AABB {
 async fn main() {
     let mut app = Minecraft::default();
 
+    app.textures.set_mip_levels(MIP_LEVELS);
+
     app.load_block_model("minecraft:block/dirt");
     app.load_block_model("minecraft:block/grass_block");
     app.load_block_model("minecraft:block/glass");
     app.load_block_model("minecraft:block/black_stained_glass");
     app.load_block_model("minecraft:block/crafting_table");
     app.load_block_model("minecraft:block/lever");
+    app.load_block_model("minecraft:block/lever_on");
+    app.load_block_model("minecraft:block/redstone_dust_dot");
+    app.load_block_model("minecraft:block/redstone_dust_side0");
+    app.load_block_model("minecraft:block/redstone_dust_side1");
+    app.load_block_model("minecraft:block/redstone_dust_side_alt0");
+    app.load_block_model("minecraft:block/redstone_dust_side_alt1");
+    app.load_block_model("minecraft:block/redstone_dust_up");
+    app.load_block_model("minecraft:block/water");
+    app.load_block_model("minecraft:block/lava");
+    app.load_block_model("minecraft:block/oak_log");
+    app.load_block_model("minecraft:block/oak_leaves");
+    app.load_block_model("minecraft:block/short_grass");
+    app.load_block_model("minecraft:block/dandelion");
+    app.load_block_model("minecraft:block/poppy");
+    app.load_block_model("minecraft:block/unknown");
+    app.load_block_model("minecraft:item/dirt");
+    app.load_block_model("minecraft:item/grass_block");
+    app.load_block_model("minecraft:item/oak_log");
+    app.load_block_model("minecraft:item/oak_leaves");
+    app.load_block_model("minecraft:item/glass");
+    app.load_block_model("minecraft:item/short_grass");
+    app.load_block_model("minecraft:item/dandelion");
+    app.load_block_model("minecraft:item/poppy");
+    app.load_block_model("minecraft:item/lever");
+    app.load_block_model("minecraft:item/black_stained_glass");
 
     app.load_block_states("dirt");
     app.load_block_states("grass_block");
     app.load_block_states("black_stained_glass");
     app.load_block_states("glass");
+    app.load_block_states("lever");
+    app.load_block_states("redstone_wire");
+    app.load_block_states("water");
+    app.load_block_states("lava");
+    app.load_block_states("oak_log");
+    app.load_block_states("oak_leaves");
+    app.load_block_states("short_grass");
+    app.load_block_states("dandelion");
+    app.load_block_states("poppy");
+    app.load_block_states("unknown");
 
     app.init();
+    app.init_items();
+    app.biomes.init();
+    app.worldgen = WorldgenSettings::load();
+
+    // A vanilla world can be given to import its columns.
+    if let Some(directory) = env::args_os().nth(1) {
+        app.import_world(directory.into())
+            .expect("failed to open the world to import");
+    }
 
-    app.create_world(0xFF0FE0);
-
-    let mut pending_chunks = vec![];
-
-    app.world
-        .each_chunk_and_neighbors(|position, buffer, chunks, biomes| {
-            pending_chunks.push((position, buffer, chunks, biomes));
-        });
-
-    let biomes = BiomeLoader::new();
+    let level = app
+        .open_world(current_dir().unwrap().join("saves").join("world"))
+        .expect("failed to open the world");
+    let player = level.player;
 
-    for (position, buffer, chunks, biome_columns) in pending_chunks {
-        app.get_world_mesh(
-            position,
-            &mut buffer.borrow_mut(),
-            &biomes,
-            chunks,
-            biome_columns,
-        );
-    }
+    app.update_world_mesh();
 
     let mut x = 0.0;
     let mut switch = false;
     let bounds = 8.0;
 
     let world_up = vec3(0.0, 1.0, 0.0);
-    let mut yaw: f32 = 1.18;
-    let mut pitch: f32 = 0.0;
+    let mut yaw: f32 = player.yaw;
+    let mut pitch: f32 = player.pitch;
 
     let mut front = vec3(
         yaw.cos() * pitch.cos(),
@@ -380,10 +721,17 @@ async fn main() {
     let mut right = front.cross(world_up).normalize();
     let mut up = right.cross(front).normalize();
 
-    let mut position = vec3(0.0, 1.0, 0.0);
+    let mut position = player.position;
     let mut last_mouse_position: Vec2 = mouse_position().into();
 
     let mut grabbed = true;
+    let mut show_map = false;
+    let mut selected_slot = 0;
+    let mut map = MapRenderer::new();
+    let sky = SkyRenderer::new();
+    let block_shader = BlockShader::new();
+    let mut lightmap = Lightmap::new();
+    let mut tick_time = 0.0;
 
     set_cursor_grab(grabbed);
     show_mouse(false);
@@ -399,6 +747,12 @@ async fn main() {
         let delta = get_frame_time();
 
         if is_key_pressed(KeyCode::Escape) {
+            app.save(PlayerData {
+                position,
+                yaw,
+                pitch,
+            });
+
             break;
         }
         if is_key_pressed(KeyCode::Tab) {
@@ -406,6 +760,29 @@ async fn main() {
             set_cursor_grab(grabbed);
             show_mouse(!grabbed);
         }
+        if is_key_pressed(KeyCode::M) {
+            show_map = !show_map;
+        }
+        if let Some(slot) = HOTBAR_KEYS.iter().position(|&key| is_key_pressed(key)) {
+            selected_slot = slot;
+        }
+        if grabbed && is_mouse_button_pressed(MouseButton::Right) {
+            app.use_block(position, front);
+        }
+        if grabbed && is_key_pressed(KeyCode::B) {
+            app.mark_structure_corner(position, front);
+        }
+        if is_key_pressed(KeyCode::C) {
+            app.copy_structure();
+        }
+        if grabbed && is_key_pressed(KeyCode::V) {
+            if let Err(err) = app.paste_structure(position, front) {
+                eprintln!("failed to paste the structure: {err}");
+            }
+        }
+        if is_key_pressed(KeyCode::R) {
+            app.structure_tool.settings.rotation = app.structure_tool.settings.rotation.clockwise();
+        }
 
         if is_key_down(KeyCode::W) {
             position += front * MOVE_SPEED;
@@ -448,7 +825,44 @@ async fn main() {
             }
         }
 
-        clear_background(LIGHTGRAY);
+        tick_time += delta;
+
+        while tick_time >= TICK_TIME {
+            tick_time -= TICK_TIME;
+
+            app.tick();
+
+            app.update_loaded_columns(position);
+
+            if app.world.ticks.time() % AUTOSAVE_INTERVAL == 0 {
+                app.save(PlayerData {
+                    position,
+                    yaw,
+                    pitch,
+                });
+            }
+        }
+
+        app.update_world_mesh();
+
+        let clock = app.world.clock;
+        let day_time = clock.day_time() as f32
+            + if clock.daylight_cycle {
+                tick_time / TICK_TIME
+            } else {
+                0.0
+            };
+        let angle = sky::celestial_angle(day_time);
+        let biome = app
+            .world
+            .get_biome(position.round().as_ivec3())
+            .unwrap_or(FALLBACK_BIOME);
+        let sky_colors = SkyColors::new(&app.biomes[biome], angle);
+
+        // Darkening the sky light only changes the lightmap, not the meshes.
+        lightmap.update(sky::sky_darken(angle));
+
+        clear_background(sky_colors.fog);
 
         // Going 3d!
 
@@ -463,78 +877,46 @@ async fn main() {
             gl::glCullFace(gl::GL_BACK);
         }
 
-        let forward = (camera.position - camera.target).normalize();
-        let right = camera.up.cross(forward);
-        let projection_mat = camera.matrix();
-        let view_mat = Mat4::from_cols(
-            vec4(right.x, camera.up.x, forward.x, 0.0),
-            vec4(right.y, camera.up.y, forward.y, 0.0),
-            vec4(right.z, camera.up.z, forward.z, 0.0),
-            vec4(
-                -right.dot(camera.position),
-                camera.up.dot(camera.position),
-                forward.dot(camera.position),
-                1.0,
-            ),
-        );
-
-        let mut num_chunks: usize = 0;
-        let mut num_sorted_chunks: usize = 0;
-        let mut num_total_chunks: usize = 0;
-
-        app.world.each_chunk(|position, _, buffer| {
-            let buffer = buffer.borrow_mut();
-
-            num_total_chunks += 1;
-
-            let inf = f32::INFINITY;
-            let mut bb_min = [inf, inf, inf];
-            let mut bb_max = [-inf, -inf, -inf];
-            let xyz = position.as_vec3() * 16.0;
-
-            for &dx in [0.0, 16.0].iter() {
-                for &dy in [0.0, 16.0].iter() {
-                    for &dz in [0.0, 16.0].iter() {
-                        let v = xyz + vec3(dx, dy, dz);
-                        let xyzw = vec4(
-                            view_mat.row(0).dot(vec4(v[0], v[1], v[2], 1.0)),
-                            view_mat.row(1).dot(vec4(v[0], v[1], v[2], 1.0)),
-                            view_mat.row(2).dot(vec4(v[0], v[1], v[2], 1.0)),
-                            view_mat.row(3).dot(vec4(v[0], v[1], v[2], 1.0)),
-                        );
+        sky.draw(position, angle, sky_colors);
 
-                        let v = vec4(
-                            projection_mat.row(0).dot(xyzw),
-                            projection_mat.row(1).dot(xyzw),
-                            projection_mat.row(2).dot(xyzw),
-                            projection_mat.row(3).dot(xyzw),
-                        );
-                        let xyz = vec3(v[0], v[1], v[2]) * (1.0 / v[3]);
+        // Like vanilla the fog fades in over a tenth of the distance.
+        let mut pass = block_shader.begin(
+            camera.matrix(),
+            position,
+            Fog {
+                color: sky_colors.fog,
+                start: FOG_END - (FOG_END / 10.0).clamp(4.0, 64.0),
+                end: FOG_END,
+            },
+            &lightmap,
+        );
 
-                        bb_min = array::from_fn(|i| bb_min[i].min(xyz[i]));
-                        bb_max = array::from_fn(|i| bb_max[i].max(xyz[i]));
+        let frustum = Frustum::from_matrix(camera.matrix());
+        let camera_section = (position + 0.5)
+            .floor()
+            .as_ivec3()
+            .div_euclid(IVec3::splat(16));
+
+        match visibility::visible_sections(&app.world, camera_section, &frustum) {
+            Some(sections) => {
+                for section in sections {
+                    if let Some(buffer) = app.world.get_buffer(section) {
+                        pass.draw(&buffer.borrow());
                     }
                 }
             }
+            // Outside of the world everything in the view is drawn.
+            None => app.world.each_chunk(|position, _, buffer| {
+                // Blocks are centered on their integer position.
+                let min = position.as_vec3() * 16.0 - 0.5;
 
-            let cull_bits: [bool; 3] = array::from_fn(|i| {
-                let (min, max) = (bb_min[i], bb_max[i]);
-
-                min.signum() == max.signum() && min.abs().min(max.abs()) >= 1.0
-            });
-
-            if !cull_bits.iter().any(|&cull| cull) {
-                for (_, mesh) in buffer.iter() {
-                    draw_mesh(mesh);
+                if frustum.intersects_aabb(min, min + 16.0) {
+                    pass.draw(&buffer.borrow());
                 }
+            }),
+        }
 
-                num_chunks += 1;
-
-                if bb_min[0] < 0.0 && bb_max[0] > 0.0 || bb_min[1] < 0.0 && bb_max[1] > 0.0 {
-                    num_sorted_chunks += 1;
-                }
-            }
-        });
+        drop(pass);
 
         // Back to screen space, render some text
 
@@ -568,6 +950,133 @@ async fn main() {
             BLACK,
         );
 
+        // Icon of the block in front of the camera.
+        let target = app
+            .world_mut()
+            .raycast(position, front, REACH)
+            .and_then(|(block, _)| app.world.get_block(block))
+            .and_then(|state| app.block_states.get_by_id(state.value.into()));
+        let mut icons: Vec<_> = target
+            .map(|(_, model)| (model, vec2(50.0, 140.0), 64.0))
+            .into_iter()
+            .collect();
+
+        let hotbar_left = (screen_width() - SLOT_SIZE * HOTBAR_KEYS.len() as f32) / 2.0;
+        let hotbar_top = screen_height() - SLOT_SIZE - 10.0;
+
+        for slot in 0..HOTBAR_KEYS.len() {
+            let left = hotbar_left + slot as f32 * SLOT_SIZE;
+
+            draw_rectangle(
+                left,
+                hotbar_top,
+                SLOT_SIZE,
+                SLOT_SIZE,
+                Color::new(0.0, 0.0, 0.0, 0.4),
+            );
+
+            if slot == selected_slot {
+                draw_rectangle_lines(left, hotbar_top, SLOT_SIZE, SLOT_SIZE, 4.0, WHITE);
+            }
+
+            if let Some((_, model)) = app.items.get_by_id(slot) {
+                let center = vec2(left, hotbar_top) + SLOT_SIZE / 2.0;
+
+                icons.push((model, center, SLOT_SIZE - 12.0));
+            }
+        }
+
+        if let Some(name) = app.items.get_name_by_id(selected_slot) {
+            draw_text(name, hotbar_left, hotbar_top - 10.0, 30.0, BLACK);
+        }
+
+        // The faces of the icons are sorted rather than culled.
+        unsafe {
+            get_internal_gl().flush();
+            gl::glDisable(gl::GL_CULL_FACE);
+        }
+
+        for (model, center, size) in icons {
+            app.draw_icon(model, biome, center, size);
+        }
+
+        unsafe {
+            get_internal_gl().flush();
+            gl::glEnable(gl::GL_CULL_FACE);
+        }
+
+        if show_map {
+            let center = position.round().as_ivec3();
+
+            map.update(&app.world, &app.block_states, ivec2(center.x, center.z));
+            map.draw(screen_width() - MAP_SCALE - 10.0, 10.0, MAP_SCALE);
+        }
+
         next_frame().await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        block_states::DirtBlock,
+        chunk::EMPTY_CHUNK,
+        loaders::{Face, Opacity},
+    };
+    use data::model::Direction;
+    use macroquad::models::Vertex;
+
+    /// Registers dirt as an opaque block with only its top face.
+    fn app() -> Minecraft {
+        let mut app = Minecraft::default();
+        let top = Face {
+            vertices: [(-0.5, -0.5), (-0.5, 0.5), (0.5, 0.5), (0.5, -0.5)].map(|(x, z)| Vertex {
+                position: vec3(x, 0.5, z),
+                uv: Vec2::ZERO,
+                color: WHITE,
+            }),
+            cull_face: Some(Direction::Top),
+            ao_face: Some(Direction::Top),
+            tint: None,
+            texture: "dirt".into(),
+        };
+        let model = Model {
+            faces: vec![top],
+            opacity: Opacity::Opaque,
+            ..Model::empty()
+        };
+
+        app.block_states
+            .register_block_with_model(1, "dirt", DirtBlock::default(), model);
+
+        app
+    }
+
+    #[test]
+    fn faces_are_culled_by_the_section_above() {
+        let app = app();
+        let dirt = app.block_states.get_default_state("dirt").unwrap();
+
+        let mut below = *EMPTY_CHUNK;
+        let mut above = *EMPTY_CHUNK;
+        below.blocks[15][0][0] = dirt;
+
+        let quads = |above: &Chunk| {
+            let mut chunks = [[[EMPTY_CHUNK; 3]; 3]; 3];
+            chunks[1][1][1] = &below;
+            chunks[2][1][1] = above;
+
+            let mut buffer = MeshBuilder::new();
+            app.get_world_mesh(IVec3::ZERO, &mut buffer, chunks, [[None; 3]; 3]);
+
+            buffer.quad_count()
+        };
+
+        assert_eq!(quads(&above), 1);
+
+        above.blocks[0][0][0] = dirt;
+
+        assert_eq!(quads(&above), 0);
+    }
+}
@@ -0,0 +1,181 @@
+//! Top-down map of the columns around the player, colored like the maps of
+//! vanilla Minecraft.
+
+use crate::{
+    chunk::{section_position, ChunkManager},
+    heightmap::HeightmapKind,
+    loaders::BlockStatesLoader,
+};
+use data::model::Direction;
+use macroquad::{
+    color::{Color, BLANK, WHITE},
+    math::{ivec3, vec2, IVec2, Vec3Swizzles},
+    texture::{draw_texture_ex, DrawTextureParams, FilterMode, Image, Texture2D},
+};
+
+/// Width and height of the map in blocks.
+pub const MAP_SIZE: usize = 64;
+
+/// Base color of a block on the map, vanilla's `MapColor` of it.
+fn map_color(name: &str) -> Color {
+    Color::from_hex(match name {
+        "minecraft:grass_block" => 0x7fb238,
+        "minecraft:dirt" => 0x976d4d,
+        "minecraft:oak_log" => 0x8f7748,
+        "minecraft:oak_leaves"
+        | "minecraft:short_grass"
+        | "minecraft:dandelion"
+        | "minecraft:poppy" => 0x007c00,
+        "minecraft:water" => 0x4040ff,
+        "minecraft:lava" | "minecraft:redstone_wire" => 0xff0000,
+        "minecraft:glass" | "minecraft:black_stained_glass" => 0xffffff,
+        _ => 0x707070,
+    })
+}
+
+pub struct MapRenderer {
+    image: Image,
+    texture: Texture2D,
+}
+
+impl Default for MapRenderer {
+    fn default() -> Self {
+        let image = Image::gen_image_color(MAP_SIZE as u16, MAP_SIZE as u16, BLANK);
+        let texture = Texture2D::from_image(&image);
+
+        texture.set_filter(FilterMode::Nearest);
+
+        Self { image, texture }
+    }
+}
+
+impl MapRenderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Redraws the map centered on the block at `center`, columns which
+    /// aren't loaded stay transparent.
+    pub fn update(
+        &mut self,
+        chunks: &ChunkManager,
+        block_states: &BlockStatesLoader,
+        center: IVec2,
+    ) {
+        let offset = center - MAP_SIZE as i32 / 2;
+
+        for z in 0..MAP_SIZE {
+            for x in 0..MAP_SIZE {
+                let position = offset + IVec2::new(x as i32, z as i32);
+                let color = Self::surface_color(chunks, block_states, position).unwrap_or(BLANK);
+
+                self.image.set_pixel(x as u32, z as u32, color);
+            }
+        }
+
+        self.texture.update(&self.image);
+    }
+
+    fn surface_height(chunks: &ChunkManager, position: IVec2) -> Option<i32> {
+        let (section, [x, _, z]) = section_position(ivec3(position.x, 0, position.y));
+
+        chunks
+            .get_chunk_column(IVec2::new(section.x, section.z))
+            .map(|column| column.heightmap(HeightmapKind::WorldSurface).get(x, z))
+    }
+
+    /// Color of the highest block, brighter if it's above the block north of
+    /// it and darker if it's below, which makes slopes visible.
+    fn surface_color(
+        chunks: &ChunkManager,
+        block_states: &BlockStatesLoader,
+        position: IVec2,
+    ) -> Option<Color> {
+        let height = Self::surface_height(chunks, position)?;
+        let state = chunks.get_block(ivec3(position.x, height - 1, position.y))?;
+        let name = block_states.get_name_by_id(state.value.into())?;
+
+        let north = position + Direction::Front.as_vec3().xz();
+
+        let brightness = match Self::surface_height(chunks, north) {
+            Some(north) if north < height => 1.0,
+            Some(north) if north > height => 180.0 / 255.0,
+            _ => 220.0 / 255.0,
+        };
+        let color = map_color(name);
+
+        Some(Color::new(
+            color.r * brightness,
+            color.g * brightness,
+            color.b * brightness,
+            1.0,
+        ))
+    }
+
+    /// Draws the map with its top left corner at `x` and `y`.
+    pub fn draw(&self, x: f32, y: f32, size: f32) {
+        draw_texture_ex(
+            &self.texture,
+            x,
+            y,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(vec2(size, size)),
+                ..Default::default()
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        block_states::DirtBlock,
+        chunk::{BiomeId, ChunkColumn, WorldHeight, SIZE},
+        loaders::Model,
+    };
+
+    #[test]
+    fn slopes_are_shaded_against_the_block_north_of_them() {
+        let mut block_states = BlockStatesLoader::default();
+
+        block_states.register_block_with_model(1, "dirt", DirtBlock::default(), Model::empty());
+
+        let dirt = block_states.get_default_state("dirt").unwrap();
+        let mut chunks = ChunkManager::new();
+
+        chunks.add_chunk_column(
+            IVec2::ZERO,
+            ChunkColumn::new(WorldHeight::default(), [[BiomeId { value: 0 }; SIZE]; SIZE]),
+        );
+
+        for z in 0..SIZE as i32 {
+            for x in 0..SIZE as i32 {
+                chunks.set_block(ivec3(x, 0, z), dirt, &block_states);
+            }
+        }
+
+        // A step up towards the north, which is +z.
+        for z in 9..SIZE as i32 {
+            chunks.set_block(ivec3(8, 1, z), dirt, &block_states);
+        }
+
+        let brightness = |chunks: &ChunkManager, z: i32| {
+            let color =
+                MapRenderer::surface_color(chunks, &block_states, IVec2::new(8, z)).unwrap();
+
+            (color.r / map_color("minecraft:dirt").r * 255.0).round()
+        };
+
+        assert_eq!(brightness(&chunks, 7), 220.0);
+        assert_eq!(brightness(&chunks, 8), 180.0);
+        assert_eq!(brightness(&chunks, 9), 220.0);
+
+        // A single block higher than the one north of it.
+        chunks.set_block(ivec3(8, 2, 12), dirt, &block_states);
+
+        assert_eq!(brightness(&chunks, 11), 180.0);
+        assert_eq!(brightness(&chunks, 12), 255.0);
+    }
+}
@@ -0,0 +1,153 @@
+//! Meshes of sections. Their vertices carry the tint, light levels, ambient
+//! occlusion and shade apart, which macroquad's vertices have no room for,
+//! so they are uploaded and drawn with [`BlockShader`](crate::shader::BlockShader)
+//! directly.
+
+use macroquad::{
+    math::{Vec2, Vec3},
+    miniquad::{BufferId, BufferSource, BufferType, BufferUsage},
+    texture::Texture2D,
+    window::get_internal_gl,
+};
+
+/// Vertices of a single batch, indices are 16 bit.
+const MAX_BATCH_VERTICES: usize = u16::MAX as usize + 1;
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct BlockVertex {
+    pub position: Vec3,
+    pub uv: Vec2,
+    /// Biome tint of the face, white if it has none.
+    pub tint: [u8; 4],
+    /// Block light and sky light, scaled from 0 to 15 up to 0 to 255 so the
+    /// averages of the corners keep their precision.
+    pub light: [u8; 2],
+    /// Share of the blocks around the corner that don't occlude it.
+    pub ao: u8,
+    /// Brightness of the direction the face looks in.
+    pub shade: u8,
+}
+
+/// Faces sharing a texture, uploaded to the GPU.
+pub struct MeshBatch {
+    pub texture: Option<Texture2D>,
+    /// Whether the mipmaps the texture has are turned off while drawing, see
+    /// [`BlockRenderLayer::is_mipmapped`](crate::block_states::BlockRenderLayer::is_mipmapped).
+    pub without_mipmaps: bool,
+    pub vertex_buffer: BufferId,
+    pub index_buffer: BufferId,
+    pub index_count: i32,
+}
+
+impl Drop for MeshBatch {
+    fn drop(&mut self) {
+        let gl = unsafe { get_internal_gl() };
+
+        gl.quad_context.delete_buffer(self.vertex_buffer);
+        gl.quad_context.delete_buffer(self.index_buffer);
+    }
+}
+
+#[derive(Default)]
+pub struct SectionMesh {
+    batches: Vec<MeshBatch>,
+}
+
+impl SectionMesh {
+    pub fn batches(&self) -> &[MeshBatch] {
+        &self.batches
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.batches.is_empty()
+    }
+}
+
+/// Faces of a [`MeshBatch`] before they are uploaded.
+struct BatchBuilder {
+    texture: Option<Texture2D>,
+    without_mipmaps: bool,
+    vertices: Vec<BlockVertex>,
+    indices: Vec<u16>,
+}
+
+/// Collects the faces of a section, sorted by their texture.
+#[derive(Default)]
+pub struct MeshBuilder {
+    batches: Vec<BatchBuilder>,
+}
+
+impl MeshBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a quad with the corners in counterclockwise order.
+    pub fn push_quad(
+        &mut self,
+        texture: Option<&Texture2D>,
+        without_mipmaps: bool,
+        vertices: [BlockVertex; 4],
+    ) {
+        let index = self
+            .batches
+            .iter()
+            .rposition(|batch| {
+                batch.texture.as_ref() == texture
+                    && batch.without_mipmaps == without_mipmaps
+                    && batch.vertices.len() + 4 <= MAX_BATCH_VERTICES
+            })
+            .unwrap_or_else(|| {
+                self.batches.push(BatchBuilder {
+                    texture: texture.cloned(),
+                    without_mipmaps,
+                    vertices: Vec::new(),
+                    indices: Vec::new(),
+                });
+
+                self.batches.len() - 1
+            });
+
+        let batch = &mut self.batches[index];
+        let i = batch.vertices.len() as u16;
+
+        batch.vertices.extend(vertices);
+        batch.indices.extend([i, i + 1, i + 2, i + 2, i + 3, i]);
+    }
+
+    #[cfg(test)]
+    pub fn quad_count(&self) -> usize {
+        self.batches
+            .iter()
+            .map(|batch| batch.vertices.len() / 4)
+            .sum()
+    }
+
+    /// Uploads the faces, has to happen on the main thread.
+    pub fn build(self) -> SectionMesh {
+        let gl = unsafe { get_internal_gl() };
+
+        let batches = self
+            .batches
+            .into_iter()
+            .map(|batch| MeshBatch {
+                vertex_buffer: gl.quad_context.new_buffer(
+                    BufferType::VertexBuffer,
+                    BufferUsage::Immutable,
+                    BufferSource::slice(&batch.vertices),
+                ),
+                index_buffer: gl.quad_context.new_buffer(
+                    BufferType::IndexBuffer,
+                    BufferUsage::Immutable,
+                    BufferSource::slice(&batch.indices),
+                ),
+                index_count: batch.indices.len() as i32,
+                texture: batch.texture,
+                without_mipmaps: batch.without_mipmaps,
+            })
+            .collect();
+
+        SectionMesh { batches }
+    }
+}
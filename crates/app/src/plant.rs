@@ -0,0 +1,91 @@
+//! Trees and small plants, placed by the vegetation features of world generation.
+
+use crate::{
+    block_states::{Block, BlockProperties, BlockRenderLayer, Tint},
+    chunk::{BlockState, EMPTY_BLOCK},
+    world::World,
+};
+use data::{block_states::Property, model::Direction};
+use macroquad::math::IVec3;
+use maqo_macros::BlockProperties;
+use std::collections::HashMap;
+
+/// Blocks plants can grow on.
+pub const SOIL_BLOCKS: [&str; 2] = ["minecraft:grass_block", "minecraft:dirt"];
+
+pub fn is_soil(name: Option<&str>) -> bool {
+    name.is_some_and(|name| SOIL_BLOCKS.contains(&name))
+}
+
+#[derive(Default, BlockProperties)]
+#[block(full_block = true)]
+pub struct LogBlock {
+    pub axis: String,
+}
+
+impl Block for LogBlock {}
+
+#[derive(BlockProperties)]
+#[block(full_block = true, opaque_cube = false)]
+#[tint(Tint::Foliage)]
+pub struct LeavesBlock;
+
+impl Block for LeavesBlock {
+    fn render_layer(&self) -> BlockRenderLayer {
+        BlockRenderLayer::CutoutMipped
+    }
+}
+
+/// Plants break once the soil below them is gone.
+fn update_bush_shape(
+    world: &World,
+    direction: Direction,
+    neighbor: BlockState,
+) -> Option<BlockState> {
+    let soil = world.block_states.get_name_by_id(neighbor.value.into());
+
+    (direction == Direction::Bottom && !is_soil(soil)).then_some(EMPTY_BLOCK)
+}
+
+#[derive(BlockProperties)]
+#[block(full_cube = false, opaque_cube = false)]
+#[tint(Tint::Grass)]
+pub struct ShortGrassBlock;
+
+impl Block for ShortGrassBlock {
+    fn render_layer(&self) -> BlockRenderLayer {
+        BlockRenderLayer::Cutout
+    }
+
+    fn update_shape(
+        &self,
+        world: &mut World,
+        _position: IVec3,
+        _state: BlockState,
+        direction: Direction,
+        neighbor: BlockState,
+    ) -> Option<BlockState> {
+        update_bush_shape(world, direction, neighbor)
+    }
+}
+
+#[derive(BlockProperties)]
+#[block(full_cube = false, opaque_cube = false)]
+pub struct FlowerBlock;
+
+impl Block for FlowerBlock {
+    fn render_layer(&self) -> BlockRenderLayer {
+        BlockRenderLayer::Cutout
+    }
+
+    fn update_shape(
+        &self,
+        world: &mut World,
+        _position: IVec3,
+        _state: BlockState,
+        direction: Direction,
+        neighbor: BlockState,
+    ) -> Option<BlockState> {
+        update_bush_shape(world, direction, neighbor)
+    }
+}
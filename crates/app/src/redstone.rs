@@ -0,0 +1,686 @@
+use crate::{
+    block_states::{Block, BlockProperties, BlockRenderLayer, GetProperty, Tint},
+    chunk::BlockState,
+    world::World,
+};
+use data::{
+    block_states::{Property, PropertyValue},
+    model::Direction,
+};
+use macroquad::{
+    color::Color,
+    math::{ivec3, IVec3},
+};
+use maqo_macros::BlockProperties;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+pub const MAX_POWER: u8 = 15;
+
+/// Horizontal directions, with names of the properties facing them.
+pub const HORIZONTAL: [(Direction, &str); 4] = [
+    (Direction::Front, "north"),
+    (Direction::Right, "east"),
+    (Direction::Back, "south"),
+    (Direction::Left, "west"),
+];
+
+const UP: IVec3 = ivec3(0, 1, 0);
+const DOWN: IVec3 = ivec3(0, -1, 0);
+
+pub fn horizontal_direction(name: &str) -> Option<Direction> {
+    HORIZONTAL
+        .iter()
+        .find_map(|&(direction, other)| (other == name).then_some(direction))
+}
+
+pub fn horizontal_name(direction: Direction) -> Option<&'static str> {
+    HORIZONTAL
+        .iter()
+        .find_map(|&(other, name)| (other == direction).then_some(name))
+}
+
+/// Color of redstone wire carrying `power`.
+pub fn wire_color(power: i64) -> Tint {
+    let f = power as f32 / MAX_POWER as f32;
+
+    Tint::Color(Color::new(
+        f * 0.6 + if f > 0.0 { 0.4 } else { 0.3 },
+        (f * f * 0.7 - 0.5).clamp(0.0, 1.0),
+        (f * f * 0.6 - 0.7).clamp(0.0, 1.0),
+        1.0,
+    ))
+}
+
+#[derive(BlockProperties)]
+#[block(full_cube = false, opaque_cube = false)]
+pub struct LeverBlock {
+    pub face: String,
+    pub facing: String,
+    pub powered: bool,
+}
+
+impl LeverBlock {
+    /// Direction towards the block the lever is attached to.
+    pub fn attached_direction(&self) -> Direction {
+        match self.face.as_str() {
+            "floor" => Direction::Bottom,
+            "ceiling" => Direction::Top,
+            _ => horizontal_direction(&self.facing)
+                .map(|facing| facing.opposite())
+                .unwrap_or(Direction::Bottom),
+        }
+    }
+}
+
+impl Block for LeverBlock {
+    fn on_use(&self, world: &mut World, position: IVec3, state: BlockState) -> bool {
+        let Some(toggled) = world
+            .block_states
+            .with_property(state, "powered", !self.powered)
+        else {
+            return false;
+        };
+
+        world.set_block(position, toggled);
+
+        // The attached block conducts the strong power to its own neighbors.
+        world.notify_neighbors(position + self.attached_direction().as_vec3());
+
+        true
+    }
+
+    fn is_signal_source(&self) -> bool {
+        true
+    }
+
+    fn power_output(&self, _world: &World, _position: IVec3, _direction: Direction) -> u8 {
+        if self.powered {
+            MAX_POWER
+        } else {
+            0
+        }
+    }
+
+    fn strong_power_output(&self, _world: &World, _position: IVec3, direction: Direction) -> u8 {
+        if self.powered && direction == self.attached_direction() {
+            MAX_POWER
+        } else {
+            0
+        }
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum WireConnection {
+    #[default]
+    None,
+    Side,
+    Up,
+}
+
+impl WireConnection {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Side => "side",
+            Self::Up => "up",
+        }
+    }
+
+    pub fn is_connected(self) -> bool {
+        self != Self::None
+    }
+}
+
+impl From<WireConnection> for PropertyValue {
+    fn from(value: WireConnection) -> Self {
+        value.as_str().into()
+    }
+}
+
+#[derive(Default, BlockProperties)]
+#[block(full_cube = false, opaque_cube = false)]
+#[tint(wire_color(self.power))]
+pub struct RedstoneWireBlock {
+    pub power: i64,
+    pub north: WireConnection,
+    pub east: WireConnection,
+    pub south: WireConnection,
+    pub west: WireConnection,
+}
+
+impl RedstoneWireBlock {
+    pub fn connections(&self) -> [WireConnection; 4] {
+        [self.north, self.east, self.south, self.west]
+    }
+
+    pub fn connection(&self, direction: Direction) -> WireConnection {
+        HORIZONTAL
+            .iter()
+            .position(|&(other, _)| other == direction)
+            .map_or(WireConnection::None, |i| self.connections()[i])
+    }
+
+    fn is_wire(world: &World, position: IVec3) -> bool {
+        world.get_name(position) == Some("minecraft:redstone_wire")
+    }
+
+    fn wire_power(world: &World, position: IVec3) -> u8 {
+        if !Self::is_wire(world, position) {
+            return 0;
+        }
+
+        world
+            .get_base_block(position)
+            .and_then(|block| GetProperty::<i64>::get_property(block, "power"))
+            .map_or(0, |&power| power as u8)
+    }
+
+    fn connects_to(world: &World, position: IVec3) -> bool {
+        Self::is_wire(world, position)
+            || world
+                .get_base_block(position)
+                .is_some_and(|block| block.block.is_signal_source())
+    }
+
+    fn find_connection(world: &World, position: IVec3, direction: Direction) -> WireConnection {
+        let neighbor = position + direction.as_vec3();
+
+        // Wire climbs up the side of a solid block unless something covers it.
+        if !world.is_conductor(position + UP)
+            && world.is_conductor(neighbor)
+            && Self::is_wire(world, neighbor + UP)
+        {
+            return WireConnection::Up;
+        }
+
+        if Self::connects_to(world, neighbor)
+            || (!world.is_conductor(neighbor) && Self::is_wire(world, neighbor + DOWN))
+        {
+            WireConnection::Side
+        } else {
+            WireConnection::None
+        }
+    }
+
+    /// Connections of a wire at `position`, a wire connected to a single
+    /// side is extended to the opposite one so it forms a line.
+    pub fn find_connections(world: &World, position: IVec3) -> [WireConnection; 4] {
+        let mut connections =
+            HORIZONTAL.map(|(direction, _)| Self::find_connection(world, position, direction));
+
+        let connected = connections
+            .iter()
+            .enumerate()
+            .filter(|(_, connection)| connection.is_connected())
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+
+        if let [i] = connected[..] {
+            connections[(i + 2) % 4] = WireConnection::Side;
+        }
+
+        connections
+    }
+
+    /// Wires the wire at `position` passes power to and takes it from, next
+    /// to it or a block up or down the side of a solid block.
+    fn wire_neighbors(world: &World, position: IVec3) -> Vec<IVec3> {
+        let covered = world.is_conductor(position + UP);
+        let mut neighbors = Vec::new();
+
+        for (direction, _) in HORIZONTAL {
+            let neighbor = position + direction.as_vec3();
+
+            neighbors.push(neighbor);
+
+            if world.is_conductor(neighbor) {
+                if !covered {
+                    neighbors.push(neighbor + UP);
+                }
+            } else {
+                neighbors.push(neighbor + DOWN);
+            }
+        }
+
+        neighbors.retain(|&neighbor| Self::is_wire(world, neighbor));
+        neighbors
+    }
+
+    /// Power the wire at `position` should have.
+    pub fn find_power(world: &mut World, position: IVec3) -> u8 {
+        let block_power = world.get_best_neighbor_signal_without_wires(position);
+
+        if block_power >= MAX_POWER {
+            return MAX_POWER;
+        }
+
+        let wire_power = Self::wire_neighbors(world, position)
+            .into_iter()
+            .map(|neighbor| Self::wire_power(world, neighbor))
+            .max()
+            .unwrap_or(0);
+
+        block_power.max(wire_power.saturating_sub(1))
+    }
+
+    /// Updates the shapes of the wires a block up or down from the sides of
+    /// `position`, which aren't its neighbors but connect to it.
+    fn update_diagonal_shapes(world: &mut World, position: IVec3) {
+        let Some(state) = world.get_block(position) else {
+            return;
+        };
+
+        for (direction, _) in HORIZONTAL {
+            for offset in [UP, DOWN] {
+                let diagonal = position + direction.as_vec3() + offset;
+
+                let (Some(diagonal_state), Some(block)) =
+                    (world.get_block(diagonal), world.get_base_block(diagonal))
+                else {
+                    continue;
+                };
+
+                if !Self::is_wire(world, diagonal) {
+                    continue;
+                }
+
+                if let Some(new_state) = block.block.update_shape(
+                    world,
+                    diagonal,
+                    diagonal_state,
+                    direction.opposite(),
+                    state,
+                ) {
+                    world.set_block(diagonal, new_state);
+                }
+            }
+        }
+    }
+
+    /// Changes the power of the wire at `position` without updating anything.
+    fn set_power(world: &mut World, position: IVec3, power: u8) {
+        let block_states = world.block_states;
+
+        if let Some(state) = world
+            .get_block(position)
+            .and_then(|state| block_states.with_property(state, "power", i64::from(power)))
+        {
+            world.chunks.set_block(position, state, block_states);
+        }
+    }
+
+    /// Updates the power of the wire at `position` and of the wires it's
+    /// connected to, like vanilla does.
+    fn update_power(&self, world: &mut World, position: IVec3) {
+        let power = Self::find_power(world, position);
+
+        if i64::from(power) == self.power {
+            return;
+        }
+
+        let mut changed = vec![position];
+        let mut sources = VecDeque::new();
+
+        if i64::from(power) < self.power {
+            // The power the wire passed on is taken away first, otherwise the
+            // wires around it keep handing it back to each other.
+            let mut removed = VecDeque::from([(position, self.power as u8)]);
+
+            Self::set_power(world, position, 0);
+
+            while let Some((wire, wire_power)) = removed.pop_front() {
+                for neighbor in Self::wire_neighbors(world, wire) {
+                    let neighbor_power = Self::wire_power(world, neighbor);
+
+                    if neighbor_power == 0 {
+                        continue;
+                    }
+
+                    if neighbor_power < wire_power {
+                        Self::set_power(world, neighbor, 0);
+                        changed.push(neighbor);
+                        removed.push_back((neighbor, neighbor_power));
+                    } else {
+                        sources.push_back(neighbor);
+                    }
+                }
+            }
+
+            // Then the wires get what the blocks around them still give.
+            for &wire in &changed {
+                let power = world.get_best_neighbor_signal_without_wires(wire);
+
+                if power > 0 {
+                    Self::set_power(world, wire, power);
+                    sources.push_back(wire);
+                }
+            }
+        } else {
+            Self::set_power(world, position, power);
+            sources.push_back(position);
+        }
+
+        // Power spreads along the wires, one less with every block.
+        while let Some(wire) = sources.pop_front() {
+            let power = Self::wire_power(world, wire).saturating_sub(1);
+
+            for neighbor in Self::wire_neighbors(world, wire) {
+                if Self::wire_power(world, neighbor) < power {
+                    Self::set_power(world, neighbor, power);
+                    changed.push(neighbor);
+                    sources.push_back(neighbor);
+                }
+            }
+        }
+
+        let mut notified = HashSet::new();
+
+        // Blocks the wires power have to tell their own neighbors as well.
+        for wire in changed {
+            if !notified.insert(wire) {
+                continue;
+            }
+
+            world.notify_neighbors(wire);
+
+            for direction in Direction::ALL {
+                world.notify_neighbors(wire + direction.as_vec3());
+            }
+        }
+    }
+}
+
+impl Block for RedstoneWireBlock {
+    fn render_layer(&self) -> BlockRenderLayer {
+        BlockRenderLayer::Cutout
+    }
+
+    fn on_place(&self, world: &mut World, position: IVec3) {
+        Self::update_diagonal_shapes(world, position);
+        self.update_power(world, position);
+    }
+
+    fn on_neighbor_changed(&self, world: &mut World, position: IVec3, _neighbor: IVec3) {
+        self.update_power(world, position);
+    }
+
+    fn update_shape(
+        &self,
+        world: &mut World,
+        position: IVec3,
+        state: BlockState,
+        _direction: Direction,
+        _neighbor: BlockState,
+    ) -> Option<BlockState> {
+        let connections = Self::find_connections(world, position);
+
+        if connections == self.connections() {
+            return None;
+        }
+
+        HORIZONTAL
+            .iter()
+            .zip(connections)
+            .try_fold(state, |state, (&(_, name), connection)| {
+                world.block_states.with_property(state, name, connection)
+            })
+    }
+
+    fn is_signal_source(&self) -> bool {
+        true
+    }
+
+    fn power_output(&self, world: &World, _position: IVec3, direction: Direction) -> u8 {
+        if !world.wires_emit_power() || direction == Direction::Top {
+            return 0;
+        }
+
+        if direction == Direction::Bottom || self.connection(direction).is_connected() {
+            self.power as u8
+        } else {
+            0
+        }
+    }
+
+    fn strong_power_output(&self, world: &World, position: IVec3, direction: Direction) -> u8 {
+        self.power_output(world, position, direction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        block_states::DirtBlock,
+        chunk::{BiomeId, ChunkColumn, ChunkManager, WorldHeight, SIZE},
+        loaders::{BlockStatesLoader, Model},
+    };
+    use macroquad::math::ivec2;
+
+    /// Registers dirt, levers and redstone wire, without their models.
+    fn block_states() -> BlockStatesLoader {
+        let mut block_states = BlockStatesLoader::default();
+        let mut id = 1;
+
+        block_states.register_block_with_model(id, "dirt", DirtBlock::default(), Model::empty());
+
+        for face in ["floor", "wall", "ceiling"] {
+            for (_, facing) in HORIZONTAL {
+                for powered in [false, true] {
+                    id += 1;
+
+                    let lever = LeverBlock {
+                        face: face.into(),
+                        facing: facing.into(),
+                        powered,
+                    };
+
+                    block_states.register_block_with_model(id, "lever", lever, Model::empty());
+                }
+            }
+        }
+
+        let connections = [
+            WireConnection::None,
+            WireConnection::Side,
+            WireConnection::Up,
+        ];
+
+        for power in 0..=i64::from(MAX_POWER) {
+            for north in connections {
+                for east in connections {
+                    for south in connections {
+                        for west in connections {
+                            id += 1;
+
+                            let wire = RedstoneWireBlock {
+                                power,
+                                north,
+                                east,
+                                south,
+                                west,
+                            };
+
+                            block_states.register_block_with_model(
+                                id,
+                                "redstone_wire",
+                                wire,
+                                Model::empty(),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        block_states
+    }
+
+    /// A single column with a floor of dirt at a height of 0.
+    fn chunks(block_states: &BlockStatesLoader) -> ChunkManager {
+        let mut chunks = ChunkManager::new();
+        let dirt = block_states.get_default_state("dirt").unwrap();
+
+        chunks.add_chunk_column(
+            ivec2(0, 0),
+            ChunkColumn::new(WorldHeight::default(), [[BiomeId { value: 0 }; SIZE]; SIZE]),
+        );
+
+        for z in 0..SIZE as i32 {
+            for x in 0..SIZE as i32 {
+                chunks.set_block(ivec3(x, 0, z), dirt, block_states);
+            }
+        }
+
+        chunks
+    }
+
+    fn lever(world: &World, face: &str, facing: &str) -> BlockState {
+        world
+            .block_states
+            .find_state(
+                "lever",
+                [("face", face), ("facing", facing), ("powered", "false")],
+            )
+            .unwrap()
+    }
+
+    fn place_wire(world: &mut World, position: IVec3) {
+        let wire = world
+            .block_states
+            .get_default_state("redstone_wire")
+            .unwrap();
+
+        world.place_block(position, wire);
+    }
+
+    fn is_powered(world: &World, position: IVec3) -> bool {
+        world
+            .get_base_block(position)
+            .and_then(|block| GetProperty::<bool>::get_property(block, "powered"))
+            .copied()
+            .unwrap()
+    }
+
+    fn connections(world: &World, position: IVec3) -> [&'static str; 4] {
+        let block = world.get_base_block(position).unwrap();
+
+        HORIZONTAL.map(|(_, name)| {
+            let connection = GetProperty::<String>::get_property(block, name).unwrap();
+
+            [
+                WireConnection::None,
+                WireConnection::Side,
+                WireConnection::Up,
+            ]
+            .into_iter()
+            .find(|other| other.as_str() == connection)
+            .unwrap()
+            .as_str()
+        })
+    }
+
+    fn powers(world: &World, positions: impl IntoIterator<Item = IVec3>) -> Vec<u8> {
+        positions
+            .into_iter()
+            .map(|position| RedstoneWireBlock::wire_power(world, position))
+            .collect()
+    }
+
+    #[test]
+    fn lever_toggles_on_use() {
+        let block_states = block_states();
+        let mut chunks = chunks(&block_states);
+        let mut world = World::new(&block_states, &mut chunks);
+        let position = ivec3(4, 1, 4);
+
+        world.place_block(position, lever(&world, "floor", "north"));
+
+        assert!(!is_powered(&world, position));
+        assert!(world.use_block(position));
+        assert!(is_powered(&world, position));
+        assert!(world.use_block(position));
+        assert!(!is_powered(&world, position));
+    }
+
+    #[test]
+    fn power_drops_along_a_line_of_wire() {
+        let block_states = block_states();
+        let mut chunks = chunks(&block_states);
+        let mut world = World::new(&block_states, &mut chunks);
+        let dirt = block_states.get_default_state("dirt").unwrap();
+
+        // Strong power: the lever is attached to the dirt and powers it, so
+        // the dirt passes the power on to the wire behind it.
+        let strong = ivec3(0, 1, 0);
+        let line = (2..SIZE as i32).map(|x| ivec3(x, 1, 0));
+
+        world.set_block(strong + ivec3(1, 0, 0), dirt);
+        world.place_block(strong, lever(&world, "wall", "west"));
+
+        for position in line.clone() {
+            place_wire(&mut world, position);
+        }
+
+        world.use_block(strong);
+
+        assert_eq!(
+            powers(&world, line.clone()),
+            [15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2]
+        );
+
+        world.use_block(strong);
+
+        assert_eq!(powers(&world, line.clone()), [0; 14]);
+
+        world.use_block(strong);
+
+        assert_eq!(powers(&world, line.clone()).first(), Some(&15));
+
+        // Weak power: the lever sits on the floor, so the dirt next to it is
+        // only weakly powered and doesn't pass it on.
+        let weak = ivec3(0, 1, 4);
+
+        world.set_block(weak + ivec3(1, 0, 0), dirt);
+        world.place_block(weak, lever(&world, "floor", "north"));
+        place_wire(&mut world, weak + ivec3(2, 0, 0));
+        world.use_block(weak);
+
+        assert_eq!(powers(&world, [weak + ivec3(2, 0, 0)]), [0]);
+
+        // Wire right next to the lever is powered by it directly.
+        place_wire(&mut world, weak + ivec3(0, 0, 1));
+
+        assert_eq!(powers(&world, [weak + ivec3(0, 0, 1)]), [15]);
+    }
+
+    #[test]
+    fn wire_connects_to_its_neighbors() {
+        let block_states = block_states();
+        let mut chunks = chunks(&block_states);
+        let mut world = World::new(&block_states, &mut chunks);
+        let dirt = block_states.get_default_state("dirt").unwrap();
+
+        // A wire on its own is a dot.
+        let center = ivec3(4, 1, 4);
+
+        place_wire(&mut world, center);
+
+        assert_eq!(connections(&world, center), ["none"; 4]);
+
+        // A single neighbor makes it a line through both sides.
+        place_wire(&mut world, center + ivec3(1, 0, 0));
+
+        assert_eq!(
+            connections(&world, center),
+            ["none", "side", "none", "side"]
+        );
+
+        // Wire on top of a block next to it climbs up its side.
+        world.set_block(center + ivec3(0, 0, -1), dirt);
+        place_wire(&mut world, center + ivec3(0, 1, -1));
+
+        assert_eq!(connections(&world, center), ["none", "side", "up", "none"]);
+    }
+}
@@ -0,0 +1,437 @@
+use flate2::{
+    read::{GzDecoder, ZlibDecoder},
+    write::{GzEncoder, ZlibEncoder},
+};
+use macroquad::math::{ivec2, IVec2};
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Width of a region in chunk columns.
+pub const REGION_SIZE: i32 = 32;
+
+const COLUMNS: usize = (REGION_SIZE * REGION_SIZE) as usize;
+const SECTOR_SIZE: usize = 4096;
+/// The location and timestamp tables take up one sector each.
+const HEADER_SECTORS: usize = 2;
+/// Length and compression type in front of the data of each column.
+const CHUNK_HEADER_SIZE: usize = 5;
+/// Set in the compression type of columns too large for the region file,
+/// which vanilla stores in a `c.x.z.mcc` file next to it.
+const EXTERNAL_FLAG: u8 = 0x80;
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Compression {
+    Gzip = 1,
+    #[default]
+    Zlib = 2,
+    None = 3,
+}
+
+impl Compression {
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(Self::Gzip),
+            2 => Some(Self::Zlib),
+            3 => Some(Self::None),
+            _ => None,
+        }
+    }
+
+    pub fn compress(self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            Self::Zlib => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            Self::None => Ok(data.to_vec()),
+        }
+    }
+
+    pub fn decompress(self, data: &[u8]) -> io::Result<Vec<u8>> {
+        let mut decompressed = Vec::new();
+
+        match self {
+            Self::Gzip => GzDecoder::new(data).read_to_end(&mut decompressed)?,
+            Self::Zlib => ZlibDecoder::new(data).read_to_end(&mut decompressed)?,
+            Self::None => return Ok(data.to_vec()),
+        };
+
+        Ok(decompressed)
+    }
+}
+
+/// Splits a chunk column position into the position of its region and its
+/// index inside of the region file.
+pub fn region_position(column: IVec2) -> (IVec2, usize) {
+    let region = ivec2(
+        column.x.div_euclid(REGION_SIZE),
+        column.y.div_euclid(REGION_SIZE),
+    );
+    let (x, z) = (
+        column.x.rem_euclid(REGION_SIZE),
+        column.y.rem_euclid(REGION_SIZE),
+    );
+
+    (region, (x + z * REGION_SIZE) as usize)
+}
+
+/// File holding 32x32 chunk columns, split into sectors of 4 KiB.
+///
+/// The first sector tells where each column starts and how many sectors it
+/// takes, the second one when it was last written. Every column is stored
+/// compressed on its own, so it can be read and rewritten independently.
+pub struct RegionFile {
+    file: File,
+    locations: [u32; COLUMNS],
+    timestamps: [u32; COLUMNS],
+    used_sectors: Vec<bool>,
+}
+
+impl RegionFile {
+    /// Opens a region file for reading and writing, creating it if it
+    /// doesn't exist yet.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+
+        if file.metadata()?.len() < (HEADER_SECTORS * SECTOR_SIZE) as u64 {
+            file.set_len((HEADER_SECTORS * SECTOR_SIZE) as u64)?;
+        }
+
+        Self::from_file(file)
+    }
+
+    /// Opens an existing region file without ever changing it, writing to it
+    /// fails.
+    pub fn open_read(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_file(File::open(path)?)
+    }
+
+    fn from_file(mut file: File) -> io::Result<Self> {
+        let mut header = Vec::with_capacity(HEADER_SECTORS * SECTOR_SIZE);
+
+        // A truncated header only has the columns it still holds.
+        file.seek(SeekFrom::Start(0))?;
+        (&mut file)
+            .take((HEADER_SECTORS * SECTOR_SIZE) as u64)
+            .read_to_end(&mut header)?;
+        header.resize(HEADER_SECTORS * SECTOR_SIZE, 0);
+
+        let entry = |i: usize| u32::from_be_bytes([0, 1, 2, 3].map(|j| header[i * 4 + j]));
+
+        let locations = std::array::from_fn(entry);
+        let timestamps = std::array::from_fn(|i| entry(COLUMNS + i));
+
+        let sectors = (file.metadata()?.len() as usize)
+            .div_ceil(SECTOR_SIZE)
+            .max(HEADER_SECTORS);
+        let mut used_sectors = vec![false; sectors];
+
+        used_sectors[..HEADER_SECTORS].fill(true);
+
+        for &location in locations.iter() {
+            let (offset, count) = Self::split_location(location);
+
+            if let Some(sectors) = used_sectors.get_mut(offset..offset + count) {
+                sectors.fill(true);
+            }
+        }
+
+        Ok(Self {
+            file,
+            locations,
+            timestamps,
+            used_sectors,
+        })
+    }
+
+    fn split_location(location: u32) -> (usize, usize) {
+        ((location >> 8) as usize, (location & 0xff) as usize)
+    }
+
+    pub fn contains(&self, index: usize) -> bool {
+        self.locations[index] != 0
+    }
+
+    /// Seconds since the Unix epoch when the column was last written.
+    pub fn timestamp(&self, index: usize) -> u32 {
+        self.timestamps[index]
+    }
+
+    /// Reads and decompresses the data of a column, `None` if it was never written.
+    pub fn read(&mut self, index: usize) -> io::Result<Option<Vec<u8>>> {
+        let (offset, count) = Self::split_location(self.locations[index]);
+
+        if count == 0 {
+            return Ok(None);
+        }
+
+        let mut header = [0; CHUNK_HEADER_SIZE];
+
+        self.file
+            .seek(SeekFrom::Start((offset * SECTOR_SIZE) as u64))?;
+        self.file.read_exact(&mut header)?;
+
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+
+        if length == 0 || length + 4 > count * SECTOR_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("column {index} has an invalid length of {length}"),
+            ));
+        }
+
+        if header[4] & EXTERNAL_FLAG != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("column {index} is stored in an external .mcc file"),
+            ));
+        }
+
+        let compression = Compression::from_id(header[4]).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("column {index} uses unknown compression {}", header[4]),
+            )
+        })?;
+
+        let mut data = vec![0; length - 1];
+
+        self.file.read_exact(&mut data)?;
+
+        compression.decompress(&data).map(Some)
+    }
+
+    /// Compresses and writes the data of a column, reusing its old sectors
+    /// or the first free space large enough.
+    pub fn write(&mut self, index: usize, data: &[u8], compression: Compression) -> io::Result<()> {
+        let data = compression.compress(data)?;
+        let count = (CHUNK_HEADER_SIZE + data.len()).div_ceil(SECTOR_SIZE);
+
+        if count > 0xff {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("column {index} takes {count} sectors, at most 255 fit"),
+            ));
+        }
+
+        let (old_offset, old_count) = Self::split_location(self.locations[index]);
+
+        // A damaged entry may point past the end of the file, like in `open`.
+        if let Some(sectors) = self
+            .used_sectors
+            .get_mut(old_offset..old_offset + old_count)
+        {
+            sectors.fill(false);
+        }
+
+        let offset = self
+            .used_sectors
+            .windows(count)
+            .position(|sectors| sectors.iter().all(|&used| !used))
+            .unwrap_or_else(|| {
+                // Append after the last used sector.
+                self.used_sectors
+                    .iter()
+                    .rposition(|&used| used)
+                    .map_or(HEADER_SECTORS, |last| last + 1)
+            });
+
+        if offset + count > self.used_sectors.len() {
+            self.used_sectors.resize(offset + count, false);
+        }
+
+        self.used_sectors[offset..offset + count].fill(true);
+
+        let mut sectors = Vec::with_capacity(count * SECTOR_SIZE);
+
+        sectors.extend(((data.len() + 1) as u32).to_be_bytes());
+        sectors.push(compression as u8);
+        sectors.extend(data);
+        sectors.resize(count * SECTOR_SIZE, 0);
+
+        self.file
+            .seek(SeekFrom::Start((offset * SECTOR_SIZE) as u64))?;
+        self.file.write_all(&sectors)?;
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs() as u32);
+
+        self.locations[index] = (offset as u32) << 8 | count as u32;
+        self.timestamps[index] = timestamp;
+
+        self.file.seek(SeekFrom::Start((index * 4) as u64))?;
+        self.file.write_all(&self.locations[index].to_be_bytes())?;
+        self.file
+            .seek(SeekFrom::Start((SECTOR_SIZE + index * 4) as u64))?;
+        self.file.write_all(&timestamp.to_be_bytes())?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, path::PathBuf, process};
+
+    /// Region file in the temporary directory, removed again when dropped.
+    struct TempRegion(PathBuf);
+
+    impl TempRegion {
+        fn new(name: &str) -> Self {
+            let path = env::temp_dir().join(format!("maqo-{name}-{}.mqr", process::id()));
+            let _ = fs::remove_file(&path);
+
+            Self(path)
+        }
+
+        fn open(&self) -> RegionFile {
+            RegionFile::open(&self.0).unwrap()
+        }
+    }
+
+    impl Drop for TempRegion {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    /// Uncompressed data taking up `sectors` sectors once written.
+    fn data(sectors: usize, byte: u8) -> Vec<u8> {
+        vec![byte; sectors * SECTOR_SIZE - CHUNK_HEADER_SIZE]
+    }
+
+    fn location(region: &RegionFile, index: usize) -> (usize, usize) {
+        RegionFile::split_location(region.locations[index])
+    }
+
+    #[test]
+    fn columns_are_read_back_after_reopening() {
+        let temp = TempRegion::new("region-reopen");
+        let mut region = temp.open();
+
+        region.write(0, b"first", Compression::Zlib).unwrap();
+        region
+            .write(COLUMNS - 1, b"last", Compression::Gzip)
+            .unwrap();
+        drop(region);
+
+        let file = fs::read(&temp.0).unwrap();
+
+        assert_eq!(file.len(), 4 * SECTOR_SIZE);
+        // Big endian offset of 2 sectors and a length of 1 sector.
+        assert_eq!(file[..4], [0, 0, 2, 1]);
+        assert_eq!(file[(COLUMNS - 1) * 4..COLUMNS * 4], [0, 0, 3, 1]);
+
+        let mut region = temp.open();
+
+        assert!(region.contains(0));
+        assert!(!region.contains(1));
+        assert!(region.timestamp(0) > 0);
+        assert_eq!(region.read(0).unwrap().as_deref(), Some(&b"first"[..]));
+        assert_eq!(region.read(1).unwrap(), None);
+        assert_eq!(
+            region.read(COLUMNS - 1).unwrap().as_deref(),
+            Some(&b"last"[..])
+        );
+    }
+
+    #[test]
+    fn growing_an_entry_moves_it_to_the_end() {
+        let temp = TempRegion::new("region-grow");
+        let mut region = temp.open();
+
+        region.write(0, &data(1, 1), Compression::None).unwrap();
+        region.write(1, &data(1, 2), Compression::None).unwrap();
+
+        // Larger than its original sector, which is followed by another column.
+        region.write(0, &data(3, 3), Compression::None).unwrap();
+
+        assert_eq!(location(&region, 0), (4, 3));
+        assert_eq!(location(&region, 1), (3, 1));
+
+        // The sector it left is free for the next column.
+        region.write(2, &data(1, 4), Compression::None).unwrap();
+
+        assert_eq!(location(&region, 2), (2, 1));
+        drop(region);
+
+        let mut region = temp.open();
+
+        assert_eq!(region.read(0).unwrap(), Some(data(3, 3)));
+        assert_eq!(region.read(1).unwrap(), Some(data(1, 2)));
+        assert_eq!(region.read(2).unwrap(), Some(data(1, 4)));
+    }
+
+    #[test]
+    fn shrinking_an_entry_frees_its_sectors() {
+        let temp = TempRegion::new("region-shrink");
+        let mut region = temp.open();
+
+        region.write(0, &data(3, 1), Compression::None).unwrap();
+        region.write(1, &data(1, 2), Compression::None).unwrap();
+        region.write(0, &data(1, 3), Compression::None).unwrap();
+
+        assert_eq!(location(&region, 0), (2, 1));
+
+        // Fits into the two sectors freed behind it.
+        region.write(2, &data(2, 4), Compression::None).unwrap();
+
+        assert_eq!(location(&region, 2), (3, 2));
+        assert_eq!(location(&region, 1), (5, 1));
+        assert_eq!(region.read(0).unwrap(), Some(data(1, 3)));
+        assert_eq!(region.read(1).unwrap(), Some(data(1, 2)));
+        assert_eq!(region.read(2).unwrap(), Some(data(2, 4)));
+    }
+
+    #[test]
+    fn entries_past_the_end_are_rewritten() {
+        let temp = TempRegion::new("region-past-end");
+        let mut header = vec![0; HEADER_SECTORS * SECTOR_SIZE];
+
+        // A damaged entry pointing far past the end of the file.
+        header[4..8].copy_from_slice(&(100 << 8 | 5u32).to_be_bytes());
+        fs::write(&temp.0, header).unwrap();
+
+        let mut region = temp.open();
+
+        assert!(region.read(1).is_err());
+
+        region.write(1, b"fixed", Compression::Zlib).unwrap();
+
+        assert_eq!(location(&region, 1), (2, 1));
+        assert_eq!(region.read(1).unwrap().as_deref(), Some(&b"fixed"[..]));
+        assert_eq!(fs::metadata(&temp.0).unwrap().len(), 3 * SECTOR_SIZE as u64);
+    }
+
+    #[test]
+    fn oversized_columns_are_rejected() {
+        let temp = TempRegion::new("region-oversized");
+        let mut region = temp.open();
+
+        let err = region
+            .write(0, &data(256, 0), Compression::None)
+            .unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(!region.contains(0));
+    }
+}
@@ -0,0 +1,446 @@
+use crate::{
+    anvil::{PaletteEntry, PLACEHOLDER_BLOCK},
+    chunk::{BiomeId, BlockState, Chunk, ChunkColumn, LightLevel, WorldHeight, EMPTY_BLOCK, SIZE},
+    loaders::BlockStatesLoader,
+    region::{region_position, Compression, RegionFile},
+};
+use macroquad::math::{IVec2, IVec3, Vec3};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Read},
+    path::PathBuf,
+    sync::mpsc::{self, Receiver, Sender},
+    thread::{self, JoinHandle},
+};
+
+/// Game ticks between two automatic saves.
+pub const AUTOSAVE_INTERVAL: u64 = 600;
+
+const LEVEL_FILE: &str = "level.json";
+const REGION_DIRECTORY: &str = "region";
+const REGION_EXTENSION: &str = "mqr";
+
+/// Version of the column encoding, bumped whenever it changes.
+const COLUMN_VERSION: u8 = 3;
+
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize)]
+pub struct PlayerData {
+    pub position: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+/// Contents of the `level.json` file of a world.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct LevelData {
+    pub seed: u32,
+    pub spawn: IVec3,
+    pub player: PlayerData,
+    /// Game ticks passed since the world was created.
+    pub time: u64,
+    /// Ticks since the first sunrise, which keeps counting over the days.
+    pub day_time: u64,
+    pub height: WorldHeight,
+}
+
+fn read_u8(data: &mut &[u8]) -> io::Result<u8> {
+    let mut byte = [0];
+
+    data.read_exact(&mut byte)?;
+
+    Ok(byte[0])
+}
+
+fn read_u16(data: &mut &[u8]) -> io::Result<u16> {
+    let mut bytes = [0; 2];
+
+    data.read_exact(&mut bytes)?;
+
+    Ok(u16::from_be_bytes(bytes))
+}
+
+fn write_string(data: &mut Vec<u8>, string: &str) {
+    data.extend((string.len() as u16).to_be_bytes());
+    data.extend(string.as_bytes());
+}
+
+fn read_string(data: &mut &[u8]) -> io::Result<String> {
+    let mut bytes = vec![0; usize::from(read_u16(data)?)];
+
+    data.read_exact(&mut bytes)?;
+
+    String::from_utf8(bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// Encodes the blocks, light and biomes of a column, meshes aren't saved.
+///
+/// Every section has a palette of the names and properties of its blocks
+/// like Anvil, so saves survive blocks being registered in another order.
+/// Empty sections only take a single byte.
+pub fn encode_column(column: &ChunkColumn, block_states: &BlockStatesLoader) -> Vec<u8> {
+    let mut data = vec![COLUMN_VERSION];
+
+    data.extend(column.biomes.iter().flatten().map(|biome| biome.value));
+    data.push(column.min_section as i8 as u8);
+    data.push(column.chunks.len() as u8);
+
+    for chunk in column.chunks.iter() {
+        data.push(chunk.is_some().into());
+
+        let Some(chunk) = chunk else {
+            continue;
+        };
+
+        let mut palette = Vec::new();
+        let mut palette_indices = HashMap::new();
+        let indices: Vec<u16> = chunk
+            .blocks
+            .iter()
+            .flatten()
+            .flatten()
+            .map(|&block| {
+                *palette_indices.entry(block.value).or_insert_with(|| {
+                    palette.push(PaletteEntry::from_state(block_states, block));
+
+                    palette.len() as u16 - 1
+                })
+            })
+            .collect();
+
+        data.extend((palette.len() as u16).to_be_bytes());
+
+        for entry in palette.iter() {
+            write_string(&mut data, &entry.name);
+            data.push(entry.properties.len() as u8);
+
+            for (key, value) in entry.properties.iter() {
+                write_string(&mut data, key);
+                write_string(&mut data, value);
+            }
+        }
+
+        for index in indices {
+            data.extend(index.to_be_bytes());
+        }
+
+        data.extend(
+            chunk
+                .light_levels
+                .iter()
+                .flatten()
+                .flatten()
+                .map(|light| light.value),
+        );
+    }
+
+    data
+}
+
+/// Reads the palette of a section, blocks which aren't registered anymore
+/// turn into the placeholder block.
+fn decode_palette(
+    data: &mut &[u8],
+    block_states: &BlockStatesLoader,
+) -> io::Result<Vec<BlockState>> {
+    let placeholder = block_states
+        .get_default_state(PLACEHOLDER_BLOCK)
+        .unwrap_or(EMPTY_BLOCK);
+
+    (0..read_u16(data)?)
+        .map(|_| {
+            let name = read_string(data)?;
+            let properties = (0..read_u8(data)?)
+                .map(|_| Ok((read_string(data)?, read_string(data)?)))
+                .collect::<io::Result<_>>()?;
+            let entry = PaletteEntry { name, properties };
+
+            Ok(entry.to_state(block_states).unwrap_or_else(|| {
+                eprintln!("unknown saved block {entry}, using a placeholder");
+
+                placeholder
+            }))
+        })
+        .collect()
+}
+
+/// Decodes a column into one spanning `height`, sections outside of it are
+/// dropped.
+pub fn decode_column(
+    mut data: &[u8],
+    height: WorldHeight,
+    block_states: &BlockStatesLoader,
+) -> io::Result<ChunkColumn> {
+    let data = &mut data;
+    let version = read_u8(data)?;
+
+    if version != COLUMN_VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unsupported column version {version}"),
+        ));
+    }
+
+    let mut biomes = [[BiomeId { value: 0 }; SIZE]; SIZE];
+
+    for biome in biomes.iter_mut().flatten() {
+        biome.value = read_u8(data)?;
+    }
+
+    let mut column = ChunkColumn::new(height, biomes);
+
+    let min_section = i32::from(read_u8(data)? as i8);
+    let sections = read_u8(data)?;
+
+    for y in min_section..min_section + i32::from(sections) {
+        if read_u8(data)? == 0 {
+            continue;
+        }
+
+        let mut chunk = Box::new(Chunk {
+            blocks: [[[BlockState { value: 0 }; SIZE]; SIZE]; SIZE],
+            light_levels: [[[LightLevel { value: 0 }; SIZE]; SIZE]; SIZE],
+        });
+
+        let palette = decode_palette(data, block_states)?;
+
+        for block in chunk.blocks.iter_mut().flatten().flatten() {
+            *block = *palette.get(usize::from(read_u16(data)?)).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, "palette index out of bounds")
+            })?;
+        }
+
+        for light in chunk.light_levels.iter_mut().flatten().flatten() {
+            light.value = read_u8(data)?;
+        }
+
+        if let Some(index) = column.section_index(y) {
+            column.chunks[index] = Some(chunk);
+        }
+    }
+
+    Ok(column)
+}
+
+enum SaveTask {
+    Column(IVec2, Vec<u8>),
+    Level(LevelData),
+    Load(IVec2, Sender<io::Result<Option<Vec<u8>>>>),
+}
+
+/// Region files of a world, only touched by the save thread.
+struct RegionStorage {
+    directory: PathBuf,
+    regions: HashMap<IVec2, RegionFile>,
+}
+
+impl RegionStorage {
+    fn region(&mut self, region: IVec2) -> io::Result<&mut RegionFile> {
+        if !self.regions.contains_key(&region) {
+            let path = self
+                .directory
+                .join(format!("r.{}.{}.{REGION_EXTENSION}", region.x, region.y));
+
+            self.regions.insert(region, RegionFile::open(path)?);
+        }
+
+        Ok(self.regions.get_mut(&region).unwrap())
+    }
+
+    fn read(&mut self, position: IVec2) -> io::Result<Option<Vec<u8>>> {
+        let (region, index) = region_position(position);
+
+        self.region(region)?.read(index)
+    }
+
+    fn write(&mut self, position: IVec2, data: &[u8]) -> io::Result<()> {
+        let (region, index) = region_position(position);
+
+        self.region(region)?
+            .write(index, data, Compression::default())
+    }
+}
+
+/// Directory of a saved world.
+///
+/// Writing happens on a separate thread so saving doesn't stall the game,
+/// loads go through the same thread to see everything saved before them.
+pub struct WorldSave {
+    directory: PathBuf,
+    sender: Option<Sender<SaveTask>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl WorldSave {
+    pub fn open(directory: impl Into<PathBuf>) -> io::Result<Self> {
+        let directory = directory.into();
+        let mut storage = RegionStorage {
+            directory: directory.join(REGION_DIRECTORY),
+            regions: HashMap::new(),
+        };
+
+        fs::create_dir_all(&storage.directory)?;
+
+        let level_path = directory.join(LEVEL_FILE);
+        let (sender, receiver): (_, Receiver<SaveTask>) = mpsc::channel();
+
+        let worker = thread::spawn(move || {
+            for task in receiver {
+                let result = match task {
+                    SaveTask::Column(position, data) => storage
+                        .write(position, &data)
+                        .map_err(|err| format!("failed to save column {position}: {err}")),
+                    SaveTask::Level(level) => serde_json::to_vec_pretty(&level)
+                        .map_err(io::Error::from)
+                        .and_then(|data| fs::write(&level_path, data))
+                        .map_err(|err| format!("failed to save level: {err}")),
+                    SaveTask::Load(position, reply) => {
+                        let _ = reply.send(storage.read(position));
+
+                        Ok(())
+                    }
+                };
+
+                if let Err(err) = result {
+                    eprintln!("{err}");
+                }
+            }
+        });
+
+        Ok(Self {
+            directory,
+            sender: Some(sender),
+            worker: Some(worker),
+        })
+    }
+
+    fn send(&self, task: SaveTask) {
+        if let Some(sender) = &self.sender {
+            // Fails only if the save thread panicked, which it reports itself.
+            let _ = sender.send(task);
+        }
+    }
+
+    /// Reads `level.json`, `None` for a world which was never saved.
+    pub fn load_level(&self) -> io::Result<Option<LevelData>> {
+        match fs::read(self.directory.join(LEVEL_FILE)) {
+            Ok(data) => serde_json::from_slice(&data)
+                .map(Some)
+                .map_err(io::Error::from),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn save_level(&self, level: LevelData) {
+        self.send(SaveTask::Level(level));
+    }
+
+    /// Loads a column, waiting for the saves queued before.
+    pub fn load_column(
+        &self,
+        position: IVec2,
+        height: WorldHeight,
+        block_states: &BlockStatesLoader,
+    ) -> io::Result<Option<ChunkColumn>> {
+        let (reply, result) = mpsc::channel();
+
+        self.send(SaveTask::Load(position, reply));
+
+        match result.recv() {
+            Ok(data) => data?
+                .map(|data| decode_column(&data, height, block_states))
+                .transpose(),
+            Err(_) => Err(io::Error::other("the save thread stopped")),
+        }
+    }
+
+    /// Queues a column to be written in the background.
+    pub fn save_column(
+        &self,
+        position: IVec2,
+        column: &ChunkColumn,
+        block_states: &BlockStatesLoader,
+    ) {
+        self.send(SaveTask::Column(
+            position,
+            encode_column(column, block_states),
+        ));
+    }
+}
+
+impl Drop for WorldSave {
+    /// Waits until everything queued is written.
+    fn drop(&mut self) {
+        self.sender.take();
+
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{block_states::DirtBlock, loaders::Model, redstone::LeverBlock};
+
+    /// Registers dirt and wall levers facing north, either before or after
+    /// each other.
+    fn block_states(dirt_first: bool) -> BlockStatesLoader {
+        let mut block_states = BlockStatesLoader::default();
+        let (dirt, lever) = if dirt_first { (1, 2) } else { (3, 1) };
+
+        block_states.register_block_with_model(dirt, "dirt", DirtBlock::default(), Model::empty());
+
+        for powered in [false, true] {
+            let block = LeverBlock {
+                face: "wall".into(),
+                facing: "north".into(),
+                powered,
+            };
+
+            block_states.register_block_with_model(
+                lever + usize::from(powered),
+                "lever",
+                block,
+                Model::empty(),
+            );
+        }
+
+        block_states
+    }
+
+    fn lever(block_states: &BlockStatesLoader, powered: &str) -> BlockState {
+        let properties = [("face", "wall"), ("facing", "north"), ("powered", powered)];
+
+        block_states.find_state("lever", properties).unwrap()
+    }
+
+    #[test]
+    fn blocks_keep_their_names_when_registered_in_another_order() {
+        let height = WorldHeight::default();
+        let (saved, loaded) = (block_states(true), block_states(false));
+        let dirt = saved.get_default_state("dirt").unwrap();
+
+        let mut column = ChunkColumn::new(height, [[BiomeId { value: 0 }; SIZE]; SIZE]);
+        column.set_block(0, 0, 0, dirt);
+        column.set_block(1, 0, 0, lever(&saved, "true"));
+        column.set_block(2, 0, 0, lever(&saved, "false"));
+
+        let data = encode_column(&column, &saved);
+        let column = decode_column(&data, height, &loaded).unwrap();
+        let blocks = [0, 1, 2, 3].map(|x| column.get_block(x, 0, 0).unwrap().value);
+
+        assert_eq!(
+            blocks,
+            [
+                loaded.get_default_state("dirt").unwrap().value,
+                lever(&loaded, "true").value,
+                lever(&loaded, "false").value,
+                EMPTY_BLOCK.value,
+            ]
+        );
+    }
+}
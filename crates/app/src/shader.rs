@@ -0,0 +1,188 @@
+//! Shader the sections of the world are drawn with, its sources are in
+//! `assets/shaders/block.*`. The light levels of the vertices are looked up
+//! in the [`Lightmap`] and multiplied with the tint, ambient occlusion and
+//! shade, and blocks far away from the camera fade into the fog color so the
+//! edge of the loaded world blends into the sky.
+
+use crate::{lightmap::Lightmap, loaders::ShaderSources, mesh::SectionMesh};
+use macroquad::{
+    color::Color,
+    math::{Mat4, Vec3},
+    miniquad::{
+        Bindings, BlendFactor, BlendState, BlendValue, BufferLayout, Comparison, Equation,
+        FilterMode, MipmapFilterMode, PassAction, Pipeline, PipelineParams, ShaderMeta,
+        ShaderSource, TextureId, UniformBlockLayout, UniformDesc, UniformType, UniformsSource,
+        VertexAttribute, VertexFormat,
+    },
+    texture::Texture2D,
+    window::get_internal_gl,
+};
+
+/// Distance fog of the world.
+#[derive(Debug, Copy, Clone)]
+pub struct Fog {
+    pub color: Color,
+    /// Distance from the camera the fog begins at.
+    pub start: f32,
+    /// Distance from the camera blocks are hidden by the fog entirely.
+    pub end: f32,
+}
+
+/// Uniforms in the order of the shader, miniquad reads them tightly packed.
+#[repr(C)]
+struct BlockUniforms {
+    projection: [f32; 16],
+    camera_position: [f32; 3],
+    fog_color: [f32; 4],
+    fog_start: f32,
+    fog_end: f32,
+}
+
+pub struct BlockShader {
+    pipeline: Pipeline,
+    /// Stands in for missing textures, like macroquad does.
+    white: Texture2D,
+}
+
+impl Default for BlockShader {
+    fn default() -> Self {
+        let sources = ShaderSources::load("block");
+        let gl = unsafe { get_internal_gl() };
+
+        let shader = gl
+            .quad_context
+            .new_shader(
+                ShaderSource::Glsl {
+                    vertex: &sources.vertex,
+                    fragment: &sources.fragment,
+                },
+                ShaderMeta {
+                    images: vec!["Texture".to_owned(), "Lightmap".to_owned()],
+                    uniforms: UniformBlockLayout {
+                        uniforms: vec![
+                            UniformDesc::new("Projection", UniformType::Mat4),
+                            UniformDesc::new("CameraPosition", UniformType::Float3),
+                            UniformDesc::new("FogColor", UniformType::Float4),
+                            UniformDesc::new("FogStart", UniformType::Float1),
+                            UniformDesc::new("FogEnd", UniformType::Float1),
+                        ],
+                    },
+                },
+            )
+            .expect("failed to compile the block shader");
+
+        // Same as the default 3D pipeline of macroquad.
+        let pipeline = gl.quad_context.new_pipeline(
+            &[BufferLayout::default()],
+            &[
+                VertexAttribute::new("position", VertexFormat::Float3),
+                VertexAttribute::new("texcoord", VertexFormat::Float2),
+                VertexAttribute::new("tint", VertexFormat::Byte4),
+                VertexAttribute::new("light", VertexFormat::Byte2),
+                VertexAttribute::new("ao", VertexFormat::Byte1),
+                VertexAttribute::new("shade", VertexFormat::Byte1),
+            ],
+            shader,
+            PipelineParams {
+                color_blend: Some(BlendState::new(
+                    Equation::Add,
+                    BlendFactor::Value(BlendValue::SourceAlpha),
+                    BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
+                )),
+                depth_write: true,
+                depth_test: Comparison::LessOrEqual,
+                ..Default::default()
+            },
+        );
+
+        Self {
+            pipeline,
+            white: Texture2D::from_rgba8(1, 1, &[255; 4]),
+        }
+    }
+}
+
+impl BlockShader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts drawing sections seen through `projection`. Whatever macroquad
+    /// batched so far is drawn first.
+    pub fn begin<'a>(
+        &'a self,
+        projection: Mat4,
+        camera_position: Vec3,
+        fog: Fog,
+        lightmap: &Lightmap,
+    ) -> BlockPass<'a> {
+        let mut gl = unsafe { get_internal_gl() };
+
+        gl.flush();
+
+        let context = gl.quad_context;
+
+        context.begin_default_pass(PassAction::Nothing);
+        context.apply_pipeline(&self.pipeline);
+        context.apply_uniforms(UniformsSource::table(&BlockUniforms {
+            projection: projection.to_cols_array(),
+            camera_position: camera_position.to_array(),
+            fog_color: fog.color.into(),
+            fog_start: fog.start,
+            fog_end: fog.end,
+        }));
+
+        BlockPass {
+            shader: self,
+            lightmap: lightmap.texture().raw_miniquad_id(),
+        }
+    }
+}
+
+/// Sections being drawn, the pass ends once it's dropped.
+pub struct BlockPass<'a> {
+    shader: &'a BlockShader,
+    lightmap: TextureId,
+}
+
+impl BlockPass<'_> {
+    pub fn draw(&mut self, mesh: &SectionMesh) {
+        let gl = unsafe { get_internal_gl() };
+
+        for batch in mesh.batches() {
+            let texture = batch.texture.as_ref().unwrap_or(&self.shader.white);
+            let id = texture.raw_miniquad_id();
+
+            // Textures have no separate samplers, so the mipmaps are turned
+            // off for the batch instead.
+            if batch.without_mipmaps {
+                gl.quad_context.texture_set_min_filter(
+                    id,
+                    FilterMode::Nearest,
+                    MipmapFilterMode::None,
+                );
+            }
+
+            gl.quad_context.apply_bindings(&Bindings {
+                vertex_buffers: vec![batch.vertex_buffer],
+                index_buffer: batch.index_buffer,
+                images: vec![id, self.lightmap],
+            });
+            gl.quad_context.draw(0, batch.index_count, 1);
+
+            if batch.without_mipmaps {
+                gl.quad_context.texture_set_min_filter(
+                    id,
+                    FilterMode::Nearest,
+                    MipmapFilterMode::Linear,
+                );
+            }
+        }
+    }
+}
+
+impl Drop for BlockPass<'_> {
+    fn drop(&mut self) {
+        unsafe { get_internal_gl() }.quad_context.end_render_pass();
+    }
+}
@@ -0,0 +1,351 @@
+//! Sky drawn behind the world: a gradient from the color of the biome's sky
+//! above to its fog color at the horizon, the sun and the moon going around
+//! over the day and stars at night.
+//!
+//! Everything is placed around the camera and drawn without depth, so the
+//! world always covers it.
+
+use crate::{
+    loaders::{Biome, ShaderSources},
+    time::DAY_LENGTH,
+    worldgen::WorldgenRandom,
+};
+use macroquad::miniquad::{
+    BlendFactor, BlendState, BlendValue, Comparison, Equation, PipelineParams, ShaderSource,
+};
+use macroquad::{
+    color::Color,
+    material::{gl_use_default_material, gl_use_material, load_material, Material, MaterialParams},
+    math::{vec2, vec3, Quat, Vec3},
+    models::{draw_mesh, Mesh, Vertex},
+};
+use std::f32::consts::{PI, TAU};
+
+/// Distance of the sky from the camera, only the size of the things on it
+/// depends on it.
+const SKY_RADIUS: f32 = 100.0;
+/// Half of the width of the sun.
+const SUN_SIZE: f32 = 30.0;
+const MOON_SIZE: f32 = 20.0;
+const STAR_COUNT: usize = 1500;
+/// Stars drawn with a single mesh, macroquad limits the size of draw calls.
+const STARS_PER_MESH: usize = 200;
+/// Seed vanilla places its stars with.
+const STAR_SEED: u64 = 10842;
+/// Segments of the sky dome around the vertical axis.
+const DOME_SEGMENTS: usize = 16;
+/// Rings of the sky dome as their elevation in degrees and how far their
+/// color went from the fog color to the sky color.
+const DOME_RINGS: [(f32, f32); 5] = [
+    (-90.0, 0.0),
+    (0.0, 0.0),
+    (10.0, 0.55),
+    (30.0, 0.9),
+    (90.0, 1.0),
+];
+
+/// Position of the sun over the day, vanilla's celestial angle. It's 0 at
+/// noon and 0.5 at midnight, in turns.
+pub fn celestial_angle(day_time: f32) -> f32 {
+    let day = (day_time / DAY_LENGTH as f32 - 0.25).rem_euclid(1.0);
+    let eased = 0.5 - (day * PI).cos() / 2.0;
+
+    (day * 2.0 + eased) / 3.0
+}
+
+/// How much of the sky color is left, 1 during the day and 0 at night.
+pub fn sky_brightness(angle: f32) -> f32 {
+    ((angle * TAU).cos() * 2.0 + 0.5).clamp(0.0, 1.0)
+}
+
+/// Share of the sky light left, from 0.2 at night to 1 during the day.
+pub fn sky_darken(angle: f32) -> f32 {
+    ((angle * TAU).cos() * 2.0 + 0.2).clamp(0.0, 1.0) * 0.8 + 0.2
+}
+
+/// Levels taken off the sky light by blocks which check how bright it is,
+/// vanilla's `skyDarken`. It goes from 0 during the day to 11 at night.
+pub fn sky_light_darkening(angle: f32) -> u8 {
+    ((1.0 - sky_brightness(angle)) * 11.0) as u8
+}
+
+pub fn star_brightness(angle: f32) -> f32 {
+    let brightness = (1.0 - ((angle * TAU).cos() * 2.0 + 0.25)).clamp(0.0, 1.0);
+
+    brightness * brightness * 0.5
+}
+
+/// Glow of the horizon around the sun while it rises or sets.
+pub fn sunrise_color(angle: f32) -> Option<Color> {
+    let cos = (angle * TAU).cos();
+
+    if !(-0.4..=0.4).contains(&cos) {
+        return None;
+    }
+
+    let f = cos / 0.4 * 0.5 + 0.5;
+    let alpha = 1.0 - (1.0 - (f * PI).sin()) * 0.99;
+
+    Some(Color::new(
+        f * 0.3 + 0.7,
+        f * f * 0.7 + 0.2,
+        0.2,
+        alpha * alpha,
+    ))
+}
+
+/// Direction from the camera towards the sun, which rises in the east.
+pub fn sun_direction(angle: f32) -> Vec3 {
+    Quat::from_rotation_z(angle * TAU) * Vec3::Y
+}
+
+fn lerp_color(from: Color, to: Color, t: f32) -> Color {
+    Color::from_vec(from.to_vec().lerp(to.to_vec(), t))
+}
+
+/// Colors of the sky at a time of the day.
+#[derive(Debug, Copy, Clone)]
+pub struct SkyColors {
+    /// Color straight up.
+    pub sky: Color,
+    /// Color at the horizon, distant blocks fade into it.
+    pub fog: Color,
+}
+
+impl SkyColors {
+    pub fn new(biome: &Biome, angle: f32) -> Self {
+        let brightness = sky_brightness(angle);
+        let sky = biome.sky_color;
+        let fog = biome.fog_color;
+
+        // The fog never gets fully black, and stays a bit blue at night.
+        Self {
+            sky: Color::new(
+                sky.r * brightness,
+                sky.g * brightness,
+                sky.b * brightness,
+                1.0,
+            ),
+            fog: Color::new(
+                fog.r * (brightness * 0.94 + 0.06),
+                fog.g * (brightness * 0.94 + 0.06),
+                fog.b * (brightness * 0.91 + 0.09),
+                1.0,
+            ),
+        }
+    }
+}
+
+pub struct SkyRenderer {
+    material: Material,
+    /// Corners of every star, relative to the camera before the sky turns.
+    stars: Vec<[Vec3; 4]>,
+}
+
+impl Default for SkyRenderer {
+    fn default() -> Self {
+        let sources = ShaderSources::load("sky");
+        let material = load_material(
+            ShaderSource::Glsl {
+                vertex: &sources.vertex,
+                fragment: &sources.fragment,
+            },
+            MaterialParams {
+                pipeline_params: PipelineParams {
+                    color_blend: Some(BlendState::new(
+                        Equation::Add,
+                        BlendFactor::Value(BlendValue::SourceAlpha),
+                        BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
+                    )),
+                    depth_write: false,
+                    depth_test: Comparison::Always,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        )
+        .expect("failed to compile the sky shader");
+
+        Self {
+            material,
+            stars: generate_stars(),
+        }
+    }
+}
+
+impl SkyRenderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Draws the sky around the camera, has to happen before the world.
+    pub fn draw(&self, camera_position: Vec3, angle: f32, colors: SkyColors) {
+        gl_use_material(&self.material);
+
+        draw_mesh(&self.dome(camera_position, angle, colors));
+
+        let stars = star_brightness(angle);
+
+        if stars > 0.0 {
+            let rotation = Quat::from_rotation_z(angle * TAU);
+            let color = Color::new(stars, stars, stars, stars);
+
+            for stars in self.stars.chunks(STARS_PER_MESH) {
+                let mut mesh = empty_mesh();
+
+                for corners in stars {
+                    push_quad(
+                        &mut mesh,
+                        corners.map(|corner| camera_position + rotation * corner),
+                        color,
+                    );
+                }
+
+                draw_mesh(&mesh);
+            }
+        }
+
+        let sun = sun_direction(angle);
+        let mut mesh = empty_mesh();
+
+        push_quad(
+            &mut mesh,
+            billboard(camera_position + sun * SKY_RADIUS, sun, Vec3::Z, SUN_SIZE),
+            Color::new(1.0, 0.95, 0.7, 1.0),
+        );
+        push_quad(
+            &mut mesh,
+            billboard(camera_position - sun * SKY_RADIUS, -sun, Vec3::Z, MOON_SIZE),
+            Color::new(0.85, 0.87, 0.92, 1.0),
+        );
+
+        draw_mesh(&mesh);
+
+        gl_use_default_material();
+    }
+
+    /// Gradient of the sky, tinted towards the sun while it rises or sets.
+    fn dome(&self, camera_position: Vec3, angle: f32, colors: SkyColors) -> Mesh {
+        let sunrise = sunrise_color(angle);
+        let sun_side = sun_direction(angle).x.signum();
+        let mut mesh = empty_mesh();
+
+        for &(elevation, t) in DOME_RINGS.iter() {
+            let elevation = elevation.to_radians();
+
+            for segment in 0..=DOME_SEGMENTS {
+                let azimuth = segment as f32 / DOME_SEGMENTS as f32 * TAU;
+                let direction = vec3(
+                    azimuth.cos() * elevation.cos(),
+                    elevation.sin(),
+                    azimuth.sin() * elevation.cos(),
+                );
+
+                let mut color = lerp_color(colors.fog, colors.sky, t);
+
+                if let Some(sunrise) = sunrise {
+                    let facing = (azimuth.cos() * sun_side).max(0.0);
+                    let height = 1.0 - (elevation / 30f32.to_radians()).clamp(0.0, 1.0);
+
+                    color = lerp_color(color, sunrise, facing * facing * height * sunrise.a);
+                    color.a = 1.0;
+                }
+
+                mesh.vertices.push(Vertex {
+                    position: camera_position + direction * SKY_RADIUS,
+                    uv: vec2(0.0, 0.0),
+                    color,
+                });
+            }
+        }
+
+        let row = DOME_SEGMENTS as u16 + 1;
+
+        for ring in 0..DOME_RINGS.len() as u16 - 1 {
+            for segment in 0..DOME_SEGMENTS as u16 {
+                let i = ring * row + segment;
+
+                // Facing the center of the dome.
+                mesh.indices
+                    .extend([i, i + 1, i + row + 1, i + row + 1, i + row, i]);
+            }
+        }
+
+        mesh
+    }
+}
+
+fn empty_mesh() -> Mesh {
+    Mesh {
+        vertices: Vec::new(),
+        indices: Vec::new(),
+        texture: None,
+    }
+}
+
+/// Adds a quad with the corners in counterclockwise order.
+fn push_quad(mesh: &mut Mesh, corners: [Vec3; 4], color: Color) {
+    let i = mesh.vertices.len() as u16;
+    let uvs = [
+        vec2(0.0, 0.0),
+        vec2(1.0, 0.0),
+        vec2(1.0, 1.0),
+        vec2(0.0, 1.0),
+    ];
+
+    for (position, uv) in corners.into_iter().zip(uvs) {
+        mesh.vertices.push(Vertex {
+            position,
+            uv,
+            color,
+        });
+    }
+
+    mesh.indices.extend([i, i + 1, i + 2, i + 2, i + 3, i]);
+}
+
+/// Corners of a square at `center` facing the camera, which looks along
+/// `direction`. `side` is one of the directions the square spans.
+fn billboard(center: Vec3, direction: Vec3, side: Vec3, size: f32) -> [Vec3; 4] {
+    let u = side.normalize() * size;
+    let v = u.cross(direction.normalize());
+
+    [
+        center - u - v,
+        center + u - v,
+        center + u + v,
+        center - u + v,
+    ]
+}
+
+/// Scatters the stars over the sky like vanilla does.
+fn generate_stars() -> Vec<[Vec3; 4]> {
+    let mut random = WorldgenRandom::new(STAR_SEED);
+    let mut stars = Vec::with_capacity(STAR_COUNT);
+
+    for _ in 0..STAR_COUNT {
+        let mut next = || random.next_f64() as f32;
+        let direction = vec3(next() * 2.0 - 1.0, next() * 2.0 - 1.0, next() * 2.0 - 1.0);
+        let size = 0.15 + next() * 0.1;
+        let spin = next() * TAU;
+
+        // Only directions inside of the sphere are used, so the stars spread evenly.
+        let length = direction.length_squared();
+
+        if !(0.01..1.0).contains(&length) {
+            continue;
+        }
+
+        let direction = direction.normalize();
+        let side = Quat::from_axis_angle(direction, spin) * direction.any_orthonormal_vector();
+
+        stars.push(billboard(
+            direction * SKY_RADIUS,
+            direction,
+            side,
+            size * SKY_RADIUS / 100.0,
+        ));
+    }
+
+    stars
+}
@@ -0,0 +1,754 @@
+//! Structure templates, boxes of blocks stored like vanilla's `.nbt`
+//! structure files: a palette of block states and every block as a position
+//! with an index into the palette.
+
+use crate::{
+    anvil::PaletteEntry,
+    chunk::{BlockState, ChunkManager},
+    loaders::BlockStatesLoader,
+    redstone::{horizontal_direction, horizontal_name},
+    world::World,
+};
+use data::{model::Direction, nbt};
+use macroquad::math::{ivec3, IVec3};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io,
+    path::Path,
+};
+
+/// Data version of Minecraft 1.20.1, which the files are compatible with.
+pub const DATA_VERSION: i32 = 3465;
+/// Block which keeps whatever is in the world when a structure is placed.
+pub const STRUCTURE_VOID: &str = "minecraft:structure_void";
+
+/// Rotation around the vertical axis, clockwise when seen from above.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Rotation {
+    #[default]
+    None,
+    Clockwise90,
+    Clockwise180,
+    CounterClockwise90,
+}
+
+impl Rotation {
+    /// Every rotation, each a quarter turn further than the one before.
+    pub const ALL: [Self; 4] = [
+        Self::None,
+        Self::Clockwise90,
+        Self::Clockwise180,
+        Self::CounterClockwise90,
+    ];
+
+    fn turns(self) -> usize {
+        match self {
+            Self::None => 0,
+            Self::Clockwise90 => 1,
+            Self::Clockwise180 => 2,
+            Self::CounterClockwise90 => 3,
+        }
+    }
+
+    /// The rotation a quarter turn further clockwise.
+    pub fn clockwise(self) -> Self {
+        Self::ALL[(self.turns() + 1) % Self::ALL.len()]
+    }
+
+    pub fn rotate(self, direction: Direction) -> Direction {
+        (0..self.turns()).fold(direction, |direction, _| match direction {
+            Direction::Top | Direction::Bottom => direction,
+            direction => direction.rotate_y(),
+        })
+    }
+
+    /// Rotates a position around the origin, so north turns into east.
+    pub fn rotate_position(self, position: IVec3) -> IVec3 {
+        (0..self.turns()).fold(position, |p, _| ivec3(p.z, p.y, -p.x))
+    }
+}
+
+/// Flip of a structure, applied before its rotation.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Mirror {
+    #[default]
+    None,
+    /// Swaps north and south.
+    LeftRight,
+    /// Swaps east and west.
+    FrontBack,
+}
+
+impl Mirror {
+    pub fn mirror(self, direction: Direction) -> Direction {
+        match (self, direction) {
+            (Self::LeftRight, Direction::Front | Direction::Back)
+            | (Self::FrontBack, Direction::Right | Direction::Left) => direction.opposite(),
+            _ => direction,
+        }
+    }
+
+    pub fn mirror_position(self, position: IVec3) -> IVec3 {
+        match self {
+            Self::None => position,
+            Self::LeftRight => position * ivec3(1, 1, -1),
+            Self::FrontBack => position * ivec3(-1, 1, 1),
+        }
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+pub struct PlaceSettings {
+    pub rotation: Rotation,
+    pub mirror: Mirror,
+    /// Keeps the blocks of the world where the structure has air.
+    pub ignore_air: bool,
+}
+
+impl PlaceSettings {
+    pub fn transform_position(&self, position: IVec3) -> IVec3 {
+        self.rotation
+            .rotate_position(self.mirror.mirror_position(position))
+    }
+
+    pub fn transform_direction(&self, direction: Direction) -> Direction {
+        self.rotation.rotate(self.mirror.mirror(direction))
+    }
+
+    /// Turns the properties which depend on the orientation of the block,
+    /// like `facing`, `axis` and connections named after the directions.
+    pub fn transform_state(&self, entry: &PaletteEntry) -> PaletteEntry {
+        let quarter_turn = self.rotation.turns() % 2 == 1;
+        let transform_name = |name: &str| {
+            horizontal_direction(name)
+                .and_then(|direction| horizontal_name(self.transform_direction(direction)))
+        };
+
+        let properties = entry
+            .properties
+            .iter()
+            .map(|(key, value)| match key.as_str() {
+                "facing" => (
+                    key.clone(),
+                    transform_name(value).map_or_else(|| value.clone(), str::to_owned),
+                ),
+                "axis" if quarter_turn => (
+                    key.clone(),
+                    match value.as_str() {
+                        "x" => "z".to_owned(),
+                        "z" => "x".to_owned(),
+                        _ => value.clone(),
+                    },
+                ),
+                _ => (
+                    transform_name(key).map_or_else(|| key.clone(), str::to_owned),
+                    value.clone(),
+                ),
+            })
+            .collect();
+
+        PaletteEntry {
+            name: entry.name.clone(),
+            properties,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StructureBlock {
+    pub pos: IVec3,
+    /// Index into the palette of the structure.
+    pub state: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StructureTemplate {
+    #[serde(rename = "DataVersion")]
+    pub data_version: i32,
+    pub size: IVec3,
+    pub palette: Vec<PaletteEntry>,
+    pub blocks: Vec<StructureBlock>,
+    /// Kept so files from vanilla load, maqo has no entities.
+    #[serde(default)]
+    pub entities: Vec<nbt::Tag>,
+}
+
+impl StructureTemplate {
+    /// Copies the box of blocks starting at `from`, unloaded positions are left out.
+    pub fn capture(
+        block_states: &BlockStatesLoader,
+        chunks: &ChunkManager,
+        from: IVec3,
+        size: IVec3,
+    ) -> Self {
+        let mut palette = Vec::new();
+        let mut palette_ids = HashMap::new();
+        let mut blocks = Vec::new();
+
+        for y in 0..size.y {
+            for z in 0..size.z {
+                for x in 0..size.x {
+                    let pos = ivec3(x, y, z);
+                    let Some(state) = chunks.get_block(from + pos) else {
+                        continue;
+                    };
+
+                    let index = *palette_ids.entry(state.value).or_insert_with(|| {
+                        palette.push(PaletteEntry::from_state(block_states, state));
+
+                        palette.len() as i32 - 1
+                    });
+
+                    blocks.push(StructureBlock { pos, state: index });
+                }
+            }
+        }
+
+        Self {
+            data_version: DATA_VERSION,
+            size,
+            palette,
+            blocks,
+            entities: Vec::new(),
+        }
+    }
+
+    /// Reads a structure file, which is usually gzip compressed. Files
+    /// ending in `.snbt` hold the structure as text instead.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "snbt")
+        {
+            fs::read_to_string(path)?
+                .parse()
+                .and_then(nbt::from_tag)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+        } else {
+            nbt::from_reader(File::open(path)?)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        nbt::to_writer(File::create(path)?, "", self, nbt::Compression::Gzip)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Size of the structure once placed with `settings`.
+    pub fn transformed_size(&self, settings: &PlaceSettings) -> IVec3 {
+        settings.transform_position(self.size).abs()
+    }
+
+    /// The blocks to place with `settings`, relative to the first one.
+    ///
+    /// Fails without placing anything if a block isn't registered, so a
+    /// structure is never left half built.
+    pub fn resolve(
+        &self,
+        block_states: &BlockStatesLoader,
+        settings: &PlaceSettings,
+    ) -> io::Result<Vec<(IVec3, BlockState)>> {
+        let palette = self
+            .palette
+            .iter()
+            .map(|entry| {
+                if entry.name == STRUCTURE_VOID || settings.ignore_air && entry.is_air() {
+                    return Ok(None);
+                }
+
+                settings
+                    .transform_state(entry)
+                    .to_state(block_states)
+                    .map(Some)
+                    .ok_or_else(|| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("structure contains unknown block {entry}"),
+                        )
+                    })
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        Ok(self
+            .blocks
+            .iter()
+            .filter_map(|block| {
+                let state = (*palette.get(usize::try_from(block.state).ok()?)?)?;
+
+                Some((settings.transform_position(block.pos), state))
+            })
+            .collect())
+    }
+
+    /// Places the structure so its first block ends up at `origin`, the rest
+    /// extends in the directions the settings turn it to. Returns the number
+    /// of changed blocks.
+    ///
+    /// All blocks are set before their neighbors get notified, so blocks
+    /// which depend on each other don't break while half of them are missing.
+    pub fn place(
+        &self,
+        world: &mut World,
+        origin: IVec3,
+        settings: &PlaceSettings,
+    ) -> io::Result<usize> {
+        let mut placed = Vec::new();
+
+        for (position, state) in self.resolve(world.block_states, settings)? {
+            let position = origin + position;
+
+            if world.chunks.set_block(position, state, world.block_states) {
+                placed.push(position);
+            }
+        }
+
+        for &position in placed.iter() {
+            world.notify_neighbors(position);
+
+            if let Some(block) = world.get_base_block(position) {
+                block.block.on_place(world, position);
+            }
+        }
+
+        Ok(placed.len())
+    }
+}
+
+/// Building tool which copies a box of the world between two marked corners
+/// and pastes it somewhere else, turned by its settings.
+#[derive(Debug, Default)]
+pub struct StructureTool {
+    corners: [Option<IVec3>; 2],
+    clipboard: Option<StructureTemplate>,
+    pub settings: PlaceSettings,
+}
+
+impl StructureTool {
+    /// Marks a corner of the box to copy, replacing the older of the two.
+    pub fn mark(&mut self, position: IVec3) {
+        self.corners = [self.corners[1], Some(position)];
+    }
+
+    /// Copies the box between the marked corners, `None` until both are marked.
+    pub fn copy(
+        &mut self,
+        block_states: &BlockStatesLoader,
+        chunks: &ChunkManager,
+    ) -> Option<&StructureTemplate> {
+        let [Some(a), Some(b)] = self.corners else {
+            return None;
+        };
+
+        let from = a.min(b);
+        let template = StructureTemplate::capture(block_states, chunks, from, a.max(b) - from + 1);
+
+        Some(self.clipboard.insert(template))
+    }
+
+    /// Places the copied blocks at `origin`, returns the number of changed blocks.
+    pub fn paste(&self, world: &mut World, origin: IVec3) -> io::Result<usize> {
+        match &self.clipboard {
+            Some(template) => template.place(world, origin, &self.settings),
+            None => Ok(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        block_states::DirtBlock,
+        chunk::{BiomeId, ChunkColumn, WorldHeight, SIZE},
+        loaders::Model,
+        plant::LogBlock,
+        redstone::{LeverBlock, HORIZONTAL},
+    };
+    use macroquad::math::ivec2;
+    use std::{collections::BTreeMap, env, process};
+
+    /// Registers dirt, wall levers and oak logs, without their models.
+    fn block_states() -> BlockStatesLoader {
+        let mut block_states = BlockStatesLoader::default();
+        let mut id = 1;
+
+        block_states.register_block_with_model(id, "dirt", DirtBlock::default(), Model::empty());
+
+        for (_, facing) in HORIZONTAL {
+            id += 1;
+
+            let lever = LeverBlock {
+                face: "wall".into(),
+                facing: facing.into(),
+                powered: false,
+            };
+
+            block_states.register_block_with_model(id, "lever", lever, Model::empty());
+        }
+
+        for axis in ["x", "y", "z"] {
+            id += 1;
+
+            let log = LogBlock { axis: axis.into() };
+
+            block_states.register_block_with_model(id, "oak_log", log, Model::empty());
+        }
+
+        block_states
+    }
+
+    fn chunks() -> ChunkManager {
+        let mut chunks = ChunkManager::new();
+
+        chunks.add_chunk_column(
+            ivec2(0, 0),
+            ChunkColumn::new(WorldHeight::default(), [[BiomeId { value: 0 }; SIZE]; SIZE]),
+        );
+
+        chunks
+    }
+
+    fn entry(name: &str, properties: &[(&str, &str)]) -> PaletteEntry {
+        PaletteEntry {
+            name: format!("minecraft:{name}"),
+            properties: properties
+                .iter()
+                .map(|&(key, value)| (key.to_owned(), value.to_owned()))
+                .collect(),
+        }
+    }
+
+    /// Dirt with a log lying on it along x and a lever on its east side.
+    fn build(block_states: &BlockStatesLoader, chunks: &mut ChunkManager, from: IVec3) {
+        let blocks = [
+            (ivec3(0, 0, 0), entry("dirt", &[])),
+            (ivec3(1, 0, 0), entry("dirt", &[])),
+            (ivec3(0, 1, 0), entry("oak_log", &[("axis", "x")])),
+            (ivec3(2, 0, 0), entry("lever", &[("facing", "east")])),
+        ];
+
+        for (position, entry) in blocks {
+            let state = entry.to_state(block_states).unwrap();
+
+            chunks.set_block(from + position, state, block_states);
+        }
+    }
+
+    fn name_at(block_states: &BlockStatesLoader, chunks: &ChunkManager, position: IVec3) -> String {
+        PaletteEntry::from_state(block_states, chunks.get_block(position).unwrap()).to_string()
+    }
+
+    #[test]
+    fn rotations_turn_positions_like_directions() {
+        for rotation in Rotation::ALL {
+            for direction in Direction::ALL {
+                assert_eq!(
+                    rotation.rotate_position(direction.as_vec3()),
+                    rotation.rotate(direction).as_vec3(),
+                    "{rotation:?} {direction:?}"
+                );
+            }
+
+            let full_turn = (0..4).fold(rotation, |rotation, _| rotation.clockwise());
+
+            assert_eq!(full_turn, rotation);
+        }
+
+        assert_eq!(
+            Rotation::Clockwise90.rotate(Direction::Front),
+            Direction::Right
+        );
+        assert_eq!(
+            Rotation::CounterClockwise90.rotate(Direction::Front),
+            Direction::Left
+        );
+        assert_eq!(Rotation::Clockwise90.rotate(Direction::Top), Direction::Top);
+    }
+
+    #[test]
+    fn mirrors_flip_positions_like_directions() {
+        for mirror in [Mirror::None, Mirror::LeftRight, Mirror::FrontBack] {
+            for direction in Direction::ALL {
+                assert_eq!(
+                    mirror.mirror_position(direction.as_vec3()),
+                    mirror.mirror(direction).as_vec3(),
+                    "{mirror:?} {direction:?}"
+                );
+            }
+        }
+
+        assert_eq!(Mirror::LeftRight.mirror(Direction::Front), Direction::Back);
+        assert_eq!(Mirror::LeftRight.mirror(Direction::Right), Direction::Right);
+        assert_eq!(Mirror::FrontBack.mirror(Direction::Right), Direction::Left);
+    }
+
+    #[test]
+    fn mirrors_are_applied_before_rotations() {
+        let settings = PlaceSettings {
+            rotation: Rotation::Clockwise90,
+            mirror: Mirror::LeftRight,
+            ..Default::default()
+        };
+
+        // North is mirrored to south, which is turned to west.
+        assert_eq!(
+            settings.transform_direction(Direction::Front),
+            Direction::Left
+        );
+        assert_eq!(
+            settings.transform_position(ivec3(0, 0, 1)),
+            Direction::Left.as_vec3()
+        );
+    }
+
+    #[test]
+    fn orientation_properties_are_transformed() {
+        let turned = PlaceSettings {
+            rotation: Rotation::Clockwise90,
+            ..Default::default()
+        };
+        let half_turned = PlaceSettings {
+            rotation: Rotation::Clockwise180,
+            ..Default::default()
+        };
+        let mirrored = PlaceSettings {
+            mirror: Mirror::LeftRight,
+            ..Default::default()
+        };
+
+        let lever = entry("lever", &[("face", "wall"), ("facing", "north")]);
+
+        assert_eq!(
+            turned.transform_state(&lever),
+            entry("lever", &[("face", "wall"), ("facing", "east")])
+        );
+        assert_eq!(
+            mirrored.transform_state(&lever),
+            entry("lever", &[("face", "wall"), ("facing", "south")])
+        );
+
+        let log = entry("oak_log", &[("axis", "x")]);
+
+        assert_eq!(
+            turned.transform_state(&log),
+            entry("oak_log", &[("axis", "z")])
+        );
+        assert_eq!(half_turned.transform_state(&log), log);
+        assert_eq!(
+            turned.transform_state(&entry("oak_log", &[("axis", "y")])),
+            entry("oak_log", &[("axis", "y")])
+        );
+
+        let wire = entry(
+            "redstone_wire",
+            &[
+                ("north", "side"),
+                ("east", "up"),
+                ("south", "none"),
+                ("west", "none"),
+                ("power", "3"),
+            ],
+        );
+
+        assert_eq!(
+            turned.transform_state(&wire),
+            entry(
+                "redstone_wire",
+                &[
+                    ("north", "none"),
+                    ("east", "side"),
+                    ("south", "up"),
+                    ("west", "none"),
+                    ("power", "3"),
+                ],
+            )
+        );
+    }
+
+    #[test]
+    fn captured_structures_are_placed_back() {
+        let block_states = block_states();
+        let mut chunks = chunks();
+        let from = ivec3(1, 0, 1);
+
+        build(&block_states, &mut chunks, from);
+
+        let template = StructureTemplate::capture(&block_states, &chunks, from, ivec3(3, 2, 1));
+
+        assert_eq!(template.blocks.len(), 6);
+        assert_eq!(template.palette.len(), 4);
+
+        // The template survives being saved as well.
+        let path = env::temp_dir().join(format!("maqo-structure-{}.nbt", process::id()));
+
+        template.save(&path).unwrap();
+
+        let loaded = StructureTemplate::load(&path).unwrap();
+
+        fs::remove_file(&path).unwrap();
+
+        let origin = ivec3(8, 4, 8);
+        let placed = loaded
+            .place(
+                &mut World::new(&block_states, &mut chunks),
+                origin,
+                &PlaceSettings::default(),
+            )
+            .unwrap();
+
+        // Air where there already is air doesn't count as a change.
+        assert_eq!(placed, 4);
+
+        for y in 0..2 {
+            for x in 0..3 {
+                let position = ivec3(x, y, 0);
+
+                assert_eq!(
+                    name_at(&block_states, &chunks, origin + position),
+                    name_at(&block_states, &chunks, from + position),
+                    "{position}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rotated_structures_turn_their_blocks() {
+        let block_states = block_states();
+        let mut chunks = chunks();
+
+        build(&block_states, &mut chunks, IVec3::ZERO);
+
+        let template =
+            StructureTemplate::capture(&block_states, &chunks, IVec3::ZERO, ivec3(3, 2, 1));
+        let settings = PlaceSettings {
+            rotation: Rotation::Clockwise90,
+            ..Default::default()
+        };
+        let origin = ivec3(8, 4, 8);
+
+        assert_eq!(template.transformed_size(&settings), ivec3(1, 2, 3));
+
+        template
+            .place(
+                &mut World::new(&block_states, &mut chunks),
+                origin,
+                &settings,
+            )
+            .unwrap();
+
+        // East turns into south, so the row of blocks now runs towards -z.
+        assert_eq!(
+            name_at(&block_states, &chunks, origin),
+            "minecraft:dirt[snowy=false]"
+        );
+        assert_eq!(
+            name_at(&block_states, &chunks, origin + ivec3(0, 0, -1)),
+            "minecraft:dirt[snowy=false]"
+        );
+        assert_eq!(
+            name_at(&block_states, &chunks, origin + ivec3(0, 1, 0)),
+            "minecraft:oak_log[axis=z]"
+        );
+        assert_eq!(
+            name_at(&block_states, &chunks, origin + ivec3(0, 0, -2)),
+            "minecraft:lever[face=wall,facing=south,powered=false]"
+        );
+    }
+
+    #[test]
+    fn unknown_blocks_fail_without_placing() {
+        let block_states = block_states();
+        let mut chunks = chunks();
+        let template = StructureTemplate {
+            data_version: DATA_VERSION,
+            size: ivec3(2, 1, 1),
+            palette: vec![entry("dirt", &[]), entry("stone", &[])],
+            blocks: vec![
+                StructureBlock {
+                    pos: ivec3(0, 0, 0),
+                    state: 0,
+                },
+                StructureBlock {
+                    pos: ivec3(1, 0, 0),
+                    state: 1,
+                },
+            ],
+            entities: Vec::new(),
+        };
+
+        let err = template
+            .place(
+                &mut World::new(&block_states, &mut chunks),
+                ivec3(8, 4, 8),
+                &PlaceSettings::default(),
+            )
+            .unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            chunks.get_block(ivec3(8, 4, 8)).map(|state| state.value),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn the_tool_copies_between_its_corners() {
+        let block_states = block_states();
+        let mut chunks = chunks();
+        let mut tool = StructureTool::default();
+
+        build(&block_states, &mut chunks, IVec3::ZERO);
+
+        assert!(tool.copy(&block_states, &chunks).is_none());
+
+        // The older corner is dropped by a third one.
+        tool.mark(ivec3(5, 5, 5));
+        tool.mark(ivec3(2, 1, 0));
+        tool.mark(IVec3::ZERO);
+
+        let template = tool.copy(&block_states, &chunks).unwrap();
+
+        assert_eq!(template.size, ivec3(3, 2, 1));
+
+        tool.settings.rotation = Rotation::Clockwise180;
+
+        let placed = tool
+            .paste(&mut World::new(&block_states, &mut chunks), ivec3(8, 4, 8))
+            .unwrap();
+
+        assert_eq!(placed, 4);
+        assert_eq!(
+            name_at(&block_states, &chunks, ivec3(6, 4, 8)),
+            "minecraft:lever[face=wall,facing=west,powered=false]"
+        );
+    }
+
+    #[test]
+    fn bundled_structures_load() {
+        let block_states = block_states();
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../assets/structures/fallen_oak_log.snbt"
+        );
+        let template = StructureTemplate::load(path).unwrap();
+        let blocks = template
+            .resolve(&block_states, &PlaceSettings::default())
+            .unwrap();
+
+        assert_eq!(template.size, ivec3(4, 1, 1));
+        assert_eq!(blocks.len(), 4);
+        assert_eq!(
+            template.palette,
+            vec![PaletteEntry {
+                name: "minecraft:oak_log".to_owned(),
+                properties: BTreeMap::from([("axis".to_owned(), "x".to_owned())]),
+            }]
+        );
+    }
+}
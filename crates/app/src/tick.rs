@@ -0,0 +1,91 @@
+use macroquad::math::IVec3;
+use std::collections::{BTreeMap, HashSet};
+
+/// Amount of blocks picked for a random tick in every section per game tick.
+pub const DEFAULT_RANDOM_TICK_SPEED: u32 = 3;
+
+/// Upper bound of scheduled ticks which are run during a single game tick.
+pub const MAX_SCHEDULED_TICKS: usize = 65536;
+
+pub struct TickScheduler {
+    pub random_tick_speed: u32,
+    time: u64,
+    queue: BTreeMap<u64, Vec<IVec3>>,
+    pending: HashSet<IVec3>,
+}
+
+impl Default for TickScheduler {
+    fn default() -> Self {
+        Self {
+            random_tick_speed: DEFAULT_RANDOM_TICK_SPEED,
+            time: 0,
+            queue: BTreeMap::new(),
+            pending: HashSet::new(),
+        }
+    }
+}
+
+impl TickScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Amount of game ticks passed since the world was created.
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    /// Continues counting from `time`, used when loading a saved world.
+    pub fn set_time(&mut self, time: u64) {
+        self.time = time;
+    }
+
+    pub fn is_scheduled(&self, position: IVec3) -> bool {
+        self.pending.contains(&position)
+    }
+
+    /// Schedules a tick at `position` after `delay` game ticks.
+    ///
+    /// Returns `false` if the position already has a pending tick.
+    pub fn schedule(&mut self, position: IVec3, delay: u64) -> bool {
+        if !self.pending.insert(position) {
+            return false;
+        }
+
+        self.queue
+            .entry(self.time + delay.max(1))
+            .or_default()
+            .push(position);
+
+        true
+    }
+
+    /// Advances the time by one tick and returns positions whose ticks are due,
+    /// in the order they were scheduled.
+    pub fn advance(&mut self) -> Vec<IVec3> {
+        self.time += 1;
+
+        let mut due = Vec::new();
+
+        while let Some(mut entry) = self.queue.first_entry() {
+            if *entry.key() > self.time || due.len() >= MAX_SCHEDULED_TICKS {
+                break;
+            }
+
+            let positions = entry.get_mut();
+            let count = positions.len().min(MAX_SCHEDULED_TICKS - due.len());
+
+            due.extend(positions.drain(..count));
+
+            if positions.is_empty() {
+                entry.remove();
+            }
+        }
+
+        for position in due.iter() {
+            self.pending.remove(position);
+        }
+
+        due
+    }
+}
@@ -0,0 +1,155 @@
+//! Time of the day, which moves the sun and darkens the sky light at night.
+//!
+//! It's counted apart from the game time of [`TickScheduler`](crate::tick::TickScheduler),
+//! so setting it doesn't affect scheduled ticks.
+
+use std::{fmt, str::FromStr};
+
+/// Game ticks of a full day.
+pub const DAY_LENGTH: u64 = 24000;
+
+/// Times `/time set` accepts by name.
+pub const NAMED_TIMES: [(&str, u64); 4] = [
+    ("day", 1000),
+    ("noon", 6000),
+    ("night", 13000),
+    ("midnight", 18000),
+];
+
+#[derive(Debug, Copy, Clone)]
+pub struct WorldClock {
+    /// Ticks since the first sunrise, it keeps counting over the days.
+    day_time: u64,
+    /// Whether the time advances with the game ticks, vanilla's `doDaylightCycle`.
+    pub daylight_cycle: bool,
+}
+
+impl Default for WorldClock {
+    fn default() -> Self {
+        Self {
+            day_time: 0,
+            daylight_cycle: true,
+        }
+    }
+}
+
+impl WorldClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn day_time(&self) -> u64 {
+        self.day_time
+    }
+
+    /// Time into the current day, from 0 at sunrise to [`DAY_LENGTH`].
+    pub fn time_of_day(&self) -> u64 {
+        self.day_time % DAY_LENGTH
+    }
+
+    /// Days passed since the first one.
+    pub fn day(&self) -> u64 {
+        self.day_time / DAY_LENGTH
+    }
+
+    pub fn set_day_time(&mut self, day_time: u64) {
+        self.day_time = day_time;
+    }
+
+    /// Advances the time by one game tick.
+    pub fn tick(&mut self) {
+        // Huge times from commands stop at the end instead of overflowing.
+        if self.daylight_cycle {
+            self.day_time = self.day_time.saturating_add(1);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseTimeError {
+    UnknownAction(String),
+    InvalidTime(String),
+    MissingArgument,
+}
+
+impl fmt::Display for ParseTimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownAction(action) => write!(f, "unknown time action {action}"),
+            Self::InvalidTime(time) => write!(f, "invalid time {time}"),
+            Self::MissingArgument => f.write_str("missing time"),
+        }
+    }
+}
+
+impl std::error::Error for ParseTimeError {}
+
+/// Change of the time, written like the arguments of vanilla's `/time`
+/// command: `set noon`, `set 1d`, `add 100` or `query daytime`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TimeCommand {
+    /// Sets the time since the first sunrise, like vanilla this starts
+    /// counting the days over.
+    Set(u64),
+    Add(u64),
+    QueryDayTime,
+    QueryDay,
+}
+
+impl TimeCommand {
+    /// Applies the command, returns the value vanilla reports back, the time
+    /// of the day or the day.
+    pub fn execute(self, clock: &mut WorldClock) -> u64 {
+        match self {
+            Self::Set(time) => clock.set_day_time(time),
+            Self::Add(time) => clock.set_day_time(clock.day_time().saturating_add(time)),
+            Self::QueryDayTime => {}
+            Self::QueryDay => return clock.day(),
+        }
+
+        clock.time_of_day()
+    }
+}
+
+/// Parses a time in ticks, which can be given in days with `d` or seconds
+/// with `s` as well.
+fn parse_time(time: &str) -> Result<u64, ParseTimeError> {
+    let (number, unit) = match time.char_indices().last() {
+        Some((i, 'd')) => (&time[..i], DAY_LENGTH),
+        Some((i, 's')) => (&time[..i], 20),
+        Some((i, 't')) => (&time[..i], 1),
+        _ => (time, 1),
+    };
+
+    number
+        .parse::<f64>()
+        .ok()
+        .filter(|number| *number >= 0.0)
+        .map(|number| (number * unit as f64).round() as u64)
+        .ok_or_else(|| ParseTimeError::InvalidTime(time.to_owned()))
+}
+
+impl FromStr for TimeCommand {
+    type Err = ParseTimeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut args = s.trim().trim_start_matches("/time").split_whitespace();
+        let action = args.next().ok_or(ParseTimeError::MissingArgument)?;
+        let argument = args.next().ok_or(ParseTimeError::MissingArgument)?;
+
+        match action {
+            "set" => NAMED_TIMES
+                .iter()
+                .find(|(name, _)| *name == argument)
+                .map_or_else(|| parse_time(argument), |&(_, time)| Ok(time))
+                .map(Self::Set),
+            "add" => parse_time(argument).map(Self::Add),
+            "query" => match argument {
+                "daytime" => Ok(Self::QueryDayTime),
+                "day" => Ok(Self::QueryDay),
+                _ => Err(ParseTimeError::UnknownAction(format!("query {argument}"))),
+            },
+            _ => Err(ParseTimeError::UnknownAction(action.to_owned())),
+        }
+    }
+}
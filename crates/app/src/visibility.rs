@@ -0,0 +1,171 @@
+//! Occlusion culling of sections, like vanilla's visibility graph: each
+//! section knows which of its faces can see each other through blocks which
+//! aren't opaque, and only sections reachable from the camera that way are
+//! drawn.
+
+use crate::{
+    chunk::{BlockState, Chunk, ChunkManager, SIZE},
+    frustum::Frustum,
+};
+use data::model::Direction;
+use macroquad::math::IVec3;
+use std::collections::{HashSet, VecDeque};
+
+/// Fewer opaque blocks than this can't wall off any face of a section.
+const MIN_OPAQUE_BLOCKS: usize = SIZE * SIZE;
+
+/// Pairs of faces of a section connected through blocks which aren't opaque.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SectionVisibility {
+    bits: u64,
+}
+
+impl Default for SectionVisibility {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+impl SectionVisibility {
+    /// Every face sees every other one, like in a section of air.
+    pub const ALL: Self = Self {
+        bits: (1 << 36) - 1,
+    };
+    pub const NONE: Self = Self { bits: 0 };
+
+    fn bit(from: Direction, to: Direction) -> u64 {
+        1 << (from as usize * 6 + to as usize)
+    }
+
+    /// Flood fills the section through every block `is_opaque` rejects, each
+    /// filled area connects all faces it touches.
+    pub fn compute(chunk: &Chunk, is_opaque: impl Fn(BlockState) -> bool) -> Self {
+        let index = |x: usize, y: usize, z: usize| (y * SIZE + z) * SIZE + x;
+        let mut visited = vec![false; SIZE * SIZE * SIZE];
+        let mut opaque_blocks = 0;
+
+        for (y, layer) in chunk.blocks.iter().enumerate() {
+            for (z, row) in layer.iter().enumerate() {
+                for (x, &block) in row.iter().enumerate() {
+                    if is_opaque(block) {
+                        visited[index(x, y, z)] = true;
+                        opaque_blocks += 1;
+                    }
+                }
+            }
+        }
+
+        if opaque_blocks < MIN_OPAQUE_BLOCKS {
+            return Self::ALL;
+        }
+
+        let mut visibility = Self::NONE;
+        let mut stack = Vec::new();
+
+        for start in 0..visited.len() {
+            if visited[start] {
+                continue;
+            }
+
+            visited[start] = true;
+            stack.push(start);
+
+            let mut faces = Vec::new();
+
+            while let Some(i) = stack.pop() {
+                let position = IVec3::new(
+                    (i % SIZE) as i32,
+                    (i / (SIZE * SIZE)) as i32,
+                    (i / SIZE % SIZE) as i32,
+                );
+
+                for direction in Direction::ALL {
+                    let neighbor = position + direction.as_vec3();
+
+                    if neighbor.min_element() < 0 || neighbor.max_element() >= SIZE as i32 {
+                        if !faces.contains(&direction) {
+                            faces.push(direction);
+                        }
+
+                        continue;
+                    }
+
+                    let neighbor = index(
+                        neighbor.x as usize,
+                        neighbor.y as usize,
+                        neighbor.z as usize,
+                    );
+
+                    if !visited[neighbor] {
+                        visited[neighbor] = true;
+                        stack.push(neighbor);
+                    }
+                }
+            }
+
+            for &from in faces.iter() {
+                for &to in faces.iter() {
+                    visibility.bits |= Self::bit(from, to);
+                }
+            }
+        }
+
+        visibility
+    }
+
+    pub fn is_visible(self, from: Direction, to: Direction) -> bool {
+        self.bits & Self::bit(from, to) != 0
+    }
+}
+
+/// Finds the sections the camera in the section `camera` can see, going
+/// from section to section through faces which see each other, and never
+/// back towards the camera. Returns `None` if the camera isn't inside of a
+/// loaded section, nothing can be culled then.
+pub fn visible_sections(
+    chunks: &ChunkManager,
+    camera: IVec3,
+    frustum: &Frustum,
+) -> Option<Vec<IVec3>> {
+    chunks.get_visibility(camera)?;
+
+    let mut visible = Vec::new();
+    let mut queued = HashSet::from([camera]);
+    let mut queue = VecDeque::from([(camera, None, 0u8)]);
+
+    while let Some((section, entered, traveled)) = queue.pop_front() {
+        visible.push(section);
+
+        let Some(visibility) = chunks.get_visibility(section) else {
+            continue;
+        };
+
+        for direction in Direction::ALL {
+            if traveled & 1 << direction.opposite() as u8 != 0
+                || entered.is_some_and(|entered| !visibility.is_visible(entered, direction))
+            {
+                continue;
+            }
+
+            let neighbor = section + direction.as_vec3();
+            // Blocks are centered on their integer position.
+            let min = neighbor.as_vec3() * SIZE as f32 - 0.5;
+
+            if queued.contains(&neighbor)
+                || chunks.get_visibility(neighbor).is_none()
+                || !frustum.intersects_aabb(min, min + SIZE as f32)
+            {
+                continue;
+            }
+
+            queued.insert(neighbor);
+            queue.push_back((
+                neighbor,
+                Some(direction.opposite()),
+                traveled | 1 << direction as u8,
+            ));
+        }
+    }
+
+    Some(visible)
+}
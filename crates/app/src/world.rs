@@ -0,0 +1,373 @@
+use crate::{
+    block_states::BaseBlock,
+    chunk::{BlockState, ChunkManager, SIZE},
+    heightmap::HeightmapKind,
+    loaders::BlockStatesLoader,
+    sky,
+};
+use data::model::Direction;
+use macroquad::{
+    math::{ivec3, IVec2, IVec3, Vec3},
+    rand,
+};
+
+/// Maximum depth of chained neighbor updates, so blocks updating each other
+/// back and forth can't overflow the stack.
+pub const MAX_UPDATE_DEPTH: usize = 512;
+
+/// View of the world which block behaviors are allowed to modify.
+pub struct World<'a> {
+    pub block_states: &'a BlockStatesLoader,
+    pub chunks: &'a mut ChunkManager,
+    update_depth: usize,
+    wires_emit_power: bool,
+}
+
+impl<'a> World<'a> {
+    pub fn new(block_states: &'a BlockStatesLoader, chunks: &'a mut ChunkManager) -> Self {
+        Self {
+            block_states,
+            chunks,
+            update_depth: 0,
+            wires_emit_power: true,
+        }
+    }
+
+    pub fn get_block(&self, position: IVec3) -> Option<BlockState> {
+        self.chunks.get_block(position)
+    }
+
+    pub fn get_base_block(&self, position: IVec3) -> Option<&'a BaseBlock> {
+        let state = self.get_block(position)?;
+
+        self.block_states
+            .get_by_id(state.value.into())
+            .map(|(block, _)| block)
+    }
+
+    /// How bright `position` is to blocks which grow or spread, the higher
+    /// of its block light and its sky light darkened by the time of day.
+    pub fn get_brightness(&self, position: IVec3) -> u8 {
+        let Some(light) = self.chunks.get_light_level(position) else {
+            return 0;
+        };
+        let angle = sky::celestial_angle(self.chunks.clock.time_of_day() as f32);
+
+        light.block_light().max(
+            light
+                .sky_light()
+                .saturating_sub(sky::sky_light_darkening(angle)),
+        )
+    }
+
+    pub fn get_name(&self, position: IVec3) -> Option<&'a str> {
+        let state = self.get_block(position)?;
+
+        self.block_states.get_name_by_id(state.value.into())
+    }
+
+    /// Replaces a block and notifies its six neighbors about the change.
+    pub fn set_block(&mut self, position: IVec3, state: BlockState) -> bool {
+        let previous = self.get_block(position);
+
+        if !self.chunks.set_block(position, state, self.block_states) {
+            return false;
+        }
+
+        self.update_neighbors(position, state);
+
+        // A block which only changed its properties wasn't placed.
+        if previous.is_some_and(|previous| self.block_states.is_same_block(previous, state)) {
+            return true;
+        }
+
+        if let Some(block) = self.get_base_block(position) {
+            block.block.on_place(self, position);
+        }
+
+        true
+    }
+
+    /// Finds where the player should spawn inside of the column at `column`,
+    /// on the grass closest to its center if there is any.
+    pub fn find_spawn(&self, column: IVec2) -> Option<IVec3> {
+        let heightmap = self
+            .chunks
+            .get_chunk_column(column)?
+            .heightmap(HeightmapKind::MotionBlocking);
+        let origin = column * SIZE as i32;
+        let center = SIZE as i32 / 2;
+
+        let mut spawns: Vec<IVec3> = (0..SIZE)
+            .flat_map(|z| (0..SIZE).map(move |x| (x, z)))
+            .map(|(x, z)| {
+                ivec3(
+                    origin.x + x as i32,
+                    heightmap.get(x, z),
+                    origin.y + z as i32,
+                )
+            })
+            .collect();
+
+        spawns.sort_by_key(|spawn| {
+            (spawn.x - origin.x - center).pow(2) + (spawn.z - origin.y - center).pow(2)
+        });
+
+        spawns
+            .iter()
+            .find(|&&spawn| self.get_name(spawn - ivec3(0, 1, 0)) == Some("minecraft:grass_block"))
+            .or(spawns.first())
+            .copied()
+    }
+
+    /// Calls [`Block::on_use`](crate::block_states::Block::on_use) of the block at `position`.
+    pub fn use_block(&mut self, position: IVec3) -> bool {
+        let (Some(state), Some(block)) = (self.get_block(position), self.get_base_block(position))
+        else {
+            return false;
+        };
+
+        block.block.on_use(self, position, state)
+    }
+
+    /// Finds the first block hit by a ray, together with the face it was hit on.
+    pub fn raycast(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        max_distance: f32,
+    ) -> Option<(IVec3, Direction)> {
+        let direction = direction.normalize_or_zero();
+
+        // Blocks are centered on their integer position.
+        let origin = origin + 0.5;
+        let mut position = origin.floor().as_ivec3();
+
+        let step = IVec3::from_array(direction.to_array().map(|x| {
+            if x > 0.0 {
+                1
+            } else if x < 0.0 {
+                -1
+            } else {
+                0
+            }
+        }));
+        let delta = direction.recip().abs();
+        let mut distance = Vec3::from_array(std::array::from_fn(|i| match step[i] {
+            1 => (position[i] as f32 + 1.0 - origin[i]) * delta[i],
+            -1 => (origin[i] - position[i] as f32) * delta[i],
+            _ => f32::INFINITY,
+        }));
+
+        loop {
+            let axis = if distance.x < distance.y && distance.x < distance.z {
+                0
+            } else if distance.y < distance.z {
+                1
+            } else {
+                2
+            };
+
+            if distance[axis] > max_distance {
+                return None;
+            }
+
+            position[axis] += step[axis];
+            distance[axis] += delta[axis];
+
+            if self.get_base_block(position).is_some() {
+                let mut face = IVec3::ZERO;
+
+                face[axis] = -step[axis];
+
+                return Some((position, Direction::from_vec3(face)?));
+            }
+        }
+    }
+
+    /// Like [`World::set_block`], but first lets the placed block adapt its
+    /// shape to the blocks already surrounding it.
+    pub fn place_block(&mut self, position: IVec3, mut state: BlockState) -> bool {
+        for direction in Direction::ALL {
+            let Some((block, _)) = self.block_states.get_by_id(state.value.into()) else {
+                break;
+            };
+
+            let Some(neighbor) = self.get_block(position + direction.as_vec3()) else {
+                continue;
+            };
+
+            if let Some(new_state) = block
+                .block
+                .update_shape(self, position, state, direction, neighbor)
+            {
+                state = new_state;
+            }
+        }
+
+        self.set_block(position, state)
+    }
+
+    /// Calls [`Block::on_neighbor_changed`](crate::block_states::Block::on_neighbor_changed)
+    /// of the blocks around `position` without changing it.
+    pub fn notify_neighbors(&mut self, position: IVec3) {
+        if self.update_depth >= MAX_UPDATE_DEPTH {
+            return;
+        }
+
+        self.update_depth += 1;
+
+        for direction in Direction::ALL {
+            let neighbor = position + direction.as_vec3();
+
+            if let Some(block) = self.get_base_block(neighbor) {
+                block.block.on_neighbor_changed(self, neighbor, position);
+            }
+        }
+
+        self.update_depth -= 1;
+    }
+
+    fn update_neighbors(&mut self, position: IVec3, state: BlockState) {
+        if self.update_depth >= MAX_UPDATE_DEPTH {
+            return;
+        }
+
+        self.update_depth += 1;
+
+        for direction in Direction::ALL {
+            let neighbor = position + direction.as_vec3();
+
+            let (Some(neighbor_state), Some(block)) =
+                (self.get_block(neighbor), self.get_base_block(neighbor))
+            else {
+                continue;
+            };
+
+            if let Some(new_state) = block.block.update_shape(
+                self,
+                neighbor,
+                neighbor_state,
+                direction.opposite(),
+                state,
+            ) {
+                self.set_block(neighbor, new_state);
+            }
+        }
+
+        self.update_depth -= 1;
+
+        self.notify_neighbors(position);
+    }
+
+    /// Whether the block is solid enough to conduct strong redstone power.
+    pub fn is_conductor(&self, position: IVec3) -> bool {
+        self.get_base_block(position)
+            .is_some_and(|block| block.is_full_cube && block.is_opaque_cube)
+    }
+
+    /// Whether redstone wires currently emit power, it's turned off while
+    /// they look for power coming from other blocks.
+    pub fn wires_emit_power(&self) -> bool {
+        self.wires_emit_power
+    }
+
+    /// Strong power the block at `position` sends towards `direction`.
+    pub fn get_direct_signal(&self, position: IVec3, direction: Direction) -> u8 {
+        self.get_base_block(position).map_or(0, |block| {
+            block.block.strong_power_output(self, position, direction)
+        })
+    }
+
+    /// Highest strong power going into `position` from its neighbors.
+    pub fn get_direct_signal_to(&self, position: IVec3) -> u8 {
+        Direction::ALL
+            .iter()
+            .map(|direction| {
+                self.get_direct_signal(position + direction.as_vec3(), direction.opposite())
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Power the block at `position` sends towards `direction`, including
+    /// strong power conducted through it.
+    pub fn get_signal(&self, position: IVec3, direction: Direction) -> u8 {
+        let signal = self.get_base_block(position).map_or(0, |block| {
+            block.block.power_output(self, position, direction)
+        });
+
+        if self.is_conductor(position) {
+            signal.max(self.get_direct_signal_to(position))
+        } else {
+            signal
+        }
+    }
+
+    /// Highest power going into `position` from its neighbors.
+    pub fn get_best_neighbor_signal(&self, position: IVec3) -> u8 {
+        Direction::ALL
+            .iter()
+            .map(|direction| self.get_signal(position + direction.as_vec3(), direction.opposite()))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Like [`World::get_best_neighbor_signal`], but ignores redstone wires.
+    pub fn get_best_neighbor_signal_without_wires(&mut self, position: IVec3) -> u8 {
+        self.wires_emit_power = false;
+
+        let signal = self.get_best_neighbor_signal(position);
+
+        self.wires_emit_power = true;
+
+        signal
+    }
+
+    /// Schedules [`Block::on_scheduled_tick`](crate::block_states::Block::on_scheduled_tick)
+    /// of the block at `position` after `delay` game ticks.
+    pub fn schedule_tick(&mut self, position: IVec3, delay: u64) -> bool {
+        self.chunks.ticks.schedule(position, delay)
+    }
+
+    /// Runs a single game tick.
+    pub fn tick(&mut self) {
+        self.chunks.clock.tick();
+
+        for position in self.chunks.ticks.advance() {
+            if let Some(block) = self.get_base_block(position) {
+                block.block.on_scheduled_tick(self, position);
+            }
+        }
+
+        self.random_tick();
+    }
+
+    fn random_tick(&mut self) {
+        let speed = self.chunks.ticks.random_tick_speed;
+
+        if speed == 0 {
+            return;
+        }
+
+        let size = SIZE as i32;
+
+        for section in self.chunks.sections() {
+            for _ in 0..speed {
+                let position = section * size
+                    + ivec3(
+                        rand::gen_range(0, size),
+                        rand::gen_range(0, size),
+                        rand::gen_range(0, size),
+                    );
+
+                if let Some(block) = self
+                    .get_base_block(position)
+                    .filter(|block| block.block.has_random_ticks())
+                {
+                    block.block.on_random_tick(self, position);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use super::{RavineSettings, WorldgenRandom, WorldgenSettings};
+use crate::{
+    chunk::{BlockState, ChunkColumn, EMPTY_BLOCK, SIZE},
+    heightmap::HeightmapKind,
+    loaders::BlockStatesLoader,
+};
+use macroquad::math::{dvec3, ivec3, DVec3, IVec2, IVec3};
+use noise::{NoiseFn, SuperSimplex};
+use std::f64::consts::PI;
+
+/// Blocks carvers cut through.
+const CARVABLE_BLOCKS: [&str; 2] = ["minecraft:dirt", "minecraft:grass_block"];
+/// Feature index the ravines of a column are seeded with, far away from the
+/// indices of the placed features.
+const RAVINE_FEATURE: usize = 0x8000;
+
+/// Removes blocks of a single column while keeping its surface intact.
+struct ColumnCarver<'a> {
+    block_states: &'a BlockStatesLoader,
+    column: &'a mut ChunkColumn,
+    origin: IVec2,
+    lava: BlockState,
+    lava_level: i32,
+}
+
+impl ColumnCarver<'_> {
+    /// Position inside of the column, `None` outside of it.
+    fn local(&self, position: IVec3) -> Option<(usize, usize)> {
+        let (x, z) = (position.x - self.origin.x, position.z - self.origin.y);
+
+        Some((
+            usize::try_from(x).ok().filter(|&x| x < SIZE)?,
+            usize::try_from(z).ok().filter(|&z| z < SIZE)?,
+        ))
+    }
+
+    fn get_name(&self, position: IVec3) -> Option<&str> {
+        let (x, z) = self.local(position)?;
+        let state = self.column.get_block(x, position.y, z)?;
+
+        self.block_states.get_name_by_id(state.value.into())
+    }
+
+    fn set_block(&mut self, position: IVec3, state: BlockState) {
+        if let Some((x, z)) = self.local(position) {
+            self.column.set_block(x, position.y, z, state);
+        }
+    }
+
+    fn carve(&mut self, position: IVec3) {
+        let Some(name) = self.get_name(position) else {
+            return;
+        };
+
+        if !CARVABLE_BLOCKS.contains(&name) {
+            return;
+        }
+
+        let grass = name == "minecraft:grass_block";
+        let below = position - ivec3(0, 1, 0);
+
+        if position.y < self.lava_level {
+            self.set_block(position, self.lava);
+        } else {
+            self.set_block(position, EMPTY_BLOCK);
+        }
+
+        // Dirt uncovered by carving away the grass turns into grass itself.
+        if grass && self.get_name(below) == Some("minecraft:dirt") {
+            if let Some(grass) = self.block_states.get_default_state("grass_block") {
+                self.set_block(below, grass);
+            }
+        }
+    }
+}
+
+/// Carves the caves and ravines of the column at `position` out of its terrain.
+///
+/// Only depends on the seed, so carving the same column twice gives the same
+/// result no matter which of its neighbors exist.
+pub fn carve_column(
+    block_states: &BlockStatesLoader,
+    settings: &WorldgenSettings,
+    seed: u32,
+    position: IVec2,
+    column: &mut ChunkColumn,
+) {
+    column.compute_heightmaps(block_states);
+
+    let mut carver = ColumnCarver {
+        block_states,
+        column,
+        origin: position * SIZE as i32,
+        lava: block_states
+            .get_default_state("lava")
+            .unwrap_or(EMPTY_BLOCK),
+        lava_level: settings.lava_level,
+    };
+
+    carve_noise_caves(&mut carver, settings, seed);
+
+    let range = settings.ravines.range;
+
+    for dz in -range..=range {
+        for dx in -range..=range {
+            let start = position + IVec2::new(dx, dz);
+            let mut random = WorldgenRandom::for_feature(seed, start, RAVINE_FEATURE);
+
+            if random.one_in(settings.ravines.chance) {
+                carve_ravine(&mut carver, &settings.ravines, &mut random, start);
+            }
+        }
+    }
+}
+
+fn carve_noise_caves(carver: &mut ColumnCarver, settings: &WorldgenSettings, seed: u32) {
+    let caves = &settings.caves;
+    let cheese = SuperSimplex::new(seed.wrapping_add(1));
+    let spaghetti = [
+        SuperSimplex::new(seed.wrapping_add(2)),
+        SuperSimplex::new(seed.wrapping_add(3)),
+    ];
+
+    let surface = *carver.column.heightmap(HeightmapKind::WorldSurface);
+    let min_y = carver.column.height().min_y;
+
+    for (z, local_z) in (carver.origin.y..).zip(0..SIZE) {
+        for (x, local_x) in (carver.origin.x..).zip(0..SIZE) {
+            for y in min_y..surface.get(local_x, local_z) - caves.surface_depth {
+                let position = dvec3(x as f64, y as f64 * caves.vertical_scale, z as f64);
+
+                let cheese = cheese.get((position * caves.cheese.frequency).to_array());
+                let [a, b] = spaghetti
+                    .map(|noise| noise.get((position * caves.spaghetti.frequency).to_array()));
+
+                if cheese > caves.cheese.threshold
+                    || a * a + b * b < caves.spaghetti.thickness * caves.spaghetti.thickness
+                {
+                    carver.carve(ivec3(x, y, z));
+                }
+            }
+        }
+    }
+}
+
+/// Walks a ravine starting in the column at `start`, carving the parts of it
+/// which fall into the carved column.
+fn carve_ravine(
+    carver: &mut ColumnCarver,
+    settings: &RavineSettings,
+    random: &mut WorldgenRandom,
+    start: IVec2,
+) {
+    let mut position = dvec3(
+        (start.x * SIZE as i32) as f64 + random.next_f64() * SIZE as f64,
+        random.gen_range(settings.min_y..settings.max_y.max(settings.min_y + 1)) as f64,
+        (start.y * SIZE as i32) as f64 + random.next_f64() * SIZE as f64,
+    );
+    let mut yaw = random.next_f64() * PI * 2.0;
+    let mut pitch = (random.next_f64() - 0.5) / 4.0;
+    let mut yaw_change = 0.0;
+    let mut pitch_change = 0.0;
+
+    let thickness = (random.next_f64() * 2.0 + random.next_f64()) / 3.0 * settings.thickness;
+    let length = (((settings.range * 2 - 1) * SIZE as i32) as f64
+        * (0.75 + random.next_f64() * 0.25)) as i32;
+
+    let center = carver.origin.as_dvec2() + SIZE as f64 / 2.0;
+
+    for step in 0..length {
+        let radius = 1.5 + (step as f64 * PI / length as f64).sin() * thickness;
+        let height = radius * settings.vertical_scale;
+
+        position += dvec3(
+            yaw.cos() * pitch.cos(),
+            pitch.sin(),
+            yaw.sin() * pitch.cos(),
+        );
+
+        pitch *= 0.7;
+        pitch += pitch_change * 0.05;
+        yaw += yaw_change * 0.05;
+        pitch_change =
+            pitch_change * 0.8 + (random.next_f64() - random.next_f64()) * random.next_f64() * 2.0;
+        yaw_change =
+            yaw_change * 0.5 + (random.next_f64() - random.next_f64()) * random.next_f64() * 4.0;
+
+        // The walls get rough by leaving out some of the steps.
+        if random.one_in(4) {
+            continue;
+        }
+
+        let reach = SIZE as f64 / 2.0 + radius;
+
+        if (position.x - center.x).abs() > reach || (position.z - center.y).abs() > reach {
+            continue;
+        }
+
+        carve_ellipsoid(carver, position, radius, height);
+    }
+}
+
+fn carve_ellipsoid(carver: &mut ColumnCarver, center: DVec3, radius: f64, height: f64) {
+    let min = (center - dvec3(radius, height, radius)).floor().as_ivec3();
+    let max = (center + dvec3(radius, height, radius)).ceil().as_ivec3();
+
+    // Going down, so grass uncovered by carving the block above gets carved too.
+    for y in (min.y..=max.y).rev() {
+        for z in min.z..=max.z {
+            for x in min.x..=max.x {
+                let offset =
+                    (ivec3(x, y, z).as_dvec3() + 0.5 - center) / dvec3(radius, height, radius);
+
+                if offset.length_squared() < 1.0 {
+                    carver.carve(ivec3(x, y, z));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,340 @@
+use super::{GenerationRegion, WorldgenRandom};
+use crate::{
+    chunk::EMPTY_BLOCK,
+    heightmap::HeightmapKind,
+    plant::is_soil,
+    structure::{PlaceSettings, Rotation, StructureTemplate},
+};
+use macroquad::math::{ivec3, IVec2, IVec3};
+use std::{collections::BTreeMap, sync::Mutex};
+
+/// Structure templates used by features, loaded the first time they're placed.
+static STRUCTURES: Mutex<BTreeMap<&str, StructureTemplate>> = Mutex::new(BTreeMap::new());
+
+/// Vegetation of the overworld, in the order it's placed.
+pub const VEGETATION: &[PlacedFeature] = &[
+    PlacedFeature {
+        feature: Feature::Tree {
+            trunk: "oak_log",
+            leaves: "oak_leaves",
+            min_height: 4,
+            max_height: 6,
+        },
+        placement: &[
+            PlacementModifier::Rarity(3),
+            PlacementModifier::InSquare,
+            PlacementModifier::HeightMap(HeightmapKind::OceanFloor),
+            PlacementModifier::BiomeFilter(&["plains"]),
+        ],
+    },
+    PlacedFeature {
+        feature: Feature::Structure {
+            name: "fallen_oak_log",
+        },
+        placement: &[
+            PlacementModifier::Rarity(8),
+            PlacementModifier::InSquare,
+            PlacementModifier::HeightMap(HeightmapKind::WorldSurface),
+            PlacementModifier::BiomeFilter(&["plains"]),
+        ],
+    },
+    PlacedFeature {
+        feature: Feature::RandomPatch {
+            blocks: &["short_grass"],
+            tries: 32,
+            spread: 7,
+        },
+        placement: &[
+            PlacementModifier::Count(4),
+            PlacementModifier::InSquare,
+            PlacementModifier::HeightMap(HeightmapKind::WorldSurface),
+            PlacementModifier::BiomeFilter(&["plains"]),
+        ],
+    },
+    PlacedFeature {
+        feature: Feature::RandomPatch {
+            blocks: &["dandelion", "poppy"],
+            tries: 12,
+            spread: 6,
+        },
+        placement: &[
+            PlacementModifier::Rarity(2),
+            PlacementModifier::InSquare,
+            PlacementModifier::HeightMap(HeightmapKind::WorldSurface),
+            PlacementModifier::BiomeFilter(&["plains"]),
+        ],
+    },
+];
+
+/// Turns a position into the positions a feature gets placed at.
+#[derive(Debug, Copy, Clone)]
+pub enum PlacementModifier {
+    /// Repeats the placement.
+    Count(u32),
+    /// Keeps the placement once in the given number of times.
+    Rarity(u32),
+    /// Moves to a random position inside of the column.
+    InSquare,
+    /// Moves onto the surface of the terrain given by a heightmap.
+    HeightMap(HeightmapKind),
+    /// Keeps positions inside of one of the biomes.
+    BiomeFilter(&'static [&'static str]),
+}
+
+impl PlacementModifier {
+    fn apply(
+        self,
+        region: &GenerationRegion,
+        random: &mut WorldgenRandom,
+        position: IVec3,
+    ) -> Vec<IVec3> {
+        match self {
+            Self::Count(count) => vec![position; count as usize],
+            Self::Rarity(chance) => {
+                if random.one_in(chance) {
+                    vec![position]
+                } else {
+                    Vec::new()
+                }
+            }
+            Self::InSquare => {
+                let x = random.gen_range(0..16);
+                let z = random.gen_range(0..16);
+
+                vec![position + ivec3(x, 0, z)]
+            }
+            Self::HeightMap(kind) => region
+                .surface_height(kind, position.x, position.z)
+                .map(|y| ivec3(position.x, y, position.z))
+                .into_iter()
+                .collect(),
+            Self::BiomeFilter(biomes) => region
+                .get_biome(position)
+                .filter(|biome| biomes.contains(&biome.name))
+                .map(|_| position)
+                .into_iter()
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum Feature {
+    /// Tree with a straight trunk and a blob of leaves on top of it.
+    Tree {
+        trunk: &'static str,
+        leaves: &'static str,
+        min_height: i32,
+        max_height: i32,
+    },
+    /// Scatters plants on the soil around the position.
+    RandomPatch {
+        blocks: &'static [&'static str],
+        tries: u32,
+        spread: i32,
+    },
+    /// Structure template from `assets/structures`, turned at random and
+    /// resting on soil.
+    Structure { name: &'static str },
+}
+
+impl Feature {
+    /// Places the feature at `origin`, returns whether anything was placed.
+    pub fn place(
+        &self,
+        region: &mut GenerationRegion,
+        random: &mut WorldgenRandom,
+        origin: IVec3,
+    ) -> bool {
+        match *self {
+            Self::Tree {
+                trunk,
+                leaves,
+                min_height,
+                max_height,
+            } => place_tree(
+                region,
+                random,
+                origin,
+                (trunk, leaves),
+                min_height..max_height + 1,
+            ),
+            Self::RandomPatch {
+                blocks,
+                tries,
+                spread,
+            } => place_patch(region, random, origin, blocks, tries, spread),
+            Self::Structure { name } => place_structure(region, random, origin, name),
+        }
+    }
+}
+
+fn place_tree(
+    region: &mut GenerationRegion,
+    random: &mut WorldgenRandom,
+    origin: IVec3,
+    (trunk, leaves): (&str, &str),
+    height: std::ops::Range<i32>,
+) -> bool {
+    let (Some(log), Some(leaves), Some(dirt)) = (
+        region.block_states.find_state(trunk, [("axis", "y")]),
+        region.block_states.get_default_state(leaves),
+        region.block_states.get_default_state("dirt"),
+    ) else {
+        return false;
+    };
+
+    let height = random.gen_range(height);
+    let top = origin.y + height;
+
+    // The trunk stays inside of its own column, so checking it gives the
+    // same result in every region.
+    if !is_soil(region.get_name(origin - ivec3(0, 1, 0)))
+        || !(0..height).all(|y| region.is_replaceable(origin + ivec3(0, y, 0)))
+    {
+        return false;
+    }
+
+    region.set_block(origin - ivec3(0, 1, 0), dirt);
+
+    for y in top - 3..=top {
+        let dy = y - top;
+        let radius = 1 - dy / 2;
+
+        for dz in -radius..=radius {
+            for dx in -radius..=radius {
+                let corner = dx.abs() == radius && dz.abs() == radius;
+
+                // Corners are left out at random, and always at the top.
+                if corner && (random.one_in(2) || dy == 0) {
+                    continue;
+                }
+
+                let position = ivec3(origin.x + dx, y, origin.z + dz);
+
+                if region.is_replaceable(position) {
+                    region.set_block(position, leaves);
+                }
+            }
+        }
+    }
+
+    for y in 0..height {
+        region.set_block(origin + ivec3(0, y, 0), log);
+    }
+
+    true
+}
+
+fn place_patch(
+    region: &mut GenerationRegion,
+    random: &mut WorldgenRandom,
+    origin: IVec3,
+    blocks: &[&str],
+    tries: u32,
+    spread: i32,
+) -> bool {
+    let mut placed = false;
+
+    for _ in 0..tries {
+        let offset = ivec3(
+            random.gen_range(0..spread + 1) - random.gen_range(0..spread + 1),
+            random.gen_range(0..3) - random.gen_range(0..3),
+            random.gen_range(0..spread + 1) - random.gen_range(0..spread + 1),
+        );
+        let block = blocks[random.gen_range(0..blocks.len() as i32) as usize];
+        let position = origin + offset;
+
+        let Some(state) = region.block_states.get_default_state(block) else {
+            continue;
+        };
+
+        if region.get_block(position).map(|block| block.value) == Some(EMPTY_BLOCK.value)
+            && region.get_name(position - ivec3(0, 1, 0)) == Some("minecraft:grass_block")
+        {
+            placed |= region.set_block(position, state);
+        }
+    }
+
+    placed
+}
+
+fn place_structure(
+    region: &mut GenerationRegion,
+    random: &mut WorldgenRandom,
+    origin: IVec3,
+    name: &'static str,
+) -> bool {
+    let settings = PlaceSettings {
+        rotation: Rotation::ALL[random.gen_range(0..Rotation::ALL.len() as i32) as usize],
+        ignore_air: true,
+        ..Default::default()
+    };
+
+    let mut structures = STRUCTURES.lock().unwrap();
+    let template = structures.entry(name).or_insert_with(|| {
+        let path = crate::asset!("structures/{name}.snbt");
+
+        StructureTemplate::load(&path)
+            .unwrap_or_else(|err| panic!("failed to load the structure at {path}: {err}"))
+    });
+
+    // States missing from the registry leave the structure out entirely.
+    let Ok(blocks) = template.resolve(region.block_states, &settings) else {
+        return false;
+    };
+
+    // The bottom layer needs soil below it and nothing may be overwritten
+    // except air and vegetation.
+    let fits = blocks.iter().all(|&(position, _)| {
+        let position = origin + position;
+
+        region.is_replaceable(position)
+            && (position.y != origin.y || is_soil(region.get_name(position - ivec3(0, 1, 0))))
+    });
+
+    if !fits {
+        return false;
+    }
+
+    for (position, state) in blocks {
+        region.set_block(origin + position, state);
+    }
+
+    true
+}
+
+/// A feature together with where it's placed.
+#[derive(Debug, Copy, Clone)]
+pub struct PlacedFeature {
+    pub feature: Feature,
+    pub placement: &'static [PlacementModifier],
+}
+
+impl PlacedFeature {
+    /// Places the feature for the column at `column`, it may grow into the
+    /// neighboring columns of the region.
+    pub fn place(
+        &self,
+        region: &mut GenerationRegion,
+        random: &mut WorldgenRandom,
+        column: IVec2,
+    ) -> usize {
+        let origin = ivec3(column.x * 16, 0, column.y * 16);
+
+        let positions = self
+            .placement
+            .iter()
+            .fold(vec![origin], |positions, modifier| {
+                positions
+                    .into_iter()
+                    .flat_map(|position| modifier.apply(region, random, position))
+                    .collect()
+            });
+
+        positions
+            .into_iter()
+            .filter(|&position| self.feature.place(region, random, position))
+            .count()
+    }
+}
@@ -0,0 +1,100 @@
+//! World generation: noise terrain first, then caves and ravines carved out
+//! of it, then features like trees and plants placed on top of it.
+
+mod carver;
+mod feature;
+mod random;
+mod region;
+mod settings;
+
+pub use self::{
+    carver::carve_column,
+    feature::{Feature, PlacedFeature, PlacementModifier, VEGETATION},
+    random::WorldgenRandom,
+    region::{GenerationRegion, REGION_RADIUS},
+    settings::{
+        CaveSettings, CheeseCaveSettings, RavineSettings, SpaghettiCaveSettings, WorldgenSettings,
+    },
+};
+use crate::{
+    array_16x16x16,
+    chunk::{BiomeId, BlockState, Chunk, ChunkColumn, LightLevel, WorldHeight},
+    loaders::{BiomeLoader, BlockStatesLoader},
+};
+use macroquad::math::IVec2;
+use noise::{NoiseFn, SuperSimplex};
+
+/// Bare terrain of a column, without any features.
+///
+/// The surface is made out of noise between 0 and 16, below that the ground
+/// is solid down to the bottom of the world.
+pub fn generate_terrain(seed: u32, height: WorldHeight, position: IVec2) -> ChunkColumn {
+    let noise_fn = SuperSimplex::new(seed);
+    let offset = position.as_dvec2() * 16.0;
+
+    let mut column = ChunkColumn::new(height, [[BiomeId { value: 1 }; 16]; 16]);
+
+    let surface = Chunk {
+        blocks: array_16x16x16::<BlockState, 16, _>(|x, y, z| {
+            let (x, y, z) = (x as f64 + offset.x, y as f64, z as f64 + offset.y);
+            let noise = noise_fn.get([x / 30.0, y * 15.0, z / 30.0]) * 80.0 + 64.0 + y * 1.7;
+
+            if noise < 100.0 {
+                if y == 15.0 {
+                    BlockState { value: 2 }
+                } else {
+                    BlockState { value: 1 }
+                }
+            } else {
+                BlockState { value: 0 }
+            }
+        }),
+        // There is no light propagation yet, so the sky lights everything.
+        light_levels: [[[LightLevel { value: 0xf0 }; 16]; 16]; 16],
+    };
+    let ground = Chunk {
+        blocks: [[[BlockState { value: 1 }; 16]; 16]; 16],
+        light_levels: [[[LightLevel { value: 0xf0 }; 16]; 16]; 16],
+    };
+
+    for section in height.min_section()..=0 {
+        if let Some(index) = column.section_index(section) {
+            let chunk = if section == 0 { surface } else { ground };
+
+            column.chunks[index] = Some(Box::new(chunk));
+        }
+    }
+
+    column
+}
+
+/// Generates the column at `position` including the parts of features
+/// growing into it from its neighbors.
+pub fn generate_column(
+    block_states: &BlockStatesLoader,
+    biomes: &BiomeLoader,
+    settings: &WorldgenSettings,
+    seed: u32,
+    height: WorldHeight,
+    position: IVec2,
+) -> ChunkColumn {
+    let mut region = GenerationRegion::new(block_states, biomes, seed, position, |column| {
+        let mut terrain = generate_terrain(seed, height, column);
+
+        carve_column(block_states, settings, seed, column, &mut terrain);
+
+        terrain
+    });
+
+    // Every feature is done for the whole region before the next one, so
+    // the order doesn't depend on which column is generated.
+    for (index, feature) in VEGETATION.iter().enumerate() {
+        for column in region.columns() {
+            let mut random = WorldgenRandom::for_feature(seed, column, index);
+
+            feature.place(&mut region, &mut random, column);
+        }
+    }
+
+    region.into_center()
+}
@@ -0,0 +1,67 @@
+use macroquad::math::IVec2;
+use std::ops::Range;
+
+/// Xoroshiro128++ generator for world generation, which has to give the
+/// same results for the same seed no matter when a column is generated.
+#[derive(Debug, Clone)]
+pub struct WorldgenRandom {
+    state: [u64; 2],
+}
+
+impl WorldgenRandom {
+    pub fn new(seed: u64) -> Self {
+        // SplitMix64 spreads similar seeds over the whole state.
+        let mut seed = seed;
+        let mut next = || {
+            seed = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+            let mut z = seed;
+
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+
+            z ^ (z >> 31)
+        };
+
+        Self {
+            state: [next(), next()],
+        }
+    }
+
+    /// Generator for one feature of one column.
+    pub fn for_feature(seed: u32, column: IVec2, feature: usize) -> Self {
+        let column = (column.x as u64).wrapping_mul(0x2545_f491_4f6c_dd1d)
+            ^ (column.y as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+
+        Self::new(u64::from(seed) ^ column ^ (feature as u64) << 48)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let [s0, mut s1] = self.state;
+        let result = s0.wrapping_add(s1).rotate_left(17).wrapping_add(s0);
+
+        s1 ^= s0;
+        self.state = [s0.rotate_left(49) ^ s1 ^ (s1 << 21), s1.rotate_left(28)];
+
+        result
+    }
+
+    /// Uniform number between 0 and 1.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform number in `range`, which must not be empty.
+    pub fn gen_range(&mut self, range: Range<i32>) -> i32 {
+        let length = range.end.abs_diff(range.start);
+
+        range
+            .start
+            .wrapping_add((((self.next_u64() >> 32) * u64::from(length)) >> 32) as i32)
+    }
+
+    /// `true` once in `n` calls on average.
+    pub fn one_in(&mut self, n: u32) -> bool {
+        n <= 1 || self.gen_range(0..n as i32) == 0
+    }
+}
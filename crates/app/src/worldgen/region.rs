@@ -0,0 +1,136 @@
+use crate::{
+    chunk::{section_position, BlockState, ChunkColumn, EMPTY_BLOCK},
+    heightmap::HeightmapKind,
+    loaders::{Biome, BiomeLoader, BlockStatesLoader},
+};
+use macroquad::math::{ivec2, IVec2, IVec3};
+use std::collections::HashMap;
+
+/// How many columns around the generated one features can reach into.
+pub const REGION_RADIUS: i32 = 1;
+/// Blocks placed by features which other features may grow into.
+const VEGETATION_BLOCKS: [&str; 4] = [
+    "minecraft:oak_leaves",
+    "minecraft:short_grass",
+    "minecraft:dandelion",
+    "minecraft:poppy",
+];
+
+/// The column being generated together with its neighbors.
+///
+/// Features are allowed to write anywhere inside of the region, so trees
+/// growing over the border of a column aren't cut off. Neighbors only exist
+/// while the column is generated: each column places the features of all
+/// columns around it, which always turn out the same for the same seed, and
+/// keeps the blocks falling into itself.
+pub struct GenerationRegion<'a> {
+    pub block_states: &'a BlockStatesLoader,
+    pub biomes: &'a BiomeLoader,
+    pub seed: u32,
+    center: IVec2,
+    columns: HashMap<IVec2, ChunkColumn>,
+}
+
+impl<'a> GenerationRegion<'a> {
+    /// Fills the region with bare terrain made by `terrain`, and computes
+    /// its heightmaps.
+    pub fn new(
+        block_states: &'a BlockStatesLoader,
+        biomes: &'a BiomeLoader,
+        seed: u32,
+        center: IVec2,
+        terrain: impl Fn(IVec2) -> ChunkColumn,
+    ) -> Self {
+        let mut columns = HashMap::new();
+
+        for dz in -REGION_RADIUS..=REGION_RADIUS {
+            for dx in -REGION_RADIUS..=REGION_RADIUS {
+                let position = center + ivec2(dx, dz);
+
+                let mut column = terrain(position);
+
+                column.compute_heightmaps(block_states);
+                columns.insert(position, column);
+            }
+        }
+
+        Self {
+            block_states,
+            biomes,
+            seed,
+            center,
+            columns,
+        }
+    }
+
+    /// Positions of the columns, always in the same order.
+    pub fn columns(&self) -> Vec<IVec2> {
+        let mut columns: Vec<_> = self.columns.keys().copied().collect();
+
+        columns.sort_by_key(|column| (column.y, column.x));
+
+        columns
+    }
+
+    pub fn get_block(&self, position: IVec3) -> Option<BlockState> {
+        let (section, [x, _, z]) = section_position(position);
+
+        self.columns
+            .get(&ivec2(section.x, section.z))?
+            .get_block(x, position.y, z)
+    }
+
+    pub fn get_name(&self, position: IVec3) -> Option<&'a str> {
+        let state = self.get_block(position)?;
+
+        self.block_states.get_name_by_id(state.value.into())
+    }
+
+    /// Whether a feature may put a block at `position`, which is the case for
+    /// air and vegetation inside of the region.
+    pub fn is_replaceable(&self, position: IVec3) -> bool {
+        self.get_block(position).is_some_and(|block| {
+            block.value == EMPTY_BLOCK.value
+                || self
+                    .get_name(position)
+                    .is_some_and(|name| VEGETATION_BLOCKS.contains(&name))
+        })
+    }
+
+    /// Sets a block without any updates, returns `false` outside of the region.
+    pub fn set_block(&mut self, position: IVec3, state: BlockState) -> bool {
+        let (section, [x, _, z]) = section_position(position);
+
+        self.columns
+            .get_mut(&ivec2(section.x, section.z))
+            .is_some_and(|column| column.set_block(x, position.y, z, state))
+    }
+
+    pub fn get_biome(&self, position: IVec3) -> Option<&Biome> {
+        let (section, [x, _, z]) = section_position(position);
+        let column = self.columns.get(&ivec2(section.x, section.z))?;
+
+        Some(&self.biomes[column.biomes[z][x]])
+    }
+
+    /// Height of a heightmap of the terrain, from before any features were
+    /// placed. Features changing it would make where features end up depend
+    /// on the features of columns outside of the region.
+    pub fn surface_height(&self, kind: HeightmapKind, x: i32, z: i32) -> Option<i32> {
+        let (section, [x, _, z]) = section_position(IVec3::new(x, 0, z));
+        let column = self.columns.get(&ivec2(section.x, section.z))?;
+
+        Some(column.heightmap(kind).get(x, z))
+    }
+
+    /// The generated column, the neighbors are thrown away. Its heightmaps
+    /// are up to date again, and the sky light is seeded from them.
+    pub fn into_center(mut self) -> ChunkColumn {
+        let mut column = self.columns.remove(&self.center).unwrap();
+
+        column.compute_heightmaps(self.block_states);
+        column.seed_sky_light();
+
+        column
+    }
+}
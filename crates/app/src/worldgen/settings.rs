@@ -0,0 +1,122 @@
+use serde::Deserialize;
+use std::fs;
+
+/// Parameters of world generation, read from `worldgen/settings.json`.
+///
+/// Everything missing from the file keeps its default.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct WorldgenSettings {
+    pub caves: CaveSettings,
+    pub ravines: RavineSettings,
+    /// Carved blocks below this height are filled with lava.
+    pub lava_level: i32,
+}
+
+impl Default for WorldgenSettings {
+    fn default() -> Self {
+        Self {
+            caves: CaveSettings::default(),
+            ravines: RavineSettings::default(),
+            lava_level: -55,
+        }
+    }
+}
+
+impl WorldgenSettings {
+    pub fn load() -> Self {
+        let path = crate::asset!("worldgen/settings.json");
+
+        serde_json::from_slice(
+            &fs::read(&path).unwrap_or_else(|_| panic!("there are no worldgen settings at {path}")),
+        )
+        .expect("can't parse worldgen settings")
+    }
+}
+
+/// Caves made out of 3D noise.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CaveSettings {
+    /// Large open caverns, carved where the noise is above the threshold.
+    pub cheese: CheeseCaveSettings,
+    /// Long winding tunnels, carved where two noises are both close to zero.
+    pub spaghetti: SpaghettiCaveSettings,
+    /// How much the noise gets stretched vertically, flattening the caves.
+    pub vertical_scale: f64,
+    /// Caves stay at least this many blocks below the surface.
+    pub surface_depth: i32,
+}
+
+impl Default for CaveSettings {
+    fn default() -> Self {
+        Self {
+            cheese: CheeseCaveSettings::default(),
+            spaghetti: SpaghettiCaveSettings::default(),
+            vertical_scale: 2.0,
+            surface_depth: 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CheeseCaveSettings {
+    pub frequency: f64,
+    pub threshold: f64,
+}
+
+impl Default for CheeseCaveSettings {
+    fn default() -> Self {
+        Self {
+            frequency: 0.02,
+            threshold: 0.55,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SpaghettiCaveSettings {
+    pub frequency: f64,
+    pub thickness: f64,
+}
+
+impl Default for SpaghettiCaveSettings {
+    fn default() -> Self {
+        Self {
+            frequency: 0.025,
+            thickness: 0.07,
+        }
+    }
+}
+
+/// Deep and narrow cuts through the terrain, which start in a column and may
+/// carve through the columns around it.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RavineSettings {
+    /// A ravine starts in one out of this many columns.
+    pub chance: u32,
+    /// How many columns away from its start a ravine may reach.
+    pub range: i32,
+    pub min_y: i32,
+    pub max_y: i32,
+    /// Largest horizontal radius in the middle of a ravine.
+    pub thickness: f64,
+    /// Height of a ravine compared to its width.
+    pub vertical_scale: f64,
+}
+
+impl Default for RavineSettings {
+    fn default() -> Self {
+        Self {
+            chance: 50,
+            range: 8,
+            min_y: 4,
+            max_y: 16,
+            thickness: 3.0,
+            vertical_scale: 3.0,
+        }
+    }
+}
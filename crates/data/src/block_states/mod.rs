@@ -1,5 +1,5 @@
 use serde::{de::Visitor, Deserialize};
-use std::{collections::HashMap, hash::Hash};
+use std::{collections::HashMap, fmt, hash::Hash};
 
 #[derive(Debug, Deserialize)]
 pub enum BlockStates {
@@ -16,6 +16,16 @@ pub enum PropertyValue {
     String(String),
 }
 
+impl fmt::Display for PropertyValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number(value) => value.fmt(f),
+            Self::Boolean(value) => value.fmt(f),
+            Self::String(value) => value.fmt(f),
+        }
+    }
+}
+
 impl From<i64> for PropertyValue {
     fn from(value: i64) -> Self {
         Self::Number(value)
@@ -158,6 +168,23 @@ pub enum MultipartCondition {
     Condition(HashMap<String, String>),
 }
 
+impl MultipartCondition {
+    /// Checks the condition against block properties, where `a|b` matches either value.
+    pub fn matches(&self, properties: &HashMap<String, Property>) -> bool {
+        match self {
+            Self::Or { elements } => elements.iter().any(|c| c.matches(properties)),
+            Self::And { elements } => elements.iter().all(|c| c.matches(properties)),
+            Self::Condition(condition) => condition.iter().all(|(key, values)| {
+                properties.get(key).is_some_and(|property| {
+                    let value = property.value.to_string();
+
+                    values.split('|').any(|expected| expected == value)
+                })
+            }),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct Multipart {
     pub when: MultipartCondition,
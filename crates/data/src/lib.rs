@@ -1,2 +1,4 @@
 pub mod block_states;
 pub mod model;
+pub mod nbt;
+pub mod texture;
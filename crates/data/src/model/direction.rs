@@ -19,6 +19,35 @@ pub enum Direction {
 }
 
 impl Direction {
+    pub const ALL: [Direction; 6] = [
+        Direction::Top,
+        Direction::Bottom,
+        Direction::Front,
+        Direction::Back,
+        Direction::Right,
+        Direction::Left,
+    ];
+
+    pub const HORIZONTAL: [Direction; 4] = [
+        Direction::Front,
+        Direction::Right,
+        Direction::Back,
+        Direction::Left,
+    ];
+
+    pub fn opposite(&self) -> Self {
+        use Direction::*;
+
+        match self {
+            Top => Bottom,
+            Bottom => Top,
+            Front => Back,
+            Back => Front,
+            Right => Left,
+            Left => Right,
+        }
+    }
+
     pub fn vertices(&self) -> [usize; 4] {
         use Direction::*;
 
@@ -0,0 +1,116 @@
+use indexmap::IndexMap;
+use macroquad::math::{vec3, EulerRot, Mat4, Quat, Vec3};
+use serde::Deserialize;
+
+/// Where a model is shown, the keys of its `display` section.
+#[derive(Debug, Deserialize, Copy, PartialEq, Eq, Hash, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum DisplayContext {
+    ThirdpersonRighthand,
+    ThirdpersonLefthand,
+    FirstpersonRighthand,
+    FirstpersonLefthand,
+    Gui,
+    Head,
+    Ground,
+    Fixed,
+}
+
+impl DisplayContext {
+    /// The right hand matching a left one.
+    pub fn right_hand(self) -> Option<Self> {
+        use DisplayContext::*;
+
+        match self {
+            ThirdpersonLefthand => Some(ThirdpersonRighthand),
+            FirstpersonLefthand => Some(FirstpersonRighthand),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Copy, Clone, PartialEq)]
+#[serde(default)]
+pub struct Transform {
+    /// Degrees around the x, y and z axis, applied in that order.
+    pub rotation: Vec3,
+    /// In pixels, 16 to a block.
+    pub translation: Vec3,
+    pub scale: Vec3,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            rotation: Vec3::ZERO,
+            translation: Vec3::ZERO,
+            scale: Vec3::ONE,
+        }
+    }
+}
+
+impl Transform {
+    /// Transforms a model centered on the origin, in blocks. `mirrored` flips
+    /// it over to the other hand. Translation and scale are clamped like in
+    /// vanilla.
+    pub fn matrix(&self, mirrored: bool) -> Mat4 {
+        let translation = self
+            .translation
+            .clamp(Vec3::splat(-80.0), Vec3::splat(80.0))
+            / 16.0;
+        let scale = self.scale.clamp(Vec3::splat(-4.0), Vec3::splat(4.0));
+        let [x, y, z] = self.rotation.to_array().map(f32::to_radians);
+
+        let (translation, y, z) = if mirrored {
+            (vec3(-translation.x, translation.y, translation.z), -y, -z)
+        } else {
+            (translation, y, z)
+        };
+
+        Mat4::from_scale_rotation_translation(
+            scale,
+            Quat::from_euler(EulerRot::XYZ, x, y, z),
+            translation,
+        )
+    }
+}
+
+/// The `display` section of a model.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(transparent)]
+pub struct Display {
+    transforms: IndexMap<DisplayContext, Transform>,
+}
+
+impl Display {
+    pub fn get(&self, context: DisplayContext) -> Option<&Transform> {
+        self.transforms.get(&context)
+    }
+
+    /// Takes over the transforms of the parent the model doesn't override. A
+    /// right hand of the model itself overrides the left hand as well.
+    pub fn inherit(&mut self, parent: &Display) {
+        for (&context, &transform) in &parent.transforms {
+            let overridden = self.transforms.contains_key(&context)
+                || context
+                    .right_hand()
+                    .is_some_and(|right_hand| self.transforms.contains_key(&right_hand));
+
+            if !overridden {
+                self.transforms.insert(context, transform);
+            }
+        }
+    }
+
+    /// Matrix of `context`. Like in vanilla models in a left hand are
+    /// mirrored, and use the transform of the right one if they have none.
+    pub fn matrix(&self, context: DisplayContext) -> Mat4 {
+        let right_hand = context.right_hand();
+
+        self.get(context)
+            .or_else(|| right_hand.and_then(|right_hand| self.get(right_hand)))
+            .map_or(Mat4::IDENTITY, |transform| {
+                transform.matrix(right_hand.is_some())
+            })
+    }
+}
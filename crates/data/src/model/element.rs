@@ -1,6 +1,6 @@
 use super::{Direction, Rotation};
 use indexmap::IndexMap;
-use macroquad::math::{vec3, vec4, Vec3, Vec4};
+use macroquad::math::{vec2, vec3, vec4, Vec2, Vec3, Vec4};
 use serde::Deserialize;
 
 const ZERO: Vec3 = vec3(0.0, 0.0, 0.0);
@@ -17,6 +17,27 @@ pub struct Face {
     pub tint_index: Option<usize>,
 }
 
+impl Face {
+    /// Turns the texture clockwise by the rotation of the face. Like in
+    /// vanilla every corner of `uvs`, going from `u1, v1` over `u1, v2` and
+    /// `u2, v2` to `u2, v1`, takes the place of the one before it.
+    pub fn rotate_uvs(&self, uvs: Vec4, vertex_uvs: [Vec2; 4]) -> [Vec2; 4] {
+        let Vec4 {
+            x: u1,
+            y: v1,
+            z: u2,
+            w: v2,
+        } = uvs;
+        let corners = [vec2(u1, v1), vec2(u1, v2), vec2(u2, v2), vec2(u2, v1)];
+        let steps = (self.rotation / 90) as usize % 4;
+
+        vertex_uvs.map(|uv| match corners.iter().position(|&corner| corner == uv) {
+            Some(i) => corners[(i + steps) % 4],
+            None => uv,
+        })
+    }
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct Element {
     pub from: Vec3,
@@ -1,11 +1,13 @@
 mod axis;
 mod direction;
+mod display;
 mod element;
 mod rotation;
 
 pub use self::{
     axis::{Axis, AxisDirection},
     direction::Direction,
+    display::{Display, DisplayContext, Transform},
     element::{Element, Face},
     rotation::Rotation,
 };
@@ -21,6 +23,8 @@ pub struct Model {
     pub textures: IndexMap<String, String>,
     #[serde(default)]
     pub elements: Vec<Element>,
+    #[serde(default)]
+    pub display: Display,
 }
 
 impl Model {
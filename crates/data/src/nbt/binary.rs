@@ -0,0 +1,365 @@
+use super::{Compound, Error, Result, Tag, MAX_DEPTH};
+use std::io::{Read, Write};
+
+/// Reads a root tag, which is a compound with a name.
+pub fn read_root(mut reader: impl Read) -> Result<(String, Compound)> {
+    let id = read_u8(&mut reader)?;
+
+    if id != 10 {
+        return Err(Error::UnexpectedTag {
+            expected: "a root compound",
+            found: id,
+        });
+    }
+
+    let name = read_string(&mut reader)?;
+
+    match read_tag(&mut reader, id, 0)? {
+        Tag::Compound(root) => Ok((name, root)),
+        _ => unreachable!(),
+    }
+}
+
+pub fn write_root(mut writer: impl Write, name: &str, root: &Compound) -> Result<()> {
+    writer.write_all(&[10])?;
+    write_string(&mut writer, name)?;
+
+    for (key, tag) in root {
+        write_named(&mut writer, key, tag)?;
+    }
+
+    writer.write_all(&[Tag::END_ID])?;
+
+    Ok(())
+}
+
+macro_rules! read_be {
+    ($name:ident, $ty:ty) => {
+        fn $name(reader: &mut impl Read) -> Result<$ty> {
+            let mut bytes = [0; std::mem::size_of::<$ty>()];
+
+            reader.read_exact(&mut bytes)?;
+
+            Ok(<$ty>::from_be_bytes(bytes))
+        }
+    };
+}
+
+read_be!(read_u8, u8);
+read_be!(read_i8, i8);
+read_be!(read_i16, i16);
+read_be!(read_u16, u16);
+read_be!(read_i32, i32);
+read_be!(read_i64, i64);
+read_be!(read_f32, f32);
+read_be!(read_f64, f64);
+
+fn read_length(reader: &mut impl Read) -> Result<usize> {
+    let length = read_i32(reader)?;
+
+    usize::try_from(length).map_err(|_| Error::NegativeLength(length))
+}
+
+fn read_array<T>(
+    reader: &mut impl Read,
+    read: impl Fn(&mut dyn Read) -> Result<T>,
+) -> Result<Vec<T>> {
+    let length = read_length(reader)?;
+    // Don't trust the length with the allocation, the stream may end early.
+    let mut values = Vec::with_capacity(length.min(4096));
+
+    for _ in 0..length {
+        values.push(read(reader)?);
+    }
+
+    Ok(values)
+}
+
+fn read_string(reader: &mut impl Read) -> Result<String> {
+    let length = read_u16(reader)?;
+    let mut bytes = vec![0; length.into()];
+
+    reader.read_exact(&mut bytes)?;
+
+    decode_mutf8(&bytes)
+}
+
+/// Reads the payload of a tag, `depth` is the number of lists and compounds
+/// it is inside of.
+fn read_tag(reader: &mut impl Read, id: u8, depth: usize) -> Result<Tag> {
+    if depth > MAX_DEPTH {
+        return Err(Error::TooDeep);
+    }
+
+    Ok(match id {
+        1 => Tag::Byte(read_i8(reader)?),
+        2 => Tag::Short(read_i16(reader)?),
+        3 => Tag::Int(read_i32(reader)?),
+        4 => Tag::Long(read_i64(reader)?),
+        5 => Tag::Float(read_f32(reader)?),
+        6 => Tag::Double(read_f64(reader)?),
+        7 => Tag::ByteArray(read_array(reader, |mut reader| read_i8(&mut reader))?),
+        8 => Tag::String(read_string(reader)?),
+        9 => {
+            let id = read_u8(reader)?;
+            let length = read_i32(reader)?;
+
+            // Empty lists may have any length with the end tag type.
+            if id == Tag::END_ID && length <= 0 {
+                return Ok(Tag::List(Vec::new()));
+            }
+
+            let length = usize::try_from(length).map_err(|_| Error::NegativeLength(length))?;
+            let mut tags = Vec::with_capacity(length.min(4096));
+
+            for _ in 0..length {
+                tags.push(read_tag(reader, id, depth + 1)?);
+            }
+
+            Tag::List(tags)
+        }
+        10 => {
+            let mut compound = Compound::new();
+
+            loop {
+                let id = read_u8(reader)?;
+
+                if id == Tag::END_ID {
+                    break;
+                }
+
+                let name = read_string(reader)?;
+
+                compound.insert(name, read_tag(reader, id, depth + 1)?);
+            }
+
+            Tag::Compound(compound)
+        }
+        11 => Tag::IntArray(read_array(reader, |mut reader| read_i32(&mut reader))?),
+        12 => Tag::LongArray(read_array(reader, |mut reader| read_i64(&mut reader))?),
+        _ => return Err(Error::InvalidTagId(id)),
+    })
+}
+
+fn write_string(writer: &mut impl Write, value: &str) -> Result<()> {
+    let bytes = encode_mutf8(value);
+    let length = u16::try_from(bytes.len())
+        .map_err(|_| Error::Message(format!("string of {} bytes is too long", bytes.len())))?;
+
+    writer.write_all(&length.to_be_bytes())?;
+    writer.write_all(&bytes)?;
+
+    Ok(())
+}
+
+fn write_length(writer: &mut impl Write, length: usize) -> Result<()> {
+    let length = i32::try_from(length)
+        .map_err(|_| Error::Message(format!("{length} elements don't fit into an array")))?;
+
+    writer.write_all(&length.to_be_bytes())?;
+
+    Ok(())
+}
+
+fn write_named(writer: &mut impl Write, name: &str, tag: &Tag) -> Result<()> {
+    writer.write_all(&[tag.id()])?;
+    write_string(writer, name)?;
+    write_tag(writer, tag)
+}
+
+fn write_tag(writer: &mut impl Write, tag: &Tag) -> Result<()> {
+    match tag {
+        Tag::Byte(value) => writer.write_all(&value.to_be_bytes())?,
+        Tag::Short(value) => writer.write_all(&value.to_be_bytes())?,
+        Tag::Int(value) => writer.write_all(&value.to_be_bytes())?,
+        Tag::Long(value) => writer.write_all(&value.to_be_bytes())?,
+        Tag::Float(value) => writer.write_all(&value.to_be_bytes())?,
+        Tag::Double(value) => writer.write_all(&value.to_be_bytes())?,
+        Tag::ByteArray(values) => {
+            write_length(writer, values.len())?;
+            writer.write_all(&values.iter().map(|&value| value as u8).collect::<Vec<_>>())?;
+        }
+        Tag::String(value) => write_string(writer, value)?,
+        Tag::List(tags) => {
+            let id = tags.first().map_or(Tag::END_ID, Tag::id);
+
+            if tags.iter().any(|tag| tag.id() != id) {
+                return Err(Error::MixedList);
+            }
+
+            writer.write_all(&[id])?;
+            write_length(writer, tags.len())?;
+
+            for tag in tags {
+                write_tag(writer, tag)?;
+            }
+        }
+        Tag::Compound(compound) => {
+            for (name, tag) in compound {
+                write_named(writer, name, tag)?;
+            }
+
+            writer.write_all(&[Tag::END_ID])?;
+        }
+        Tag::IntArray(values) => {
+            write_length(writer, values.len())?;
+
+            for value in values {
+                writer.write_all(&value.to_be_bytes())?;
+            }
+        }
+        Tag::LongArray(values) => {
+            write_length(writer, values.len())?;
+
+            for value in values {
+                writer.write_all(&value.to_be_bytes())?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Encodes a string as Java's modified UTF-8, which writes the null
+/// character with two bytes and characters outside of the basic plane as
+/// two surrogates of three bytes each.
+fn encode_mutf8(value: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(value.len());
+
+    for unit in value.encode_utf16() {
+        match unit {
+            0x01..=0x7f => bytes.push(unit as u8),
+            0x00 | 0x80..=0x7ff => {
+                bytes.push(0xc0 | (unit >> 6) as u8);
+                bytes.push(0x80 | (unit & 0x3f) as u8);
+            }
+            _ => {
+                bytes.push(0xe0 | (unit >> 12) as u8);
+                bytes.push(0x80 | ((unit >> 6) & 0x3f) as u8);
+                bytes.push(0x80 | (unit & 0x3f) as u8);
+            }
+        }
+    }
+
+    bytes
+}
+
+fn decode_mutf8(bytes: &[u8]) -> Result<String> {
+    // Plain ASCII is the same in both encodings.
+    if bytes.iter().all(|&byte| (0x01..0x80).contains(&byte)) {
+        return String::from_utf8(bytes.to_vec()).map_err(|_| Error::InvalidString);
+    }
+
+    let mut units = Vec::with_capacity(bytes.len());
+    let mut bytes = bytes.iter().copied();
+
+    let continuation = |bytes: &mut dyn Iterator<Item = u8>| match bytes.next() {
+        Some(byte) if byte & 0xc0 == 0x80 => Ok(u16::from(byte & 0x3f)),
+        _ => Err(Error::InvalidString),
+    };
+
+    while let Some(byte) = bytes.next() {
+        let unit = match byte {
+            0x01..=0x7f => u16::from(byte),
+            0xc0..=0xdf => (u16::from(byte & 0x1f) << 6) | continuation(&mut bytes)?,
+            0xe0..=0xef => {
+                (u16::from(byte & 0x0f) << 12)
+                    | (continuation(&mut bytes)? << 6)
+                    | continuation(&mut bytes)?
+            }
+            _ => return Err(Error::InvalidString),
+        };
+
+        units.push(unit);
+    }
+
+    String::from_utf16(&units).map_err(|_| Error::InvalidString)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(root: &Compound) -> Compound {
+        let mut data = Vec::new();
+
+        write_root(&mut data, "root", root).unwrap();
+
+        let (name, read) = read_root(&data[..]).unwrap();
+
+        assert_eq!(name, "root");
+
+        read
+    }
+
+    #[test]
+    fn every_tag_round_trips() {
+        let nested = Compound::from([("name".to_owned(), Tag::String("stone".into()))]);
+        let root = Compound::from([
+            ("byte".to_owned(), Tag::Byte(-1)),
+            ("short".to_owned(), Tag::Short(-300)),
+            ("int".to_owned(), Tag::Int(70_000)),
+            ("long".to_owned(), Tag::Long(i64::MIN)),
+            ("float".to_owned(), Tag::Float(0.5)),
+            ("double".to_owned(), Tag::Double(-1.25)),
+            ("byte_array".to_owned(), Tag::ByteArray(vec![-128, 0, 127])),
+            ("string".to_owned(), Tag::String("grass".into())),
+            ("empty_list".to_owned(), Tag::List(Vec::new())),
+            ("list".to_owned(), Tag::List(vec![Tag::Int(1), Tag::Int(2)])),
+            ("compound".to_owned(), Tag::Compound(nested)),
+            (
+                "int_array".to_owned(),
+                Tag::IntArray(vec![i32::MIN, 0, i32::MAX]),
+            ),
+            ("long_array".to_owned(), Tag::LongArray(vec![1, -1])),
+        ]);
+
+        assert_eq!(round_trip(&root), root);
+    }
+
+    #[test]
+    fn strings_are_modified_utf8() {
+        let string = "null \0, é, \u{2603} and \u{1f600}";
+        let root = Compound::from([("string".to_owned(), Tag::String(string.into()))]);
+        let mut data = Vec::new();
+
+        write_root(&mut data, "", &root).unwrap();
+
+        // The null character takes two bytes and the emoji two surrogates of
+        // three bytes each, so there are no zero or four byte sequences.
+        assert!(data.windows(3).any(|bytes| bytes == [b' ', 0xc0, 0x80]));
+        assert!(data
+            .windows(6)
+            .any(|bytes| bytes == [0xed, 0xa0, 0xbd, 0xed, 0xb8, 0x80]));
+        assert_eq!(read_root(&data[..]).unwrap().1, root);
+    }
+
+    #[test]
+    fn empty_lists_of_any_length_are_read() {
+        // An empty list of end tags with a negative length, as some writers do.
+        let data = [
+            10, 0, 0, 9, 0, 4, b'l', b'i', b's', b't', 0, 0xff, 0xff, 0xff, 0xff, 0,
+        ];
+        let (_, root) = read_root(&data[..]).unwrap();
+
+        assert_eq!(root["list"], Tag::List(Vec::new()));
+    }
+
+    #[test]
+    fn deeply_nested_tags_are_rejected() {
+        let nested = |depth| {
+            let list = (1..depth).fold(Tag::List(Vec::new()), |tag, _| Tag::List(vec![tag]));
+
+            Compound::from([("list".to_owned(), list)])
+        };
+        let read = |root: &Compound| {
+            let mut data = Vec::new();
+
+            write_root(&mut data, "", root).unwrap();
+            read_root(&data[..])
+        };
+
+        assert!(read(&nested(MAX_DEPTH)).is_ok());
+        assert!(matches!(read(&nested(MAX_DEPTH + 1)), Err(Error::TooDeep)));
+    }
+}
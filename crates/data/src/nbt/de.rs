@@ -0,0 +1,318 @@
+use super::{Compound, Error, Result, Tag};
+use serde::{
+    de::{
+        self, value::StringDeserializer, DeserializeOwned, DeserializeSeed, IntoDeserializer,
+        MapAccess, SeqAccess, Visitor,
+    },
+    forward_to_deserialize_any, Deserialize,
+};
+use std::{fmt, vec};
+
+/// Converts a tag into a value.
+pub fn from_tag<T: DeserializeOwned>(tag: Tag) -> Result<T> {
+    T::deserialize(tag)
+}
+
+impl<'de> Deserialize<'de> for Tag {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(TagVisitor)
+    }
+}
+
+struct TagVisitor;
+
+impl<'de> Visitor<'de> for TagVisitor {
+    type Value = Tag;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a tag")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Tag, E> {
+        Ok(v.into())
+    }
+
+    fn visit_i8<E>(self, v: i8) -> Result<Tag, E> {
+        Ok(Tag::Byte(v))
+    }
+
+    fn visit_i16<E>(self, v: i16) -> Result<Tag, E> {
+        Ok(Tag::Short(v))
+    }
+
+    fn visit_i32<E>(self, v: i32) -> Result<Tag, E> {
+        Ok(Tag::Int(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Tag, E> {
+        Ok(Tag::Long(v))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Tag, E> {
+        Ok(Tag::Long(v as i64))
+    }
+
+    fn visit_f32<E>(self, v: f32) -> Result<Tag, E> {
+        Ok(Tag::Float(v))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Tag, E> {
+        Ok(Tag::Double(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Tag, E> {
+        Ok(Tag::String(v.into()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Tag, E> {
+        Ok(Tag::String(v))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Tag, A::Error> {
+        let mut tags = Vec::new();
+
+        while let Some(tag) = seq.next_element()? {
+            tags.push(tag);
+        }
+
+        Ok(Tag::List(tags))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Tag, A::Error> {
+        let mut compound = Compound::new();
+
+        while let Some((key, tag)) = map.next_entry()? {
+            compound.insert(key, tag);
+        }
+
+        Ok(Tag::Compound(compound))
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Tag {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+fn unexpected(expected: &'static str, tag: &Tag) -> Error {
+    Error::UnexpectedTag {
+        expected,
+        found: tag.id(),
+    }
+}
+
+/// Unsigned numbers are read back from the signed tag of the same size.
+macro_rules! deserialize_unsigned {
+    ($($method:ident => $visit:ident, $ty:ty;)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+                match self {
+                    Tag::Byte(v) => visitor.$visit(v as u8 as $ty),
+                    Tag::Short(v) => visitor.$visit(v as u16 as $ty),
+                    Tag::Int(v) => visitor.$visit(v as u32 as $ty),
+                    Tag::Long(v) => visitor.$visit(v as u64 as $ty),
+                    tag => tag.deserialize_any(visitor),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> serde::Deserializer<'de> for Tag {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Tag::Byte(v) => visitor.visit_i8(v),
+            Tag::Short(v) => visitor.visit_i16(v),
+            Tag::Int(v) => visitor.visit_i32(v),
+            Tag::Long(v) => visitor.visit_i64(v),
+            Tag::Float(v) => visitor.visit_f32(v),
+            Tag::Double(v) => visitor.visit_f64(v),
+            Tag::ByteArray(values) => visitor.visit_seq(SeqDeserializer::new(values)),
+            Tag::String(v) => visitor.visit_string(v),
+            Tag::List(tags) => visitor.visit_seq(SeqDeserializer::new(tags)),
+            Tag::Compound(compound) => visitor.visit_map(CompoundDeserializer::new(compound)),
+            Tag::IntArray(values) => visitor.visit_seq(SeqDeserializer::new(values)),
+            Tag::LongArray(values) => visitor.visit_seq(SeqDeserializer::new(values)),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Tag::Byte(v) => visitor.visit_bool(v != 0),
+            tag => tag.deserialize_any(visitor),
+        }
+    }
+
+    deserialize_unsigned! {
+        deserialize_u8 => visit_u8, u8;
+        deserialize_u16 => visit_u16, u16;
+        deserialize_u32 => visit_u32, u32;
+        deserialize_u64 => visit_u64, u64;
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        // Missing fields are `None`, anything present is `Some`.
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self {
+            Tag::String(variant) => {
+                let variant: StringDeserializer<Error> = variant.into_deserializer();
+
+                visitor.visit_enum(variant)
+            }
+            Tag::Compound(compound) if compound.len() == 1 => {
+                let (variant, value) = compound.into_iter().next().unwrap();
+
+                visitor.visit_enum(EnumDeserializer { variant, value })
+            }
+            tag => Err(unexpected("a string or a compound with one entry", &tag)),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u128 f32 f64 char str string bytes byte_buf seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct SeqDeserializer<T> {
+    values: vec::IntoIter<T>,
+}
+
+impl<T> SeqDeserializer<T> {
+    fn new(values: Vec<T>) -> Self {
+        Self {
+            values: values.into_iter(),
+        }
+    }
+}
+
+impl<'de, T: Into<Tag>> SeqAccess<'de> for SeqDeserializer<T> {
+    type Error = Error;
+
+    fn next_element_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> Result<Option<S::Value>> {
+        self.values
+            .next()
+            .map(|value| seed.deserialize(value.into()))
+            .transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.values.len())
+    }
+}
+
+struct CompoundDeserializer {
+    entries: indexmap::map::IntoIter<String, Tag>,
+    value: Option<Tag>,
+}
+
+impl CompoundDeserializer {
+    fn new(compound: Compound) -> Self {
+        Self {
+            entries: compound.into_iter(),
+            value: None,
+        }
+    }
+}
+
+impl<'de> MapAccess<'de> for CompoundDeserializer {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        let Some((key, value)) = self.entries.next() else {
+            return Ok(None);
+        };
+
+        self.value = Some(value);
+
+        let key: StringDeserializer<Error> = key.into_deserializer();
+
+        seed.deserialize(key).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let value = self
+            .value
+            .take()
+            .ok_or_else(|| Error::Message("value requested before its key".into()))?;
+
+        seed.deserialize(value)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+struct EnumDeserializer {
+    variant: String,
+    value: Tag,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumDeserializer {
+    type Error = Error;
+    type Variant = Tag;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Tag)> {
+        let variant: StringDeserializer<Error> = self.variant.into_deserializer();
+
+        Ok((seed.deserialize(variant)?, self.value))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Tag {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        serde::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        serde::Deserializer::deserialize_map(self, visitor)
+    }
+}
@@ -0,0 +1,72 @@
+use std::{fmt, io};
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// A tag id outside of the known ones.
+    InvalidTagId(u8),
+    /// A string which isn't valid modified UTF-8.
+    InvalidString,
+    NegativeLength(i32),
+    /// The elements of a list have to share their type.
+    MixedList,
+    /// Lists and compounds nested deeper than vanilla allows.
+    TooDeep,
+    UnexpectedTag {
+        expected: &'static str,
+        found: u8,
+    },
+    /// SNBT which couldn't be parsed, `position` is a byte offset into it.
+    Syntax {
+        position: usize,
+        message: String,
+    },
+    Message(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => err.fmt(f),
+            Self::InvalidTagId(id) => write!(f, "invalid tag id {id}"),
+            Self::InvalidString => f.write_str("invalid modified UTF-8 string"),
+            Self::NegativeLength(length) => write!(f, "negative length {length}"),
+            Self::MixedList => f.write_str("list elements have different types"),
+            Self::TooDeep => f.write_str("tags are nested too deeply"),
+            Self::UnexpectedTag { expected, found } => {
+                write!(f, "expected {expected}, found tag {found}")
+            }
+            Self::Syntax { position, message } => write!(f, "{message} at {position}"),
+            Self::Message(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl serde::ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self::Message(msg.to_string())
+    }
+}
+
+impl serde::de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self::Message(msg.to_string())
+    }
+}
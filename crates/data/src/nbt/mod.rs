@@ -0,0 +1,333 @@
+//! Named Binary Tag, the format Minecraft stores worlds, players and
+//! structures in.
+//!
+//! Tags are read and written in the big-endian binary form, optionally gzip
+//! or zlib compressed, and in the SNBT text form used by commands. Anything
+//! implementing serde's traits can be converted from and to tags.
+
+mod binary;
+mod de;
+mod error;
+mod ser;
+mod snbt;
+
+pub use self::{
+    binary::{read_root, write_root},
+    de::from_tag,
+    error::{Error, Result},
+    ser::{to_tag, ByteArray, IntArray, LongArray},
+};
+use flate2::{
+    read::{GzDecoder, ZlibDecoder},
+    write::{GzEncoder, ZlibEncoder},
+};
+use indexmap::IndexMap;
+use serde::{de::DeserializeOwned, Serialize};
+use std::io::{BufRead, BufReader, Read, Write};
+
+/// Lists and compounds nested deeper than this are rejected, like vanilla's
+/// `NbtAccounter` does, so malicious data can't overflow the stack.
+const MAX_DEPTH: usize = 512;
+
+pub type Compound = IndexMap<String, Tag>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Tag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    List(Vec<Tag>),
+    Compound(Compound),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl Tag {
+    pub const END_ID: u8 = 0;
+
+    pub fn id(&self) -> u8 {
+        match self {
+            Self::Byte(_) => 1,
+            Self::Short(_) => 2,
+            Self::Int(_) => 3,
+            Self::Long(_) => 4,
+            Self::Float(_) => 5,
+            Self::Double(_) => 6,
+            Self::ByteArray(_) => 7,
+            Self::String(_) => 8,
+            Self::List(_) => 9,
+            Self::Compound(_) => 10,
+            Self::IntArray(_) => 11,
+            Self::LongArray(_) => 12,
+        }
+    }
+
+    /// Numeric value of number tags, converted like Java's casts would.
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Self::Byte(value) => Some(value.into()),
+            Self::Short(value) => Some(value.into()),
+            Self::Int(value) => Some(value.into()),
+            Self::Long(value) => Some(value),
+            Self::Float(value) => Some(value as i64),
+            Self::Double(value) => Some(value as i64),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Self::Float(value) => Some(value.into()),
+            Self::Double(value) => Some(value),
+            _ => self.as_i64().map(|value| value as f64),
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Tag]> {
+        match self {
+            Self::List(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_compound(&self) -> Option<&Compound> {
+        match self {
+            Self::Compound(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_long_array(&self) -> Option<&[i64]> {
+        match self {
+            Self::LongArray(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_byte_array(&self) -> Option<&[i8]> {
+        match self {
+            Self::ByteArray(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+macro_rules! impl_from {
+    ($($ty:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl From<$ty> for Tag {
+                fn from(value: $ty) -> Self {
+                    Self::$variant(value.into())
+                }
+            }
+        )*
+    };
+}
+
+impl_from! {
+    i8 => Byte,
+    i16 => Short,
+    i32 => Int,
+    i64 => Long,
+    f32 => Float,
+    f64 => Double,
+    String => String,
+    &str => String,
+    Vec<Tag> => List,
+    Compound => Compound,
+}
+
+impl From<bool> for Tag {
+    fn from(value: bool) -> Self {
+        Self::Byte(value.into())
+    }
+}
+
+/// Compression of a whole NBT file.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    /// Used by `level.dat`, player data and structure files.
+    #[default]
+    Gzip,
+    /// Used by chunks inside of region files.
+    Zlib,
+}
+
+impl Compression {
+    /// Guesses the compression from the first bytes of a stream.
+    pub fn detect(header: &[u8]) -> Self {
+        match header {
+            [0x1f, 0x8b, ..] => Self::Gzip,
+            [0x78, ..] => Self::Zlib,
+            _ => Self::None,
+        }
+    }
+}
+
+/// Reads a root compound, detecting the compression of the stream.
+pub fn read_compressed(reader: impl Read) -> Result<(String, Compound)> {
+    let mut reader = BufReader::new(reader);
+    let compression = Compression::detect(reader.fill_buf()?);
+
+    match compression {
+        Compression::None => read_root(&mut reader),
+        Compression::Gzip => read_root(&mut GzDecoder::new(reader)),
+        Compression::Zlib => read_root(&mut ZlibDecoder::new(reader)),
+    }
+}
+
+pub fn write_compressed(
+    writer: impl Write,
+    name: &str,
+    root: &Compound,
+    compression: Compression,
+) -> Result<()> {
+    let level = flate2::Compression::default();
+
+    match compression {
+        Compression::None => write_root(writer, name, root),
+        Compression::Gzip => {
+            let mut encoder = GzEncoder::new(writer, level);
+
+            write_root(&mut encoder, name, root)?;
+            encoder.finish()?;
+
+            Ok(())
+        }
+        Compression::Zlib => {
+            let mut encoder = ZlibEncoder::new(writer, level);
+
+            write_root(&mut encoder, name, root)?;
+            encoder.finish()?;
+
+            Ok(())
+        }
+    }
+}
+
+/// Reads a possibly compressed NBT file into `T`, ignoring the root name.
+pub fn from_reader<T: DeserializeOwned>(reader: impl Read) -> Result<T> {
+    let (_, root) = read_compressed(reader)?;
+
+    from_tag(Tag::Compound(root))
+}
+
+/// Writes `value`, which has to serialize into a compound, as an NBT file.
+pub fn to_writer<T: Serialize + ?Sized>(
+    writer: impl Write,
+    name: &str,
+    value: &T,
+    compression: Compression,
+) -> Result<()> {
+    match to_tag(value)? {
+        Tag::Compound(root) => write_compressed(writer, name, &root, compression),
+        tag => Err(Error::UnexpectedTag {
+            expected: "a compound",
+            found: tag.id(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct Level {
+        name: String,
+        seed: i64,
+        hardcore: bool,
+        spawn: [i32; 3],
+        heights: LongArray,
+        tags: Vec<String>,
+        difficulty: Difficulty,
+        weather: Option<Weather>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        border: Option<f64>,
+    }
+
+    #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+    enum Difficulty {
+        #[default]
+        Easy,
+        Hard,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Weather {
+        raining: bool,
+        time: u32,
+    }
+
+    fn level() -> Level {
+        Level {
+            name: "maqo".into(),
+            seed: -42,
+            hardcore: true,
+            spawn: [1, 64, -3],
+            heights: LongArray(vec![1, 2, 3]),
+            tags: vec!["a".into(), "b".into()],
+            difficulty: Difficulty::Hard,
+            weather: Some(Weather {
+                raining: false,
+                time: u32::MAX,
+            }),
+            border: None,
+        }
+    }
+
+    #[test]
+    fn serde_round_trips() {
+        let tag = to_tag(&level()).unwrap();
+        let compound = tag.as_compound().unwrap();
+
+        assert_eq!(compound["Hardcore"], Tag::Byte(1));
+        assert_eq!(compound["Heights"], Tag::LongArray(vec![1, 2, 3]));
+        assert_eq!(compound["Difficulty"], Tag::String("Hard".into()));
+        assert!(!compound.contains_key("Border"));
+
+        assert_eq!(from_tag::<Level>(tag).unwrap(), level());
+    }
+
+    #[test]
+    fn serde_round_trips_through_snbt() {
+        let snbt = to_tag(&level()).unwrap().to_string();
+
+        assert_eq!(from_tag::<Level>(snbt.parse().unwrap()).unwrap(), level());
+    }
+
+    #[test]
+    fn compressed_files_round_trip() {
+        for compression in [Compression::None, Compression::Gzip, Compression::Zlib] {
+            let mut data = Vec::new();
+
+            to_writer(&mut data, "level", &level(), compression).unwrap();
+
+            assert_eq!(Compression::detect(&data), compression);
+            assert_eq!(read_compressed(&data[..]).unwrap().0, "level");
+            assert_eq!(from_reader::<Level>(&data[..]).unwrap(), level());
+        }
+    }
+
+    #[test]
+    fn only_compounds_are_written_as_files() {
+        let result = to_writer(Vec::new(), "", &[1, 2, 3], Compression::None);
+
+        assert!(matches!(result, Err(Error::UnexpectedTag { .. })));
+    }
+}
@@ -0,0 +1,588 @@
+use super::{Compound, Error, Result, Tag};
+use serde::{
+    ser::{self, Impossible},
+    Serialize,
+};
+
+/// Names of newtype structs which turn a sequence of numbers into an array tag.
+pub(super) const BYTE_ARRAY: &str = "__nbt_byte_array";
+pub(super) const INT_ARRAY: &str = "__nbt_int_array";
+pub(super) const LONG_ARRAY: &str = "__nbt_long_array";
+
+/// Converts a value into a tag.
+///
+/// `None` fields are left out of compounds, as NBT has no null value. Use
+/// [`ByteArray`], [`IntArray`] and [`LongArray`] for array tags, other
+/// sequences become lists.
+pub fn to_tag<T: Serialize + ?Sized>(value: &T) -> Result<Tag> {
+    value
+        .serialize(Serializer)?
+        .ok_or_else(|| Error::Message("none can't be serialized on its own".into()))
+}
+
+macro_rules! array_wrapper {
+    ($(#[$attr:meta])* $name:ident, $ty:ty, $marker:ident) => {
+        $(#[$attr])*
+        #[derive(Debug, Default, Clone, PartialEq, Eq)]
+        pub struct $name(pub Vec<$ty>);
+
+        impl Serialize for $name {
+            fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_newtype_struct($marker, &self.0)
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                Vec::deserialize(deserializer).map(Self)
+            }
+        }
+
+        impl From<Vec<$ty>> for $name {
+            fn from(value: Vec<$ty>) -> Self {
+                Self(value)
+            }
+        }
+    };
+}
+
+array_wrapper!(
+    /// Serialized as a byte array tag instead of a list.
+    ByteArray,
+    i8,
+    BYTE_ARRAY
+);
+array_wrapper!(
+    /// Serialized as an int array tag instead of a list.
+    IntArray,
+    i32,
+    INT_ARRAY
+);
+array_wrapper!(
+    /// Serialized as a long array tag instead of a list.
+    LongArray,
+    i64,
+    LONG_ARRAY
+);
+
+impl Serialize for Tag {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Byte(value) => serializer.serialize_i8(*value),
+            Self::Short(value) => serializer.serialize_i16(*value),
+            Self::Int(value) => serializer.serialize_i32(*value),
+            Self::Long(value) => serializer.serialize_i64(*value),
+            Self::Float(value) => serializer.serialize_f32(*value),
+            Self::Double(value) => serializer.serialize_f64(*value),
+            Self::ByteArray(values) => serializer.serialize_newtype_struct(BYTE_ARRAY, values),
+            Self::String(value) => serializer.serialize_str(value),
+            Self::List(tags) => tags.serialize(serializer),
+            Self::Compound(compound) => compound.serialize(serializer),
+            Self::IntArray(values) => serializer.serialize_newtype_struct(INT_ARRAY, values),
+            Self::LongArray(values) => serializer.serialize_newtype_struct(LONG_ARRAY, values),
+        }
+    }
+}
+
+/// Serializes into a tag, or `None` for values which are left out.
+struct Serializer;
+
+fn list(tags: Vec<Tag>) -> Result<Tag> {
+    match tags.first() {
+        Some(first) if tags.iter().any(|tag| tag.id() != first.id()) => Err(Error::MixedList),
+        _ => Ok(Tag::List(tags)),
+    }
+}
+
+fn single(key: &'static str, tag: Tag) -> Tag {
+    Tag::Compound(Compound::from_iter([(key.to_string(), tag)]))
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    type SerializeSeq = SerializeList;
+    type SerializeTuple = SerializeList;
+    type SerializeTupleStruct = SerializeList;
+    type SerializeTupleVariant = SerializeVariant<SerializeList>;
+    type SerializeMap = SerializeCompound;
+    type SerializeStruct = SerializeCompound;
+    type SerializeStructVariant = SerializeVariant<SerializeCompound>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok> {
+        Ok(Some(v.into()))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok> {
+        Ok(Some(Tag::Byte(v)))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok> {
+        Ok(Some(Tag::Short(v)))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok> {
+        Ok(Some(Tag::Int(v)))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok> {
+        Ok(Some(Tag::Long(v)))
+    }
+
+    // NBT has no unsigned numbers, they keep their bits in the signed tag of the same size.
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok> {
+        Ok(Some(Tag::Byte(v as i8)))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok> {
+        Ok(Some(Tag::Short(v as i16)))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok> {
+        Ok(Some(Tag::Int(v as i32)))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok> {
+        Ok(Some(Tag::Long(v as i64)))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok> {
+        Ok(Some(Tag::Float(v)))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok> {
+        Ok(Some(Tag::Double(v)))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok> {
+        Ok(Some(Tag::String(v.into())))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok> {
+        Ok(Some(Tag::String(v.into())))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok> {
+        Ok(Some(Tag::ByteArray(v.iter().map(|&b| b as i8).collect())))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Self::Ok> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok> {
+        Ok(Some(Tag::Compound(Compound::new())))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok> {
+        let tag = value.serialize(self)?;
+
+        let Some(Tag::List(tags)) = tag else {
+            return Ok(tag);
+        };
+
+        let numbers = || tags.iter().map(|tag| tag.as_i64().unwrap_or_default());
+
+        Ok(Some(match name {
+            BYTE_ARRAY => Tag::ByteArray(numbers().map(|value| value as i8).collect()),
+            INT_ARRAY => Tag::IntArray(numbers().map(|value| value as i32).collect()),
+            LONG_ARRAY => Tag::LongArray(numbers().collect()),
+            _ => Tag::List(tags),
+        }))
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok> {
+        Ok(value.serialize(self)?.map(|tag| single(variant, tag)))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Ok(SerializeList(Vec::with_capacity(len.unwrap_or_default())))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Ok(SerializeVariant {
+            variant,
+            inner: SerializeList(Vec::with_capacity(len)),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Ok(SerializeCompound::default())
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Ok(SerializeCompound::default())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Ok(SerializeVariant {
+            variant,
+            inner: SerializeCompound::default(),
+        })
+    }
+}
+
+struct SerializeList(Vec<Tag>);
+
+impl SerializeList {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let tag = value
+            .serialize(Serializer)?
+            .ok_or_else(|| Error::Message("lists can't contain none".into()))?;
+
+        self.0.push(tag);
+
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for SerializeList {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        list(self.0).map(Some)
+    }
+}
+
+impl ser::SerializeTuple for SerializeList {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        list(self.0).map(Some)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeList {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        list(self.0).map(Some)
+    }
+}
+
+#[derive(Default)]
+struct SerializeCompound {
+    compound: Compound,
+    key: Option<String>,
+}
+
+impl SerializeCompound {
+    fn insert<T: Serialize + ?Sized>(&mut self, key: String, value: &T) -> Result<()> {
+        if let Some(tag) = value.serialize(Serializer)? {
+            self.compound.insert(key, tag);
+        }
+
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for SerializeCompound {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        self.key = Some(key.serialize(KeySerializer)?);
+
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| Error::Message("value serialized before its key".into()))?;
+
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        Ok(Some(Tag::Compound(self.compound)))
+    }
+}
+
+impl ser::SerializeStruct for SerializeCompound {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.insert(key.into(), value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        Ok(Some(Tag::Compound(self.compound)))
+    }
+}
+
+/// Wraps tuple and struct variants into a compound with the variant name as its only key.
+struct SerializeVariant<T> {
+    variant: &'static str,
+    inner: T,
+}
+
+impl ser::SerializeTupleVariant for SerializeVariant<SerializeList> {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.inner.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        Ok(Some(single(self.variant, list(self.inner.0)?)))
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeVariant<SerializeCompound> {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.inner.insert(key.into(), value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        Ok(Some(single(
+            self.variant,
+            Tag::Compound(self.inner.compound),
+        )))
+    }
+}
+
+/// Compound keys are strings, numbers and chars are written out.
+struct KeySerializer;
+
+fn key_error() -> Error {
+    Error::Message("compound keys must be strings".into())
+}
+
+impl ser::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = Error;
+
+    type SerializeSeq = Impossible<String, Error>;
+    type SerializeTuple = Impossible<String, Error>;
+    type SerializeTupleStruct = Impossible<String, Error>;
+    type SerializeTupleVariant = Impossible<String, Error>;
+    type SerializeMap = Impossible<String, Error>;
+    type SerializeStruct = Impossible<String, Error>;
+    type SerializeStructVariant = Impossible<String, Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<String> {
+        Err(key_error())
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<String> {
+        Err(key_error())
+    }
+
+    fn serialize_char(self, v: char) -> Result<String> {
+        Ok(v.into())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<String> {
+        Ok(v.into())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<String> {
+        Err(key_error())
+    }
+
+    fn serialize_none(self) -> Result<String> {
+        Err(key_error())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _value: &T) -> Result<String> {
+        Err(key_error())
+    }
+
+    fn serialize_unit(self) -> Result<String> {
+        Err(key_error())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String> {
+        Err(key_error())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<String> {
+        Ok(variant.into())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String> {
+        Err(key_error())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(key_error())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(key_error())
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(key_error())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(key_error())
+    }
+}
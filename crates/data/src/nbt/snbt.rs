@@ -0,0 +1,458 @@
+//! SNBT, the text form of tags like `{name:"maqo",count:3b,pos:[I;1,2,3]}`.
+
+use super::{Compound, Error, Result, Tag, MAX_DEPTH};
+use std::{fmt, str::FromStr};
+
+fn is_unquoted(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '+')
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, value: &str) -> fmt::Result {
+    f.write_str("\"")?;
+
+    for c in value.chars() {
+        match c {
+            '"' | '\\' => write!(f, "\\{c}")?,
+            _ => write!(f, "{c}")?,
+        }
+    }
+
+    f.write_str("\"")
+}
+
+fn write_key(f: &mut fmt::Formatter<'_>, key: &str) -> fmt::Result {
+    if !key.is_empty() && key.chars().all(is_unquoted) {
+        f.write_str(key)
+    } else {
+        write_string(f, key)
+    }
+}
+
+fn write_array<T: fmt::Display>(
+    f: &mut fmt::Formatter<'_>,
+    prefix: char,
+    suffix: &str,
+    values: &[T],
+) -> fmt::Result {
+    write!(f, "[{prefix};")?;
+
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            f.write_str(",")?;
+        }
+
+        write!(f, "{value}{suffix}")?;
+    }
+
+    f.write_str("]")
+}
+
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Byte(value) => write!(f, "{value}b"),
+            Self::Short(value) => write!(f, "{value}s"),
+            Self::Int(value) => write!(f, "{value}"),
+            Self::Long(value) => write!(f, "{value}L"),
+            Self::Float(value) => write!(f, "{value}f"),
+            Self::Double(value) => write!(f, "{value}d"),
+            Self::ByteArray(values) => write_array(f, 'B', "b", values),
+            Self::String(value) => write_string(f, value),
+            Self::List(tags) => {
+                f.write_str("[")?;
+
+                for (i, tag) in tags.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+
+                    tag.fmt(f)?;
+                }
+
+                f.write_str("]")
+            }
+            Self::Compound(compound) => {
+                f.write_str("{")?;
+
+                for (i, (key, tag)) in compound.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+
+                    write_key(f, key)?;
+                    f.write_str(":")?;
+                    tag.fmt(f)?;
+                }
+
+                f.write_str("}")
+            }
+            Self::IntArray(values) => write_array(f, 'I', "", values),
+            Self::LongArray(values) => write_array(f, 'L', "L", values),
+        }
+    }
+}
+
+impl FromStr for Tag {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parser = Parser {
+            input: s,
+            position: 0,
+            depth: 0,
+        };
+        let tag = parser.parse_tag()?;
+
+        parser.skip_whitespace();
+
+        if parser.position < s.len() {
+            return Err(parser.error("trailing data"));
+        }
+
+        Ok(tag)
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    position: usize,
+    /// Number of lists and compounds the parser is inside of.
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> Error {
+        Error::Syntax {
+            position: self.position,
+            message: message.into(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.position..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek().filter(|c| c.is_whitespace()) {
+            self.position += c.len_utf8();
+        }
+    }
+
+    /// Skips whitespace and consumes `expected` if it comes next.
+    fn eat(&mut self, expected: char) -> bool {
+        self.skip_whitespace();
+
+        if self.peek() == Some(expected) {
+            self.position += expected.len_utf8();
+
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<()> {
+        if self.eat(expected) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{expected}'")))
+        }
+    }
+
+    fn parse_quoted(&mut self, quote: char) -> Result<String> {
+        let mut value = String::new();
+
+        self.position += quote.len_utf8();
+
+        loop {
+            let c = self
+                .peek()
+                .ok_or_else(|| self.error("unterminated string"))?;
+
+            self.position += c.len_utf8();
+
+            match c {
+                '\\' => {
+                    let escaped = self
+                        .peek()
+                        .filter(|&c| c == '\\' || c == quote)
+                        .ok_or_else(|| self.error("invalid escape"))?;
+
+                    self.position += escaped.len_utf8();
+                    value.push(escaped);
+                }
+                c if c == quote => return Ok(value),
+                c => value.push(c),
+            }
+        }
+    }
+
+    fn parse_unquoted(&mut self) -> &str {
+        let start = self.position;
+
+        while let Some(c) = self.peek().filter(|&c| is_unquoted(c)) {
+            self.position += c.len_utf8();
+        }
+
+        &self.input[start..self.position]
+    }
+
+    fn parse_string(&mut self) -> Result<String> {
+        self.skip_whitespace();
+
+        match self.peek() {
+            Some(quote @ ('"' | '\'')) => self.parse_quoted(quote),
+            _ => {
+                let value = self.parse_unquoted();
+
+                if value.is_empty() {
+                    Err(self.error("expected a string"))
+                } else {
+                    Ok(value.into())
+                }
+            }
+        }
+    }
+
+    fn parse_tag(&mut self) -> Result<Tag> {
+        self.skip_whitespace();
+
+        match self.peek() {
+            Some(c @ ('{' | '[')) => {
+                // The root counts as 0, like in the binary form.
+                if self.depth > MAX_DEPTH {
+                    return Err(Error::TooDeep);
+                }
+
+                self.depth += 1;
+
+                let tag = if c == '{' {
+                    self.parse_compound()
+                } else {
+                    self.parse_list()
+                };
+
+                self.depth -= 1;
+
+                tag
+            }
+            Some(quote @ ('"' | '\'')) => self.parse_quoted(quote).map(Tag::String),
+            _ => {
+                let value = self.parse_unquoted();
+
+                if value.is_empty() {
+                    return Err(self.error("expected a value"));
+                }
+
+                Ok(parse_literal(value))
+            }
+        }
+    }
+
+    fn parse_compound(&mut self) -> Result<Tag> {
+        let mut compound = Compound::new();
+
+        self.expect('{')?;
+
+        if !self.eat('}') {
+            loop {
+                let key = self.parse_string()?;
+
+                self.expect(':')?;
+                compound.insert(key, self.parse_tag()?);
+
+                if self.eat('}') {
+                    break;
+                }
+
+                self.expect(',')?;
+            }
+        }
+
+        Ok(Tag::Compound(compound))
+    }
+
+    fn parse_list(&mut self) -> Result<Tag> {
+        self.expect('[')?;
+
+        let rest = &self.input[self.position..];
+
+        for (prefix, id) in [("B;", 1), ("I;", 3), ("L;", 4)] {
+            if rest.starts_with(prefix) {
+                self.position += prefix.len();
+
+                return self.parse_array(id);
+            }
+        }
+
+        let mut tags = Vec::new();
+
+        if !self.eat(']') {
+            loop {
+                let tag = self.parse_tag()?;
+
+                if tags
+                    .first()
+                    .is_some_and(|first: &Tag| first.id() != tag.id())
+                {
+                    return Err(self.error("list elements have different types"));
+                }
+
+                tags.push(tag);
+
+                if self.eat(']') {
+                    break;
+                }
+
+                self.expect(',')?;
+            }
+        }
+
+        Ok(Tag::List(tags))
+    }
+
+    fn parse_array(&mut self, id: u8) -> Result<Tag> {
+        let mut values = Vec::new();
+
+        if !self.eat(']') {
+            loop {
+                let tag = self.parse_tag()?;
+
+                // Arrays may hold smaller numbers, but nothing larger.
+                let value = match tag {
+                    Tag::Byte(_) | Tag::Short(_) | Tag::Int(_) if id >= tag.id() => tag.as_i64(),
+                    Tag::Long(value) if id == 4 => Some(value),
+                    _ => None,
+                }
+                .ok_or_else(|| self.error("invalid array element"))?;
+
+                values.push(value);
+
+                if self.eat(']') {
+                    break;
+                }
+
+                self.expect(',')?;
+            }
+        }
+
+        Ok(match id {
+            1 => Tag::ByteArray(values.into_iter().map(|value| value as i8).collect()),
+            3 => Tag::IntArray(values.into_iter().map(|value| value as i32).collect()),
+            _ => Tag::LongArray(values),
+        })
+    }
+}
+
+/// Parses a number with its type suffix or `true` and `false`, anything
+/// else is an unquoted string.
+fn parse_literal(value: &str) -> Tag {
+    match value {
+        "true" => return Tag::Byte(1),
+        "false" => return Tag::Byte(0),
+        _ => {}
+    }
+
+    let (number, suffix) = match value.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => (&value[..i], Some(c.to_ascii_lowercase())),
+        _ => (value, None),
+    };
+
+    let tag = match suffix {
+        Some('b') => number.parse().ok().map(Tag::Byte),
+        Some('s') => number.parse().ok().map(Tag::Short),
+        Some('l') => number.parse().ok().map(Tag::Long),
+        Some('f') => number.parse().ok().map(Tag::Float),
+        Some('d') => number.parse().ok().map(Tag::Double),
+        Some(_) => None,
+        None if number.contains(['.', 'e', 'E']) => number.parse().ok().map(Tag::Double),
+        None => number.parse().ok().map(Tag::Int),
+    };
+
+    // Other spellings of infinity and NaN are strings, only the ones floats
+    // are written with are read back.
+    let is_number = value.starts_with(|c: char| c.is_ascii_digit() || matches!(c, '-' | '+' | '.'))
+        || (matches!(number, "NaN" | "inf") && matches!(suffix, Some('f' | 'd')));
+
+    tag.filter(|_| is_number)
+        .unwrap_or_else(|| Tag::String(value.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses `snbt`, then parses what it prints as again.
+    fn round_trip(snbt: &str) -> Tag {
+        let tag: Tag = snbt.parse().unwrap();
+
+        assert_eq!(tag.to_string().parse::<Tag>().unwrap(), tag);
+
+        tag
+    }
+
+    #[test]
+    fn every_tag_round_trips() {
+        let tag = round_trip(
+            r#"{byte: 1b, short: -2s, int: 3, long: 4L, float: 0.5f, double: 1.5,
+                bytes: [B; 1b, -2b], ints: [I; 3, -4], longs: [L; 5L, -6L],
+                string: "say \"hi\" \\ there", list: [[], [{}]], "key with spaces": true}"#,
+        );
+
+        let Tag::Compound(compound) = tag else {
+            panic!("expected a compound");
+        };
+
+        assert_eq!(compound["byte"], Tag::Byte(1));
+        assert_eq!(compound["double"], Tag::Double(1.5));
+        assert_eq!(compound["longs"], Tag::LongArray(vec![5, -6]));
+        assert_eq!(
+            compound["string"],
+            Tag::String(r#"say "hi" \ there"#.into())
+        );
+        assert_eq!(compound["key with spaces"], Tag::Byte(1));
+    }
+
+    #[test]
+    fn unquoted_strings() {
+        assert_eq!(round_trip("stone"), Tag::String("stone".into()));
+        assert_eq!(round_trip("'single'"), Tag::String("single".into()));
+        assert_eq!(round_trip("Infinity"), Tag::String("Infinity".into()));
+        assert_eq!(round_trip("12x"), Tag::String("12x".into()));
+    }
+
+    #[test]
+    fn infinite_and_nan_floats_round_trip() {
+        assert_eq!(round_trip("inff"), Tag::Float(f32::INFINITY));
+        assert_eq!(round_trip("-infd"), Tag::Double(f64::NEG_INFINITY));
+
+        for tag in [Tag::Float(f32::NAN), Tag::Double(f64::NAN)] {
+            let parsed = tag.to_string().parse::<Tag>().unwrap();
+
+            assert!(parsed.as_f64().is_some_and(f64::is_nan));
+            assert_eq!(parsed.id(), tag.id());
+        }
+    }
+
+    #[test]
+    fn invalid_snbt_is_rejected() {
+        for snbt in ["{a:1", "[1, 2b]", "[I; 1L]", "{a 1}", "\"open", "1 2"] {
+            assert!(snbt.parse::<Tag>().is_err(), "{snbt}");
+        }
+    }
+
+    #[test]
+    fn deeply_nested_snbt_is_rejected() {
+        let nested = |depth| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+
+        assert!(nested(MAX_DEPTH + 1).parse::<Tag>().is_ok());
+        assert!(matches!(
+            nested(MAX_DEPTH + 2).parse::<Tag>(),
+            Err(Error::TooDeep)
+        ));
+        assert!(matches!(
+            "[".repeat(100_000).parse::<Tag>(),
+            Err(Error::TooDeep)
+        ));
+    }
+}
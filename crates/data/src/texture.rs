@@ -0,0 +1,53 @@
+use serde::Deserialize;
+
+/// Contents of the `.mcmeta` file next to a texture.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct TextureMeta {
+    pub animation: Option<Animation>,
+}
+
+/// Frames of an animated texture, stacked in its image from top to bottom.
+#[derive(Debug, Deserialize, Clone)]
+pub struct Animation {
+    /// Game ticks each frame is shown for, unless it has a time of its own.
+    #[serde(rename = "frametime", default = "Animation::default_frame_time")]
+    pub frame_time: u32,
+    /// Whether frames fade into the next one instead of switching at once.
+    #[serde(default)]
+    pub interpolate: bool,
+    /// Order of the frames, all of them from the top if there is none.
+    pub frames: Option<Vec<AnimationFrame>>,
+    /// Size of a frame in pixels, they are square by default.
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+impl Animation {
+    fn default_frame_time() -> u32 {
+        1
+    }
+}
+
+#[derive(Debug, Deserialize, Copy, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum AnimationFrame {
+    Index(u32),
+    Timed { index: u32, time: Option<u32> },
+}
+
+impl AnimationFrame {
+    pub fn index(self) -> u32 {
+        match self {
+            Self::Index(index) | Self::Timed { index, .. } => index,
+        }
+    }
+
+    /// Game ticks the frame is shown for, `frame_time` if it has no time of
+    /// its own.
+    pub fn time(self, frame_time: u32) -> u32 {
+        match self {
+            Self::Index(_) => frame_time,
+            Self::Timed { time, .. } => time.unwrap_or(frame_time),
+        }
+    }
+}
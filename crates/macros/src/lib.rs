@@ -7,11 +7,11 @@ use syn::{
     parse::{Parse, Parser},
     parse_macro_input,
     punctuated::Punctuated,
-    Data, DeriveInput, Ident, LitBool, LitInt, Meta, Token,
+    Data, DeriveInput, Expr, LitBool, LitInt, Meta, Token,
 };
 
-#[proc_macro_derive(Block, attributes(block, tint, prop))]
-pub fn derive_block(input: TokenStream) -> TokenStream {
+#[proc_macro_derive(BlockProperties, attributes(block, tint, prop))]
+pub fn derive_block_properties(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
     let mut attr = input
@@ -50,13 +50,13 @@ pub fn derive_block(input: TokenStream) -> TokenStream {
         .filter_map(|attr| match attr.meta {
             Meta::List(meta) => {
                 if meta.path.is_ident("tint") {
-                    if let Ok(ident) = Ident::parse.parse2(meta.tokens.clone()) {
-                        Some(ident.into_token_stream())
+                    if let Ok(value) = LitInt::parse.parse2(meta.tokens.clone()) {
+                        Some(quote! { Tint::Color(Color::from_hex(#value)) })
                     } else {
-                        LitInt::parse
+                        Expr::parse
                             .parse2(meta.tokens)
                             .ok()
-                            .map(|value| quote! { Color::from_hex(#value) })
+                            .map(|expr| expr.into_token_stream())
                     }
                 } else {
                     None
@@ -116,7 +116,6 @@ pub fn derive_block(input: TokenStream) -> TokenStream {
             .unwrap_or_default();
 
         let name = ident.to_string();
-        let ty = field.ty;
 
         if let Some(default) = attr.remove("default") {
             properties.push(quote! {
@@ -124,7 +123,7 @@ pub fn derive_block(input: TokenStream) -> TokenStream {
             });
         } else {
             properties.push(quote! {
-                (#name.into(), #ty ::default().into())
+                (#name.into(), self.#ident.clone().into())
             });
         }
     }
@@ -184,7 +183,7 @@ pub fn derive_block(input: TokenStream) -> TokenStream {
     } else {
         quote! {
             HashMap::from_iter([
-                #(#properties)*,
+                #(#properties),*
             ])
         }
     };
@@ -193,14 +192,14 @@ pub fn derive_block(input: TokenStream) -> TokenStream {
         TokenStream2::new()
     } else {
         quote! {
-            fn tints(&self) -> Vec<Color> {
-                vec![#(#tints)*,]
+            fn tints(&self) -> Vec<Tint> {
+                vec![#(#tints),*]
             }
         }
     };
 
     quote! {
-        impl Block for #name {
+        impl BlockProperties for #name {
             fn properties(&self) -> HashMap<String, Property> {
                 #properties
             }